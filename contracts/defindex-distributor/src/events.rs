@@ -1,9 +1,14 @@
-use soroban_sdk::{contractevent, Address};
+use soroban_sdk::{contractevent, Address, BytesN};
+
+use crate::RejectReason;
 
 /// Emitted once per recipient after their df tokens are transferred.
 ///
 /// - topics - `["distributed"]`
-/// - data   - `[asset: Address, vault: Address, user: Address, underlying_amount: i128, df_tokens: i128]`
+/// - data   - `[asset: Address, vault: Address, user: Address, underlying_amount: i128, df_tokens: i128, seq: u64]`
+///
+/// `seq` increases by one per `Distributed` event across all distributions,
+/// so indexers can detect gaps and ordering.
 #[contractevent(topics = ["distributed"])]
 pub struct Distributed {
     pub asset: Address,
@@ -11,4 +16,247 @@ pub struct Distributed {
     pub user: Address,
     pub underlying_amount: i128,
     pub df_tokens: i128,
+    pub seq: u64,
+}
+
+/// Emitted for each row a best-effort distribution skips instead of
+/// panicking, e.g. a zero amount under `skip_zero`.
+///
+/// - topics - `["recipient_rejected"]`
+/// - data   - `[index: u32, user: Address, reason: RejectReason]`
+///
+/// `index` is the row's position in the caller's input list.
+#[contractevent(topics = ["recipient_rejected"])]
+pub struct RecipientRejected {
+    pub index: u32,
+    pub user: Address,
+    pub reason: RejectReason,
+}
+
+/// Emitted once at the end of every distribution, whichever entry point
+/// made it, summarising the batch.
+///
+/// - topics - `["distribution_batch"]`
+/// - data   - `[asset: Address, vault: Address, recipients: u32, total_underlying: i128, df_tokens: i128, batch_id: BytesN<32>]`
+///
+/// `batch_id` is `sha256(xdr(caller, asset, vault, recipients, nonce))` with
+/// a contract-wide nonce, so identical calls still get distinct ids.  Escrow
+/// distributions use their escrow batch id instead.
+#[contractevent(topics = ["distribution_batch"])]
+pub struct DistributionBatch {
+    pub asset: Address,
+    pub vault: Address,
+    pub recipients: u32,
+    pub total_underlying: i128,
+    pub df_tokens: i128,
+    pub batch_id: BytesN<32>,
+}
+
+/// Emitted right after `DistributionBatch` as a cost receipt for the batch.
+///
+/// - topics - `["distribution_cost"]`
+/// - data   - `[vault: Address, recipients: u32, unit_cost: u64, estimated_instructions: u64]`
+///
+/// `unit_cost` is the contract's fixed per-recipient instruction estimate;
+/// `estimated_instructions = recipients * unit_cost`.
+#[contractevent(topics = ["distribution_cost"])]
+pub struct DistributionCost {
+    pub vault: Address,
+    pub recipients: u32,
+    pub unit_cost: u64,
+    pub estimated_instructions: u64,
+}
+
+/// Emitted when a distribution rebates part of the minted df tokens to the
+/// caller or its referral address.
+///
+/// - topics - `["referral_rebate"]`
+/// - data   - `[vault: Address, to: Address, df_tokens: i128]`
+#[contractevent(topics = ["referral_rebate"])]
+pub struct ReferralRebate {
+    pub vault: Address,
+    pub to: Address,
+    pub df_tokens: i128,
+}
+
+/// Emitted when `distribute_with_reserve` pays the reserved slice of the
+/// minted df tokens.
+///
+/// - topics - `["reserved"]`
+/// - data   - `[vault: Address, to: Address, df_tokens: i128]`
+#[contractevent(topics = ["reserved"])]
+pub struct Reserved {
+    pub vault: Address,
+    pub to: Address,
+    pub df_tokens: i128,
+}
+
+/// Emitted when a distribution withholds the underlying fee set by
+/// `set_underlying_fee` before depositing.
+///
+/// - topics - `["underlying_fee"]`
+/// - data   - `[asset: Address, to: Address, amount: i128]`
+#[contractevent(topics = ["underlying_fee"])]
+pub struct UnderlyingFee {
+    pub asset: Address,
+    pub to: Address,
+    pub amount: i128,
+}
+
+/// Emitted when a distribution's post-distribution hook fails and the caller
+/// chose to tolerate the failure.
+///
+/// - topics - `["hook_failed"]`
+/// - data   - `[hook: Address, vault: Address]`
+#[contractevent(topics = ["hook_failed"])]
+pub struct HookFailed {
+    pub hook: Address,
+    pub vault: Address,
+}
+
+/// Emitted once per recipient when their df tokens are credited to an escrow batch.
+///
+/// - topics - `["escrowed"]`
+/// - data   - `[batch_id: BytesN<32>, vault: Address, user: Address, underlying_amount: i128, df_tokens: i128]`
+#[contractevent(topics = ["escrowed"])]
+pub struct Escrowed {
+    pub batch_id: BytesN<32>,
+    pub vault: Address,
+    pub user: Address,
+    pub underlying_amount: i128,
+    pub df_tokens: i128,
+}
+
+/// Emitted when a recipient claims their escrowed df tokens.
+///
+/// - topics - `["claimed"]`
+/// - data   - `[batch_id: BytesN<32>, vault: Address, user: Address, df_tokens: i128]`
+#[contractevent(topics = ["claimed"])]
+pub struct Claimed {
+    pub batch_id: BytesN<32>,
+    pub vault: Address,
+    pub user: Address,
+    pub df_tokens: i128,
+}
+
+/// Emitted when the batch caller voids an escrow batch before any claim.
+///
+/// - topics - `["canceled"]`
+/// - data   - `[batch_id: BytesN<32>, vault: Address, caller: Address, df_tokens: i128]`
+#[contractevent(topics = ["canceled"])]
+pub struct Canceled {
+    pub batch_id: BytesN<32>,
+    pub vault: Address,
+    pub caller: Address,
+    pub df_tokens: i128,
+}
+
+/// Emitted when the batch caller reclaims unclaimed allocations after expiry.
+///
+/// - topics - `["reclaimed"]`
+/// - data   - `[batch_id: BytesN<32>, vault: Address, caller: Address, df_tokens: i128, underlying: i128]`
+///
+/// `underlying` is the amount redeemed through the vault, or 0 when the df
+/// tokens were returned as-is.
+#[contractevent(topics = ["reclaimed"])]
+pub struct Reclaimed {
+    pub batch_id: BytesN<32>,
+    pub vault: Address,
+    pub caller: Address,
+    pub df_tokens: i128,
+    pub underlying: i128,
+}
+
+/// Emitted when the admin moves unclaimed escrow from one address to another.
+///
+/// - topics - `["reassigned"]`
+/// - data   - `[vault: Address, from: Address, to: Address, df_tokens: i128]`
+#[contractevent(topics = ["reassigned"])]
+pub struct Reassigned {
+    pub vault: Address,
+    pub from: Address,
+    pub to: Address,
+    pub df_tokens: i128,
+}
+
+/// Emitted when `distribute_with_swap` converts the reward asset into the
+/// vault's underlying.
+///
+/// - topics - `["swapped"]`
+/// - data   - `[reward_asset: Address, asset: Address, amount_in: i128, amount_out: i128]`
+#[contractevent(topics = ["swapped"])]
+pub struct Swapped {
+    pub reward_asset: Address,
+    pub asset: Address,
+    pub amount_in: i128,
+    pub amount_out: i128,
+}
+
+/// Emitted when `distribute_with_harvest` harvests the strategy before
+/// distributing.
+///
+/// - topics - `["harvested"]`
+/// - data   - `[strategy: Address, pool: Address, pending: i128]`
+///
+/// `pending` is the BLND the strategy had accrued in the Blend `pool` when
+/// it triggered the harvest.
+#[contractevent(topics = ["harvested"])]
+pub struct Harvested {
+    pub strategy: Address,
+    pub pool: Address,
+    pub pending: i128,
+}
+
+/// Emitted when `distribute_capped_underlying` routes the df tokens clamped
+/// off capped recipients to the overflow address.
+///
+/// - topics - `["overflow"]`
+/// - data   - `[asset: Address, vault: Address, to: Address, df_tokens: i128]`
+#[contractevent(topics = ["overflow"])]
+pub struct Overflowed {
+    pub asset: Address,
+    pub vault: Address,
+    pub to: Address,
+    pub df_tokens: i128,
+}
+
+/// Emitted once per distribution to quantify the underlying value lost to
+/// floor rounding in share minting and splitting.
+///
+/// - topics - `["rounding_loss"]`
+/// - data   - `[vault: Address, asset: Address, deposited: i128, recoverable: i128]`
+///
+/// `recoverable` is the sum of every recipient's df tokens valued in
+/// underlying at the post-deposit share price.
+#[contractevent(topics = ["rounding_loss"])]
+pub struct RoundingLoss {
+    pub vault: Address,
+    pub asset: Address,
+    pub deposited: i128,
+    pub recoverable: i128,
+}
+
+/// Emitted when the admin redeems contract-held df tokens via `emergency_drain`.
+///
+/// - topics - `["emergency_drain"]`
+/// - data   - `[vault: Address, to: Address, df_tokens: i128, underlying: i128, forced: bool]`
+#[contractevent(topics = ["emergency_drain"])]
+pub struct EmergencyDrain {
+    pub vault: Address,
+    pub to: Address,
+    pub df_tokens: i128,
+    pub underlying: i128,
+    pub forced: bool,
+}
+
+/// Emitted when `reconcile` redeems a caller's residual df tokens.
+///
+/// - topics - `["reconciled"]`
+/// - data   - `[caller: Address, vault: Address, df_tokens: i128, underlying: i128]`
+#[contractevent(topics = ["reconciled"])]
+pub struct Reconciled {
+    pub caller: Address,
+    pub vault: Address,
+    pub df_tokens: i128,
+    pub underlying: i128,
 }
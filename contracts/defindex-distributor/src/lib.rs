@@ -1,13 +1,23 @@
 #![no_std]
+// Soroban entry points (and the clients generated for them and for imported
+// contracts) routinely take more than clippy's default of seven arguments.
+#![allow(clippy::too_many_arguments)]
 use soroban_fixed_point_math::SorobanFixedPoint;
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token::TokenClient, vec, Address, Env, Map, Vec,
+    contract, contractimpl, contracttype,
+    token::{StellarAssetClient, TokenClient},
+    vec, Address, Bytes, BytesN, Env, Map, String, Vec,
 };
 use soroban_sdk::auth::InvokerContractAuthEntry;
 use soroban_sdk::auth::SubContractInvocation;
 use soroban_sdk::auth::ContractContext;
 use soroban_sdk::Symbol;
 use soroban_sdk::IntoVal;
+use soroban_sdk::xdr::{FromXdr, ToXdr};
+use storage::{
+    ChunkedDistribution, DistributionReceipt, DistributionRecord, DistributionSummary,
+    EscrowBatch, TtlParams, UnderlyingFeeConfig,
+};
 
 // Generated client for the defindex vault (deposit + SAC df token interface).
 // The WASM is a pre-built external binary; Cargo dependency tracking and the
@@ -19,10 +29,141 @@ mod vault {
     );
 }
 
+// Generated client for the Soroswap router (multi-hop exact-in swaps).
+#[allow(unknown_lints, contract_import_dependency)]
+mod soroswap_router {
+    soroban_sdk::contractimport!(
+        file = "external_wasms/soroswap_router.wasm"
+    );
+}
+
+// Generated client for a Blend lending pool, to read the BLND emissions a
+// Blend strategy has accrued but not yet claimed.
+#[allow(unknown_lints, contract_import_dependency)]
+mod blend_pool {
+    soroban_sdk::contractimport!(
+        file = "external_wasms/pool.wasm"
+    );
+}
+
+// Interface of a staking contract (e.g. a gauge) that df tokens can be
+// auto-staked into on a recipient's behalf.
+mod stake {
+    use soroban_sdk::{contractclient, Address, Env};
+
+    #[allow(dead_code)]
+    #[contractclient(name = "StakeClient")]
+    pub trait Stake {
+        /// Credits `user` with `amount` df tokens already transferred to the
+        /// staking contract.
+        fn stake(e: Env, user: Address, amount: i128);
+    }
+}
+
+// Interface of a contract notified after a distribution completes, e.g. a
+// registry mirroring distributions for off-chain consumers.
+mod hook {
+    use soroban_sdk::{contractclient, Address, Env};
+
+    #[allow(dead_code)]
+    #[contractclient(name = "HookClient")]
+    pub trait DistributionHook {
+        /// Called once all of a distribution's df tokens have been paid out.
+        fn on_distributed(
+            e: Env,
+            vault: Address,
+            total: i128,
+            df_minted: i128,
+            recipient_count: u32,
+        );
+    }
+}
+
+// Interface of a DeFindex strategy that accrues reward tokens and can
+// reinvest them, e.g. the Blend strategy.
+mod strategy {
+    use soroban_sdk::{contractclient, Address, Bytes, Env};
+
+    #[allow(dead_code)]
+    #[contractclient(name = "StrategyClient")]
+    pub trait Strategy {
+        /// The underlying asset the strategy invests.
+        fn asset(e: Env) -> Address;
+
+        /// Claims the strategy's rewards and reinvests them into its
+        /// position.  `from` must be the strategy's keeper.
+        fn harvest(e: Env, from: Address, data: Option<Bytes>);
+    }
+}
+
+/// Contract ABI version reported by [`Distributor::version`].  Bump it with
+/// every change to the public entry points or their types.
+pub const CONTRACT_VERSION: u32 = 50;
+
+/// Maximum number of addresses accepted by batched read-only queries.
+pub const MAX_QUERY_LEN: u32 = 100;
+
+/// Distribution summaries kept by [`Distributor::audit_log`]; older entries
+/// are overwritten in place, bounding the log's storage.
+pub const AUDIT_LOG_CAPACITY: u32 = 128;
+
+/// Largest list [`Distributor::distribute_auto`] pays in one transaction, and
+/// the size of each [`Distributor::distribute_chunk`].  One `Distributed`
+/// event is about 370 bytes, so 40 keeps a call under the network's 16 KiB
+/// per-transaction event limit with room for the batch summary events.
+pub const MAX_RECIPIENTS_PER_TX: u32 = 40;
+
+// Chunk bounds are computed as `(chunk + 1) * MAX_RECIPIENTS_PER_TX` in u32;
+// a small cap keeps that far from overflow for any list a ledger can hold.
+const _: () = assert!(MAX_RECIPIENTS_PER_TX > 0 && MAX_RECIPIENTS_PER_TX <= u16::MAX as u32);
+
+/// Most escrow allocations [`Distributor::claim_all`] releases in one call.
+/// Each emits a `Claimed` event, smaller than a `Distributed` one, so the
+/// per-transaction recipient bound is safe here too.
+pub const MAX_CLAIMS_PER_TX: u32 = MAX_RECIPIENTS_PER_TX;
+
+/// Default for [`Distributor::set_max_vaults_per_call`]: most distinct
+/// vaults one multi-vault call may deposit into.  Each vault adds a deposit
+/// and its batch summary events on top of the per-recipient cost.
+pub const DEFAULT_MAX_VAULTS_PER_CALL: u32 = 10;
+
+/// Estimated CPU instructions one recipient adds to a distribution (share
+/// transfer plus its `Distributed` event), reported in `DistributionCost`
+/// events for billing.  Contracts cannot read the metered cost on-chain.
+pub const INSTRUCTIONS_PER_RECIPIENT: u64 = 150_000;
+
+/// Basis-point denominator: 10_000 bps = 100%.
+pub const BPS_DENOMINATOR: i128 = 10_000;
+
+/// Largest referral rebate, in basis points of the minted df tokens,
+/// accepted by [`Distributor::set_referral_rebate`].
+pub const MAX_REFERRAL_REBATE_BPS: u32 = 1_000;
+
+/// Largest underlying fee, in basis points of the distributed total,
+/// accepted by [`Distributor::set_underlying_fee`].
+pub const MAX_UNDERLYING_FEE_BPS: u32 = 1_000;
+
+/// Shares priced by the post-deposit sanity probe: one whole df token at the
+/// vault's 7 decimals.
+pub const PRICE_PROBE_SHARES: i128 = 10_000_000;
+
+/// Strkey of the all-zero ed25519 account, a common placeholder for "no
+/// address" in off-chain tooling.  No one holds its key.
+pub const ZERO_ACCOUNT: &str = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF";
+
+/// Strkey of the all-zero contract id, the contract counterpart of
+/// [`ZERO_ACCOUNT`].
+pub const ZERO_CONTRACT: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABSC4";
+
 /// A single recipient entry passed to [`Distributor::distribute`].
 ///
 /// Defining this as a `#[contracttype]` ensures the Vec parameter is composed
 /// of validated, contract-defined types rather than raw tuples.
+///
+/// `address` must not be [`ZERO_ACCOUNT`], [`ZERO_CONTRACT`] or this
+/// contract: shares sent to the placeholders are burned, and shares sent to
+/// the distributor are stranded.  Such a recipient panics with
+/// `"recipient {i} address is invalid"`.
 #[contracttype]
 #[derive(Clone)]
 pub struct Recipient {
@@ -30,161 +171,4424 @@ pub struct Recipient {
     pub amount: i128,
 }
 
+/// Why a row was skipped by a best-effort distribution, reported in
+/// `RecipientRejected` events.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum RejectReason {
+    /// Zero amount, dropped under `skip_zero`.
+    ZeroAmount = 1,
+    /// The df-token transfer to the recipient failed; their shares were
+    /// refunded to the caller.
+    TransferFailed = 2,
+    /// The df token reported the recipient as not authorised to hold it
+    /// (e.g. frozen), so no transfer was attempted and their shares were
+    /// refunded to the caller.
+    Ineligible = 3,
+    /// The recipient already held the best-effort call's `max_balance` of
+    /// df tokens, so their shares were refunded to the caller.
+    BalanceCap = 4,
+}
+
+/// A member of a group passed to [`Distributor::distribute_nested`], owning
+/// `bps` basis points of the group's total.
+#[contracttype]
+#[derive(Clone)]
+pub struct RecipientBps {
+    pub address: Address,
+    pub bps: u32,
+}
+
+/// Outcome of [`Distributor::distribute_auto`].
+///
+/// - `results`        - `(address, df_tokens)` paid in this call (empty when
+///                      the list was chunked)
+/// - `batch_id`       - id for the follow-up chunks, the same id as the
+///                      `DistributionBatch` event's (None if all paid)
+/// - `pending_chunks` - chunk indices to pass to `distribute_chunk`
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoDistribution {
+    pub results: Vec<(Address, i128)>,
+    pub batch_id: Option<BytesN<32>>,
+    pub pending_chunks: Vec<u32>,
+}
+
+/// Contract-wide configuration returned by [`Distributor::get_config`].
+///
+/// - `admin`                 - account allowed to call the admin setters
+/// - `paused`                - global pause flag
+/// - `ttl_params`            - instance-TTL extension parameters
+/// - `min_amount`            - per-recipient minimum amount (0 = none)
+/// - `min_recipients`        - fewest recipients per distribution
+/// - `max_recipients_per_tx` - [`MAX_RECIPIENTS_PER_TX`]
+/// - `max_vaults_per_call`   - most distinct vaults per multi-vault call
+/// - `escrow_expiry`         - ledgers until new escrow batches expire (0 = never)
+/// - `referral_rebate_bps`   - referral rebate off each mint, in bps
+/// - `underlying_fee_to`     - receives the underlying fee (None = no fee)
+/// - `underlying_fee_bps`    - underlying fee off each total, in bps
+///
+/// Per-asset and per-vault settings (asset pauses, escrow caps, max
+/// deposits, price bounds, accounting tolerances) have their own getters.
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributorConfig {
+    pub admin: Address,
+    pub paused: bool,
+    pub ttl_params: TtlParams,
+    pub min_amount: i128,
+    pub min_recipients: u32,
+    pub max_recipients_per_tx: u32,
+    pub max_vaults_per_call: u32,
+    pub escrow_expiry: u32,
+    pub referral_rebate_bps: u32,
+    pub underlying_fee_to: Option<Address>,
+    pub underlying_fee_bps: u32,
+}
+
+/// Optional behaviour switches for [`Distributor::distribute_with_options`].
+///
+/// `Default` reproduces plain [`Distributor::distribute`].
+#[contracttype]
+#[derive(Clone, PartialEq)]
+pub struct DistributeOptions {
+    /// Recipients are sorted by strictly ascending address, so duplicates are
+    /// detected by comparing neighbours (O(n)) instead of building a `Map`.
+    /// Ordering is still verified; an unsorted list panics.
+    pub assume_sorted: bool,
+    /// Caller-declared sum of recipient amounts.  When set, a mismatch with
+    /// the computed total panics before anything is deposited, catching
+    /// off-chain list-assembly bugs.
+    pub expected_total: Option<i128>,
+    /// Emit a `Distributed` event per recipient (default).  When false only
+    /// the per-batch summary events (`RoundingLoss`, `DistributionBatch`) are
+    /// emitted, trading indexer granularity for event-size headroom on large
+    /// batches.
+    pub emit_per_recipient: bool,
+    /// Drop zero-amount rows (e.g. placeholders) instead of panicking;
+    /// negative amounts are still rejected.  The split and the remainder
+    /// rule run over the remaining rows, so the last *non-zero* recipient
+    /// absorbs the rounding remainder.  Skipped rows receive nothing, emit
+    /// no event, and appear in the results as `(address, 0)` to keep them
+    /// index-aligned with the input; each also emits a `RecipientRejected`
+    /// event.  A list with no non-zero row panics rather than depositing a
+    /// zero total.
+    pub skip_zero: bool,
+    /// Auto-stake each recipient's shares into this contract: the df tokens
+    /// are transferred to it and `stake(user, amount)` credits the recipient.
+    /// Recipients who opted out via [`Distributor::set_stake_opt_out`]
+    /// receive their shares directly.
+    pub stake_contract: Option<Address>,
+    /// Accept a vault whose minted shares are worth less than the deposit
+    /// (default).  The pro-rata split stays proportional either way; when
+    /// false, the distribution panics if the minted df tokens redeem for
+    /// less than `total` by more than the deposit's own rounding (less than
+    /// one share), so callers never distribute into a loss-making vault.
+    pub allow_loss: bool,
+    /// Contract holding the vault's shares, for vault designs that keep the
+    /// share token separate from the vault logic.  Deposits and valuation
+    /// still go through the vault; only the share transfers use this token.
+    /// `None` (default) means the vault is its own share token.
+    pub share_token: Option<Address>,
+    /// Receives the referral rebate (see [`Distributor::set_referral_rebate`])
+    /// instead of the caller, e.g. the front-end that assembled the call.
+    pub referral: Option<Address>,
+    /// Give the rounding remainder to the recipient with the largest address
+    /// instead of the last one, so the same recipients receive the same
+    /// shares whatever order they are listed in.
+    pub remainder_to_largest_address: bool,
+    /// Contract whose `on_distributed(vault, total, df_minted,
+    /// recipient_count)` is called after every transfer of the distribution.
+    pub hook: Option<Address>,
+    /// Tolerate a failing `hook`: its failure is reported in a `HookFailed`
+    /// event and the distribution stands.  By default (false) a failing hook
+    /// reverts the whole distribution.
+    pub hook_failure_ok: bool,
+    /// Survive a vault whose valuation query (`total_supply` /
+    /// `get_asset_amounts_per_shares`) reverts, e.g. on an internal cap.  The
+    /// split never depends on it; only the `RoundingLoss` report does, and it
+    /// falls back to pricing shares at this deposit's own rate,
+    /// `total / df_tokens_minted`.  That local price ignores the vault's
+    /// redemption rounding and any difference between the deposit rate and
+    /// the vault-wide rate, so the reported `recoverable` is then only an
+    /// estimate.  `allow_loss = false` still needs a working valuation.
+    pub fallback_valuation: bool,
+    /// Round every recipient's df tokens except the remainder absorber's down
+    /// to a multiple of this, for systems that need round lots; the absorber
+    /// takes what the rounding frees up.  Must be positive (default 1).
+    pub share_increment: i128,
+    /// Tightens the post-condition on the split.  The remainder absorber's
+    /// share may always exceed its own floored pro-rata share by at most one
+    /// df token per other recipient, since consistent floors leave no more,
+    /// so a larger remainder means a math bug and the distribution panics.
+    /// `Some(bound)` also fails a remainder above `bound`; a looser bound has
+    /// no effect.  Checked before `share_increment` rounding.
+    pub max_dust: Option<i128>,
+    /// Floor every recipient's share, then hand the rounding remainder out
+    /// one df token each to the first `remainder` recipients in list order,
+    /// instead of giving it all to one absorber.  The shares still sum to the
+    /// split pool.  Cannot be combined with `remainder_to_largest_address` or
+    /// a `share_increment` above 1.
+    pub spread_remainder: bool,
+    /// Per-account df-token balance cap, for share tokens that refuse to
+    /// credit an account past a limit.  Before any transfer, each recipient's
+    /// current share-token `balance` plus their share is checked against it,
+    /// and the distribution panics cleanly instead of reverting inside the
+    /// token.  The cap is supplied by the caller: neither the DeFindex vault
+    /// nor the SAC interface reports one.  `None` (default) skips the check.
+    pub max_balance: Option<i128>,
+    /// Safety rail against fat-fingered lists: the batch panics before
+    /// depositing if any recipient's amount exceeds this many basis points
+    /// of the total.  At most [`BPS_DENOMINATOR`].  `None` (default) skips
+    /// the check.
+    pub max_single_bps: Option<u32>,
+}
+
+impl Default for DistributeOptions {
+    fn default() -> Self {
+        DistributeOptions {
+            assume_sorted: false,
+            expected_total: None,
+            emit_per_recipient: true,
+            skip_zero: false,
+            stake_contract: None,
+            allow_loss: true,
+            share_token: None,
+            referral: None,
+            remainder_to_largest_address: false,
+            hook: None,
+            hook_failure_ok: false,
+            fallback_valuation: false,
+            share_increment: 1,
+            max_dust: None,
+            spread_remainder: false,
+            max_balance: None,
+            max_single_bps: None,
+        }
+    }
+}
+
 #[contract]
 pub struct Distributor;
 
 #[contractimpl]
 impl Distributor {
-    /// Deposits the sum of all recipient amounts into a defindex vault on behalf
-    /// of `caller`, then distributes the minted df tokens back to each recipient
-    /// pro-rata (floor).  The last recipient absorbs any remainder from rounding.
+    /// Stores the `admin` allowed to tune the contract's operational parameters.
+    pub fn __constructor(e: Env, admin: Address) {
+        storage::set_admin(&e, &admin);
+    }
+
+    /// Returns the admin address set at deployment.
+    pub fn get_admin(e: Env) -> Address {
+        storage::get_admin(&e)
+    }
+
+    /// Returns [`CONTRACT_VERSION`], so clients can feature-detect entry
+    /// points before calling them.
+    pub fn version() -> u32 {
+        CONTRACT_VERSION
+    }
+
+    /// Returns every contract-wide admin-set parameter and flag in one call.
+    pub fn get_config(e: Env) -> DistributorConfig {
+        let fee = storage::get_underlying_fee(&e);
+        DistributorConfig {
+            admin: storage::get_admin(&e),
+            paused: storage::is_paused(&e),
+            ttl_params: storage::get_ttl_params(&e),
+            min_amount: storage::get_min_amount(&e),
+            min_recipients: storage::get_min_recipients(&e),
+            max_recipients_per_tx: MAX_RECIPIENTS_PER_TX,
+            max_vaults_per_call: storage::get_max_vaults_per_call(&e),
+            escrow_expiry: storage::get_escrow_expiry(&e),
+            referral_rebate_bps: storage::get_referral_rebate(&e),
+            underlying_fee_bps: fee.as_ref().map_or(0, |f| f.bps),
+            underlying_fee_to: fee.map(|f| f.to),
+        }
+    }
+
+    /// Names the distribution entry points this build supports.
+    pub fn supported_modes(e: Env) -> Vec<Symbol> {
+        let mut modes: Vec<Symbol> = vec![&e];
+        for mode in [
+            "distribute",
+            "distribute_with_id",
+            "distribute_best_effort",
+            "distribute_tuples",
+            "distribute_from_keys",
+            "distribute_nested",
+            "distribute_balance_bps",
+            "distribute_set",
+            "distribute_with_options",
+            "distribute_mixed",
+            "distribute_with_change",
+            "repeat_last",
+            "distribute_at_rate",
+            "distribute_direct",
+            "distribute_from_holder",
+            "distribute_tranched",
+            "distribute_by_tenure",
+            "distribute_quadratic",
+            "distribute_with_reserve",
+            "distribute_with_bonus",
+            "distribute_from_budget",
+            "distribute_to_target",
+            "distribute_capped_underlying",
+            "distribute_with_swap",
+            "distribute_with_harvest",
+            "reveal_and_distribute",
+            "distribute_auto",
+            "distribute_escrow",
+            "offer_distribution",
+        ] {
+            modes.push_back(Symbol::new(&e, mode));
+        }
+        modes
+    }
+
+    /// Sets the instance-TTL extension parameters used by `distribute`.
     ///
-    /// Returns `[(user, df_tokens_received)]` in the same order as `recipients`.
+    /// `threshold` must be non-zero and not greater than `extend_to`, which in
+    /// turn must not exceed the network's maximum entry TTL.
+    pub fn set_ttl_params(e: Env, admin: Address, threshold: u32, extend_to: u32) {
+        storage::require_admin(&e, &admin);
+        if threshold == 0 || threshold > extend_to {
+            panic!("ttl threshold must be in 1..=extend_to");
+        }
+        if extend_to > e.storage().max_ttl() {
+            panic!("ttl extend_to exceeds max ttl");
+        }
+        storage::set_ttl_params(&e, &TtlParams { threshold, extend_to });
+    }
+
+    /// Returns the instance-TTL extension parameters (defaults if never set).
+    pub fn get_ttl_params(e: Env) -> TtlParams {
+        storage::get_ttl_params(&e)
+    }
+
+    /// Sets the minimum amount any single recipient may be allocated.  Amounts
+    /// below the vault's share granularity would floor to zero df tokens;
+    /// this rejects them before the deposit.  `0` disables the check.
+    pub fn set_min_amount(e: Env, admin: Address, min_amount: i128) {
+        storage::require_admin(&e, &admin);
+        if min_amount < 0 {
+            panic!("min_amount must not be negative");
+        }
+        storage::set_min_amount(&e, min_amount);
+    }
+
+    /// Returns the per-recipient minimum amount (0 if never set).
+    pub fn get_min_amount(e: Env) -> i128 {
+        storage::get_min_amount(&e)
+    }
+
+    /// Sets the fewest recipients a distribution may have, e.g. 2 to require
+    /// that a split has at least two parties.  Must be at least 1.
+    pub fn set_min_recipients(e: Env, admin: Address, min_recipients: u32) {
+        storage::require_admin(&e, &admin);
+        if min_recipients == 0 {
+            panic!("min_recipients must be at least 1");
+        }
+        storage::set_min_recipients(&e, min_recipients);
+    }
+
+    /// Returns the minimum recipient count (1 if never set).
+    pub fn get_min_recipients(e: Env) -> u32 {
+        storage::get_min_recipients(&e)
+    }
+
+    /// Sets the most distinct vaults a multi-vault call such as
+    /// `distribute_mixed` may touch, bounding its instruction budget.  Must
+    /// be at least 1.
+    pub fn set_max_vaults_per_call(e: Env, admin: Address, max_vaults: u32) {
+        storage::require_admin(&e, &admin);
+        if max_vaults == 0 {
+            panic!("max_vaults must be at least 1");
+        }
+        storage::set_max_vaults_per_call(&e, max_vaults);
+    }
+
+    /// Returns the per-call vault cap ([`DEFAULT_MAX_VAULTS_PER_CALL`] if
+    /// never set).
+    pub fn get_max_vaults_per_call(e: Env) -> u32 {
+        storage::get_max_vaults_per_call(&e)
+    }
+
+    /// Sets how many ledgers after creation new escrow batches expire, after
+    /// which their caller may `reclaim` unclaimed allocations.  `0` (the
+    /// default) means batches never expire.
+    pub fn set_escrow_expiry(e: Env, admin: Address, ledgers: u32) {
+        storage::require_admin(&e, &admin);
+        storage::set_escrow_expiry(&e, ledgers);
+    }
+
+    /// Returns the escrow expiry period in ledgers (0 if never set).
+    pub fn get_escrow_expiry(e: Env) -> u32 {
+        storage::get_escrow_expiry(&e)
+    }
+
+    /// Caps the total escrow this contract may hold for `asset`, valued in
+    /// underlying at current vault prices.  Escrow-creating calls that would
+    /// push the held value above `amount` panic.
+    pub fn set_max_escrow(e: Env, admin: Address, asset: Address, amount: i128) {
+        storage::require_admin(&e, &admin);
+        if amount < 0 {
+            panic!("max escrow must not be negative");
+        }
+        storage::set_escrow_cap(&e, &asset, amount);
+    }
+
+    /// Returns the escrow cap for `asset`, if one is set.
+    pub fn get_max_escrow(e: Env, asset: Address) -> Option<i128> {
+        storage::get_escrow_cap(&e, &asset)
+    }
+
+    /// Caps the underlying deposited into `vault` by a single distribution.
+    /// DeFindex vaults expose no deposit-limit getter, so operators mirror the
+    /// vault's limit here to get a clean rejection instead of a failure deep
+    /// inside the vault's deposit.
+    pub fn set_max_deposit(e: Env, admin: Address, vault: Address, amount: i128) {
+        storage::require_admin(&e, &admin);
+        if amount <= 0 {
+            panic!("max deposit must be positive");
+        }
+        storage::set_max_deposit(&e, &vault, amount);
+    }
+
+    /// Returns the per-call deposit ceiling for `vault`, if one is set.
+    pub fn get_max_deposit(e: Env, vault: Address) -> Option<i128> {
+        storage::get_max_deposit(&e, &vault)
+    }
+
+    /// Rebates `bps` basis points of the df tokens minted by `distribute`
+    /// (and `distribute_with_options` / `distribute_auto`) to the caller, or
+    /// to the `referral` address named in its options, so front-ends can
+    /// earn a fee.  The rebate comes off the top of the mint; recipients
+    /// split the rest pro-rata.  At most [`MAX_REFERRAL_REBATE_BPS`]; 0
+    /// disables it.
+    pub fn set_referral_rebate(e: Env, admin: Address, bps: u32) {
+        storage::require_admin(&e, &admin);
+        if bps > MAX_REFERRAL_REBATE_BPS {
+            panic!("referral rebate exceeds maximum");
+        }
+        storage::set_referral_rebate(&e, bps);
+    }
+
+    /// Returns the referral rebate in basis points.
+    pub fn get_referral_rebate(e: Env) -> u32 {
+        storage::get_referral_rebate(&e)
+    }
+
+    /// Charges `bps` basis points of each `distribute` total as an operator
+    /// fee in underlying: `floor(total * bps / 10_000)` is withheld before
+    /// the deposit and sent to `to`, and only the rest is deposited.
+    /// Recipients bear the fee pro-rata, and their `Distributed` events
+    /// report the net underlying deposited for them.
     ///
-    /// # Auth
-    /// `caller` must authorise this invocation AND the nested sub-invocations:
-    ///   - underlying token transfer from `caller` to the vault (pulled internally by the vault)
-    ///   - `vault_df_token.transfer(caller → userN, amountN)` for every recipient
+    /// The fee applies to `distribute` and the modes built on it:
+    /// `distribute_with_options`, `distribute_with_id`, `distribute_tuples`,
+    /// `distribute_from_keys`, `distribute_nested`, `distribute_balance_bps`,
+    /// `distribute_set`, `distribute_mixed`, `repeat_last`,
+    /// `distribute_with_bonus`, `distribute_with_harvest`,
+    /// `reveal_and_distribute`, and `distribute_auto` lists short enough to
+    /// be paid in one call.  Every other mode deposits its full total and
+    /// charges no fee.
     ///
-    /// # Pro-rata note
-    /// The vault may have a share price != 1:1 (e.g. 1 df token = 1.05 USDC if
-    /// the vault has accrued yield).  As a result, the number of df tokens each
-    /// user receives will differ from their input amount, but *proportionality*
-    /// is preserved: a user who contributed X% of the total receives X% of the
-    /// minted df tokens, which redeems for exactly X% of the deposited underlying.
-    pub fn distribute(
+    /// Unlike the share-based referral rebate, which takes df tokens off the
+    /// mint after the whole total was deposited, this fee never enters the
+    /// vault: `to` receives liquid underlying unaffected by the vault's share
+    /// price or rounding, and the vault sees the smaller deposit.  At most
+    /// [`MAX_UNDERLYING_FEE_BPS`]; 0 removes the fee.
+    pub fn set_underlying_fee(e: Env, admin: Address, to: Address, bps: u32) {
+        storage::require_admin(&e, &admin);
+        if bps > MAX_UNDERLYING_FEE_BPS {
+            panic!("underlying fee exceeds maximum");
+        }
+        if bps > 0 {
+            require_valid_payout(&e, &to, "fee recipient");
+        }
+        let fee = if bps == 0 { None } else { Some(UnderlyingFeeConfig { to, bps }) };
+        storage::set_underlying_fee(&e, &fee);
+    }
+
+    /// Returns the underlying fee, or None if unset.
+    pub fn get_underlying_fee(e: Env) -> Option<UnderlyingFeeConfig> {
+        storage::get_underlying_fee(&e)
+    }
+
+    /// Bounds `vault`'s share price, in underlying per
+    /// [`PRICE_PROBE_SHARES`] shares.  After every deposit the vault is asked
+    /// to price the probe; a quote outside `[min_price, max_price]` panics,
+    /// guarding recipients against a buggy or compromised vault.
+    pub fn set_price_bounds(
         e: Env,
-        caller: Address,
-        asset: Address,
+        admin: Address,
         vault: Address,
-        recipients: Vec<Recipient>,
-    ) -> Vec<(Address, i128)> {
+        min_price: i128,
+        max_price: i128,
+    ) {
+        storage::require_admin(&e, &admin);
+        if min_price < 0 || min_price > max_price {
+            panic!("invalid price bounds");
+        }
+        storage::set_price_bounds(&e, &vault, &(min_price, max_price));
+    }
+
+    /// Requires `vault`'s books to balance after every deposit: for each
+    /// asset, the reported `total_amount` must be within `tolerance` of
+    /// `idle_amount` plus the strategy allocations, or the distribution
+    /// panics before any share is transferred.  Catches a vault in a broken
+    /// accounting state.
+    pub fn set_accounting_tolerance(e: Env, admin: Address, vault: Address, tolerance: i128) {
+        storage::require_admin(&e, &admin);
+        if tolerance < 0 {
+            panic!("tolerance must be non-negative");
+        }
+        storage::set_accounting_tolerance(&e, &vault, tolerance);
+    }
+
+    /// Returns `vault`'s accounting tolerance, if the check is enabled.
+    pub fn get_accounting_tolerance(e: Env, vault: Address) -> Option<i128> {
+        storage::get_accounting_tolerance(&e, &vault)
+    }
+
+    /// Returns `vault`'s `(min_price, max_price)` band, if one is set.
+    pub fn get_price_bounds(e: Env, vault: Address) -> Option<(i128, i128)> {
+        storage::get_price_bounds(&e, &vault)
+    }
+
+    /// Halts every distribution, for all assets, until `unpause`.  Takes
+    /// precedence over per-asset pause state.  Claims are unaffected.
+    pub fn pause(e: Env, admin: Address) {
+        storage::require_admin(&e, &admin);
+        storage::set_paused(&e, true);
+    }
+
+    pub fn unpause(e: Env, admin: Address) {
+        storage::require_admin(&e, &admin);
+        storage::set_paused(&e, false);
+    }
+
+    pub fn is_paused(e: Env) -> bool {
+        storage::is_paused(&e)
+    }
+
+    /// Halts distributions of `asset` only, leaving other assets running.
+    pub fn pause_asset(e: Env, admin: Address, asset: Address) {
+        storage::require_admin(&e, &admin);
+        storage::set_asset_paused(&e, &asset, true);
+    }
+
+    pub fn unpause_asset(e: Env, admin: Address, asset: Address) {
+        storage::require_admin(&e, &admin);
+        storage::set_asset_paused(&e, &asset, false);
+    }
+
+    pub fn is_asset_paused(e: Env, asset: Address) -> bool {
+        storage::is_asset_paused(&e, &asset)
+    }
+
+    /// Redeems the df tokens this contract holds for `vault` through the
+    /// vault's withdraw path and sends the underlying to `to`.
+    ///
+    /// df tokens reserved for live escrow are left in place unless `force` is
+    /// set; a forced drain leaves escrow allocations unbacked, so later
+    /// claims against them fail.  Returns the underlying sent.
+    pub fn emergency_drain(
+        e: Env,
+        admin: Address,
+        vault: Address,
+        to: Address,
+        force: bool,
+    ) -> i128 {
+        storage::require_admin(&e, &admin);
+        storage::extend_instance_ttl(&e);
+        require_valid_payout(&e, &to, "drain target");
+        begin_distribution(&e);
+
+        let asset = vault_underlying(&e, &vault);
+        let held = TokenClient::new(&e, &vault).balance(&e.current_contract_address());
+        let reserved = if force {
+            0
+        } else {
+            storage::get_escrow_held(&e, &asset).get(vault.clone()).unwrap_or(0)
+        };
+        let df_tokens = match held.checked_sub(reserved) {
+            Some(v) => v,
+            None => panic!("drainable df tokens underflow"),
+        };
+        if df_tokens <= 0 {
+            panic!("nothing to drain");
+        }
+
+        let underlying = withdraw_held(&e, &vault, df_tokens, 0);
+        TokenClient::new(&e, &asset).transfer(&e.current_contract_address(), &to, &underlying);
+
+        events::EmergencyDrain {
+            vault,
+            to,
+            df_tokens,
+            underlying,
+            forced: force,
+        }
+        .publish(&e);
+
+        end_distribution(&e);
+        underlying
+    }
+
+    /// Redeems the df tokens `caller` received back as surplus from
+    /// rounding-up modes such as `distribute_to_target` and returns the
+    /// underlying to `caller`.
+    ///
+    /// The residual is the recorded difference between what those
+    /// distributions minted and what their recipients needed, capped at the
+    /// df tokens `caller` still holds.  Returns the underlying sent.
+    pub fn reconcile(e: Env, caller: Address, vault: Address) -> i128 {
         caller.require_auth();
-        e.storage().instance().extend_ttl(17280, 17280 * 7);
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let residual = storage::get_residual(&e, &caller, &vault);
+        let df_token = TokenClient::new(&e, &vault);
+        let df_tokens = residual.min(df_token.balance(&caller));
+        if df_tokens <= 0 {
+            panic!("nothing to reconcile");
+        }
+        storage::set_residual(&e, &caller, &vault, 0);
 
-        let n = recipients.len();
+        let this = e.current_contract_address();
+        df_token.transfer(&caller, &this, &df_tokens);
+        let underlying = withdraw_held(&e, &vault, df_tokens, 0);
+        let asset = vault_underlying(&e, &vault);
+        TokenClient::new(&e, &asset).transfer(&this, &caller, &underlying);
 
-        // ── 1. Validate and sum all input amounts ─────────────────────────────
+        events::Reconciled {
+            caller,
+            vault,
+            df_tokens,
+            underlying,
+        }
+        .publish(&e);
+
+        end_distribution(&e);
+        underlying
+    }
+
+    /// Returns the df tokens `reconcile` would redeem for `caller`, before
+    /// capping at their balance.
+    pub fn get_residual(e: Env, caller: Address, vault: Address) -> i128 {
+        storage::get_residual(&e, &caller, &vault)
+    }
+
+    /// Returns the `(symbol, decimals)` of `vault`'s underlying token, for
+    /// display, saving clients a lookup of the asset address.
+    pub fn asset_metadata(e: Env, vault: Address) -> (String, u32) {
+        let token = TokenClient::new(&e, &vault_underlying(&e, &vault));
+        (token.symbol(), token.decimals())
+    }
+
+    /// Returns how many recipients one distribution can pay within
+    /// `budget_instructions`, at [`INSTRUCTIONS_PER_RECIPIENT`] each.
+    ///
+    /// Capped at [`MAX_RECIPIENTS_PER_TX`], beyond which the per-recipient
+    /// events outgrow the network's event-size limit whatever the budget.
+    pub fn max_recipients_for_budget(budget_instructions: u64) -> u32 {
+        let fit = budget_instructions / INSTRUCTIONS_PER_RECIPIENT;
+        fit.min(MAX_RECIPIENTS_PER_TX as u64) as u32
+    }
+
+    /// Returns the minimum `total` that, split evenly across `n` recipients
+    /// (`total / n` each), gives every recipient a non-zero df-token floor at
+    /// the vault's current share price.
+    ///
+    /// Every recipient receives `floor(df_tokens_minted / n)`, so the deposit
+    /// must mint at least `n` shares.  The vault values `n` shares at
+    /// `v = floor(n * managed / supply)`; any deposit strictly above `v` mints
+    /// at least `n` shares.  The result is `v + 1` rounded up to a multiple of
+    /// `n` so that the even split is exact.
+    pub fn min_total_for_recipients(e: Env, vault: Address, n: u32) -> i128 {
         if n == 0 {
-            panic!("recipients must not be empty");
+            panic!("n must be positive");
         }
+        let n = n as i128;
 
-        let mut seen: Map<Address, ()> = Map::new(&e);
-        let mut total: i128 = 0;
-        for r in recipients.iter() {
-            if r.amount <= 0 {
-                panic!("each recipient amount must be positive");
-            }
-            if seen.contains_key(r.address.clone()) {
-                panic!("duplicate recipient address");
-            }
-            seen.set(r.address.clone(), ());
-            total = match total.checked_add(r.amount) {
-                Some(v) => v,
-                None => panic!("total overflow"),
-            };
+        let value_of_n = vault::Client::new(&e, &vault)
+            .get_asset_amounts_per_shares(&n)
+            .get(0)
+            .unwrap_or(0);
+        let min = match value_of_n.checked_add(1) {
+            Some(v) => v,
+            None => panic!("min total overflow"),
+        };
+
+        // ceil(min / n) * n
+        match min.checked_add(n - 1).and_then(|v| (v / n).checked_mul(n)) {
+            Some(v) => v,
+            None => panic!("min total overflow"),
         }
+    }
 
-        // ── 2. Pull underlying asset from caller into this contract ──────────
-        let asset_token = TokenClient::new(&e, &asset);
-        asset_token.transfer(&caller, &e.current_contract_address(), &total);
+    /// Previews the df tokens the last recipient would receive from
+    /// `distribute(.., vault, recipients)` at the vault's current share price.
+    ///
+    /// The last recipient absorbs the rounding remainder
+    /// `df_tokens_minted - sum(floors of the others)`; the minted amount is
+    /// estimated as `floor(total * supply / managed)`.
+    pub fn preview_remainder(e: Env, vault: Address, recipients: Vec<Recipient>) -> i128 {
+        let total = validate_recipients(&e, &recipients, false);
+        let (managed, supply) = vault_price(&e, &vault);
+        let df_tokens_minted = total.fixed_mul_floor(&e, &supply, &managed);
 
-        // ── 3. Deposit into the defindex vault ────────────────────────────────
-        // The vault pulls `total` of the underlying asset from this contract and
-        // mints df tokens back to this contract.
-        let vault_client = vault::Client::new(&e, &vault);
+        match split_shares(&e, &recipients, total, df_tokens_minted).last() {
+            Some(v) => v,
+            None => panic!("recipients must not be empty"),
+        }
+    }
 
-        e.authorize_as_current_contract(vec![
-            &e,
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: asset.clone(),
-                    fn_name: Symbol::new(&e, "transfer"),
-                    args: (
-                        e.current_contract_address(),
-                        vault.clone(),
-                        total,
-                    )
-                        .into_val(&e),
-                },
-                sub_invocations: vec![&e],
-            }),
-        ]);
-
-        let (_deposited, df_tokens_minted, _allocs) = vault_client.deposit(
-            &vec![&e, total],
-            &vec![&e, total],
-            &e.current_contract_address(),
-            &true,
-        );
+    /// Quotes the underlying `distribute(.., vault, recipients)` would lose to
+    /// rounding at the vault's current share price: `total - recoverable`,
+    /// the figure its `RoundingLoss` event will report.
+    ///
+    /// The mint is estimated as `floor(total * S / M)`, split as by
+    /// `distribute`, and each share valued at the post-deposit price
+    /// `floor(share * (M + total) / (S + minted))`.
+    pub fn quote_rounding_loss(e: Env, vault: Address, recipients: Vec<Recipient>) -> i128 {
+        let total = validate_recipients(&e, &recipients, false);
+        let (managed, supply) = vault_price(&e, &vault);
+        let df_tokens_minted = total.fixed_mul_floor(&e, &supply, &managed);
+        let (managed_after, supply_after) = match (
+            managed.checked_add(total),
+            supply.checked_add(df_tokens_minted),
+        ) {
+            (Some(m), Some(s)) => (m, s),
+            _ => panic!("vault totals overflow"),
+        };
 
-        // df tokens are already in this contract (vault minted them to e.current_contract_address())
-        let df_token = TokenClient::new(&e, &vault);
+        let mut recoverable: i128 = 0;
+        for share in split_shares(&e, &recipients, total, df_tokens_minted).iter() {
+            let value = share.fixed_mul_floor(&e, &managed_after, &supply_after);
+            recoverable = match recoverable.checked_add(value) {
+                Some(v) => v,
+                None => panic!("recoverable overflow"),
+            };
+        }
+        total - recoverable
+    }
 
-        // ── 4. Distribute df tokens pro-rata to each recipient ────────────────
-        // Each recipient contributed r.amount / total of the deposit, so they
-        // receive r.amount / total * df_tokens_minted shares.
-        // floor(r.amount * df_tokens_minted / total) — no extra vault call needed.
+    /// Pre-flight cost summary for `distribute(caller, .., vault, recipients)`
+    /// under an underlying fee of `fee_bps`: returns `(underlying_out,
+    /// df_retained)`, the underlying the caller parts with and the df tokens
+    /// that come back to them.
+    ///
+    /// The fee is withheld out of the recipients' total, so `underlying_out`
+    /// is that total whatever `fee_bps` is.  The rounding remainder goes to
+    /// the last recipient, so `df_retained` is only the configured referral
+    /// rebate on the estimated mint `floor((total - fee) * S / M)`, which the
+    /// caller receives when the call names no other referral.  Pass the bps
+    /// of [`Distributor::get_underlying_fee`] to quote the current settings.
+    pub fn quote_caller_cost(
+        e: Env,
+        vault: Address,
+        recipients: Vec<Recipient>,
+        fee_bps: u32,
+    ) -> (i128, i128) {
+        if fee_bps as i128 > BPS_DENOMINATOR {
+            panic!("fee_bps must be at most 10_000");
+        }
+        let total = validate_recipients(&e, &recipients, false);
+        let (managed, supply) = vault_price(&e, &vault);
 
-        let mut distributed: i128 = 0;
-        let mut results: Vec<(Address, i128)> = vec![&e];
-        let mut i: u32 = 0;
+        let deposited = total - total.fixed_mul_floor(&e, &(fee_bps as i128), &BPS_DENOMINATOR);
+        let df_tokens_minted = deposited.fixed_mul_floor(&e, &supply, &managed);
+        let rebate_bps = storage::get_referral_rebate(&e) as i128;
+        (total, df_tokens_minted.fixed_mul_floor(&e, &rebate_bps, &BPS_DENOMINATOR))
+    }
 
-        for r in recipients.iter() {
-            // Use checked_add to detect last element without risking u32 overflow.
-            let is_last = i.checked_add(1).map_or(false, |next| next == n);
-
-            let user_df = if is_last {
-                // Last recipient gets whatever is left to avoid losing dust.
-                match df_tokens_minted.checked_sub(distributed) {
-                    Some(v) => v,
-                    None => panic!("underflow distributing last recipient"),
-                }
-            } else {
-                // floor( r.amount * df_tokens_minted / underlying_for_minted )
-                // Each recipient's share of df tokens is proportional to their
-                // underlying contribution relative to the vault's authoritative
-                // valuation of the total minted shares.
-                r.amount.fixed_div_floor(&e, &total, &df_tokens_minted)
-            };
-            e.authorize_as_current_contract(vec![
-                &e,
-                InvokerContractAuthEntry::Contract(SubContractInvocation {
-                    context: ContractContext {
-                        contract: vault.clone(),
-                        fn_name: Symbol::new(&e, "transfer"),
-                        args: (
-                            e.current_contract_address(),
-                            r.address.clone(),
-                            user_df.clone(),
-                        )
-                            .into_val(&e),
-                    },
-                    sub_invocations: vec![&e],
-                }),
-            ]);
-            df_token.transfer(&e.current_contract_address(), &r.address, &user_df);
-            events::Distributed {
-                asset: asset.clone(),
-                vault: vault.clone(),
-                user: r.address.clone(),
-                underlying_amount: r.amount,
-                df_tokens: user_df,
+    /// Quotes the underlying a holder would receive by redeeming `shares` of
+    /// `vault` through its `withdraw`.
+    ///
+    /// The vault has no fee-inclusive withdrawal preview, so this is its gross
+    /// valuation `get_asset_amounts_per_shares(shares)`.  DeFindex charges no
+    /// withdrawal fee: its fees are taken on reported gains and locked out of
+    /// the managed funds before shares are priced, so `withdraw` pays out the
+    /// same figure.  A vault that did charge on exit would make this an
+    /// upper bound.
+    pub fn quote_redemption(e: Env, vault: Address, shares: i128) -> i128 {
+        if shares < 0 {
+            panic!("shares must not be negative");
+        }
+        if shares == 0 {
+            return 0;
+        }
+        vault::Client::new(&e, &vault)
+            .get_asset_amounts_per_shares(&shares)
+            .get(0)
+            .unwrap_or(0)
+    }
+
+    /// Previews the df tokens `vault` would mint for each candidate total in
+    /// `totals` at its current share price, `floor(total * supply / managed)`,
+    /// in one call.
+    ///
+    /// At most [`MAX_QUERY_LEN`] totals per call.
+    pub fn preview_mints(e: Env, vault: Address, totals: Vec<i128>) -> Vec<i128> {
+        if totals.len() > MAX_QUERY_LEN {
+            panic!("too many totals in query");
+        }
+        let (managed, supply) = vault_price(&e, &vault);
+        let mut mints: Vec<i128> = vec![&e];
+        for total in totals.iter() {
+            if total < 0 {
+                panic!("total must not be negative");
             }
-            .publish(&e);
-            distributed = match distributed.checked_add(user_df) {
-                Some(v) => v,
-                None => panic!("distributed overflow"),
-            };
-            results.push_back((r.address, user_df));
-            i += 1;
+            mints.push_back(total.fixed_mul_floor(&e, &supply, &managed));
         }
+        mints
+    }
 
-        results
+    /// Values `probe_shares` df tokens of each vault in `vaults`, in order,
+    /// via its `get_asset_amounts_per_shares`: one call for a dashboard
+    /// pricing several vaults.
+    ///
+    /// At most [`MAX_QUERY_LEN`] vaults per call.
+    pub fn share_prices(e: Env, vaults: Vec<Address>, probe_shares: i128) -> Vec<i128> {
+        if vaults.len() > MAX_QUERY_LEN {
+            panic!("too many vaults in query");
+        }
+        if probe_shares <= 0 {
+            panic!("probe_shares must be positive");
+        }
+        let mut prices: Vec<i128> = vec![&e];
+        for vault in vaults.iter() {
+            let price = vault::Client::new(&e, &vault)
+                .get_asset_amounts_per_shares(&probe_shares)
+                .get(0)
+                .unwrap_or(0);
+            prices.push_back(price);
+        }
+        prices
     }
+
+    /// Previews the `(address, df_tokens)` results of
+    /// `distribute(.., vault, recipients)` at the vault's current share price,
+    /// net of the configured fees: the underlying fee (see
+    /// [`Distributor::set_underlying_fee`]) comes off the total before the
+    /// mint is estimated, and the referral rebate off the minted pool before
+    /// it is split.  With neither set it is the plain pro-rata preview.
+    ///
+    /// The mint is estimated as `floor(deposited * supply / managed)`.
+    pub fn preview_distribute_with_fee(
+        e: Env,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        let total = validate_recipients(&e, &recipients, false);
+        let (managed, supply) = vault_price(&e, &vault);
+
+        let fee_bps = storage::get_underlying_fee(&e).map_or(0, |fee| fee.bps) as i128;
+        let deposited = total - total.fixed_mul_floor(&e, &fee_bps, &BPS_DENOMINATOR);
+        let df_tokens_minted = deposited.fixed_mul_floor(&e, &supply, &managed);
+        let rebate_bps = storage::get_referral_rebate(&e) as i128;
+        let rebate = df_tokens_minted.fixed_mul_floor(&e, &rebate_bps, &BPS_DENOMINATOR);
+
+        let shares = split_shares(&e, &recipients, total, df_tokens_minted - rebate);
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for (r, user_df) in recipients.iter().zip(shares.iter()) {
+            results.push_back((r.address, user_df));
+        }
+        results
+    }
+
+    /// Lists the `(contract, function)` sub-invocations `caller` must
+    /// authorise beneath a `distribute(caller, asset, vault, recipients)` call,
+    /// for wallets assembling the auth tree: the single
+    /// `asset.transfer(caller → distributor, total)`.
+    ///
+    /// The vault deposit and df token transfers are authorised by this
+    /// contract itself via `authorize_as_current_contract` and are therefore
+    /// not listed.  Takes the same arguments as `distribute` and validates
+    /// `recipients` the same way, so a list `distribute` would reject panics
+    /// here too.
+    pub fn required_auth_tree(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, Symbol)> {
+        // The tree's shape does not depend on who calls or on which vault.
+        let _ = (caller, vault);
+        validate_recipients(&e, &recipients, false);
+        vec![&e, (asset, Symbol::new(&e, "transfer"))]
+    }
+
+    /// Deposits the sum of all recipient amounts into a defindex vault on behalf
+    /// of `caller`, then distributes the minted df tokens back to each recipient
+    /// pro-rata (floor).  The last recipient absorbs any remainder from rounding.
+    ///
+    /// Returns `[(user, df_tokens_received)]` in the same order as `recipients`.
+    /// This ordering is part of the interface: entry `i` always corresponds to
+    /// `recipients[i]`, so callers may zip results against their input by index.
+    ///
+    /// A single recipient takes a fast path that skips duplicate detection and
+    /// the pro-rata split: every minted df token goes to that recipient.
+    ///
+    /// # Auth
+    /// `caller` must authorise this invocation AND its one nested
+    /// sub-invocation, `asset.transfer(caller → distributor, total)`, as
+    /// listed by [`Distributor::required_auth_tree`].  The vault deposit and
+    /// the df token transfers to recipients are made from this contract's own
+    /// balance under its own authorisation, so `caller` signs neither.
+    ///
+    /// When `caller` is a contract invoking `distribute` directly, its auth for
+    /// this call is implicit, but the nested
+    /// `asset.transfer(caller → distributor, total)` is not: the contract must
+    /// pre-authorise it with `authorize_as_current_contract` before the call.
+    ///
+    /// # Pro-rata note
+    /// The vault may have a share price != 1:1 (e.g. 1 df token = 1.05 USDC if
+    /// the vault has accrued yield).  As a result, the number of df tokens each
+    /// user receives will differ from their input amount, but *proportionality*
+    /// is preserved: a user who contributed X% of the total receives X% of the
+    /// minted df tokens, which redeems for exactly X% of the deposited underlying.
+    pub fn distribute(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        Self::distribute_with_options(
+            e,
+            caller,
+            asset,
+            vault,
+            recipients,
+            DistributeOptions::default(),
+        )
+    }
+
+    /// [`Distributor::distribute`] that also returns the distribution's id:
+    /// `(batch_id, results)`, where `batch_id` is the id its
+    /// `DistributionBatch` event carries, so callers can match the call to
+    /// the event without scraping it.  `distribute` keeps its plain return.
+    pub fn distribute_with_id(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> (BytesN<32>, Vec<(Address, i128)>) {
+        caller.require_auth();
+        distribute_authorized_with_id(
+            e,
+            caller,
+            asset,
+            vault,
+            recipients,
+            DistributeOptions::default(),
+        )
+    }
+
+    /// [`Distributor::distribute`] taking recipients as raw
+    /// `(address, amount)` tuples instead of `Recipient` structs.
+    pub fn distribute_tuples(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<(Address, i128)>,
+    ) -> Vec<(Address, i128)> {
+        let mut mapped: Vec<Recipient> = vec![&e];
+        for (address, amount) in recipients.iter() {
+            mapped.push_back(Recipient { address, amount });
+        }
+        Self::distribute(e, caller, asset, vault, mapped)
+    }
+
+    /// [`Distributor::distribute`] to recipients named by raw ed25519 public
+    /// keys, for off-chain systems that track accounts that way.
+    ///
+    /// Each key becomes the Stellar account address whose master key it is
+    /// (the `G...` strkey of the same 32 bytes), decoded from its XDR
+    /// encoding.  Only classic accounts can be named this way,
+    /// never contracts, and the mapping says nothing about which keys can
+    /// sign for the account: the df tokens simply land on it.
+    pub fn distribute_from_keys(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<(BytesN<32>, i128)>,
+    ) -> Vec<(Address, i128)> {
+        let mut mapped: Vec<Recipient> = vec![&e];
+        for (key, amount) in recipients.iter() {
+            let address = account_address(&e, &key);
+            mapped.push_back(Recipient { address, amount });
+        }
+        Self::distribute(e, caller, asset, vault, mapped)
+    }
+
+    /// [`Distributor::distribute`] for hierarchical allocations: each
+    /// `(group_total, members)` group's total is first subdivided among its
+    /// members by their bps, then every member is paid as one distribution.
+    ///
+    /// Each group's bps must sum to [`BPS_DENOMINATOR`].  A member receives
+    /// `floor(group_total * bps / 10_000)` of underlying, the group's last
+    /// member absorbing the group's rounding remainder.  Members are
+    /// validated as a single recipient list, so an address may appear in
+    /// only one group.
+    pub fn distribute_nested(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        groups: Vec<(i128, Vec<RecipientBps>)>,
+    ) -> Vec<(Address, i128)> {
+        let mut recipients: Vec<Recipient> = vec![&e];
+        for (i, (group_total, members)) in groups.iter().enumerate() {
+            recipients.append(&split_group(&e, i as u32, group_total, &members));
+        }
+        Self::distribute(e, caller, asset, vault, recipients)
+    }
+
+    /// Distributes `bps` basis points of `caller`'s current `asset` balance,
+    /// split among `recipients` by their bps, for "distribute what I hold"
+    /// flows where the caller does not know its exact balance.
+    ///
+    /// The total is `floor(balance * bps / 10_000)` and must be non-zero;
+    /// `recipients`' bps must sum to [`BPS_DENOMINATOR`].  Each recipient
+    /// receives `floor(total * bps / 10_000)` of underlying, the last one
+    /// absorbing the remainder, and is then paid as by `distribute`.
+    pub fn distribute_balance_bps(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        bps: u32,
+        recipients: Vec<RecipientBps>,
+    ) -> Vec<(Address, i128)> {
+        if bps == 0 || bps as i128 > BPS_DENOMINATOR {
+            panic!("bps must be in 1..=10000");
+        }
+        if recipients.is_empty() {
+            panic!("recipients must not be empty");
+        }
+        if bps_sum(&recipients) != BPS_DENOMINATOR {
+            panic!("recipient bps must sum to 10000");
+        }
+        let balance = TokenClient::new(&e, &asset).balance(&caller);
+        let total = balance.fixed_mul_floor(&e, &(bps as i128), &BPS_DENOMINATOR);
+        if total == 0 {
+            panic!("computed total is zero");
+        }
+        let split = split_by_bps(&e, total, &recipients);
+        Self::distribute(e, caller, asset, vault, split)
+    }
+
+    /// Registers `recipients` under `name` for recurring distributions with
+    /// [`Distributor::distribute_set`], replacing any set already stored
+    /// under that name.  Amounts are validated as for `distribute`.
+    pub fn register_set(e: Env, admin: Address, name: Symbol, recipients: Vec<Recipient>) {
+        storage::require_admin(&e, &admin);
+        storage::extend_instance_ttl(&e);
+        validate_recipients(&e, &recipients, false);
+        storage::set_recipient_set(&e, &name, &recipients);
+    }
+
+    /// Deletes the recipient set registered under `name`.
+    pub fn remove_set(e: Env, admin: Address, name: Symbol) {
+        storage::require_admin(&e, &admin);
+        storage::extend_instance_ttl(&e);
+        if storage::get_recipient_set(&e, &name).is_none() {
+            panic!("recipient set not found");
+        }
+        storage::remove_recipient_set(&e, &name);
+    }
+
+    /// Returns the recipient set registered under `name`, if any.
+    pub fn get_set(e: Env, name: Symbol) -> Option<Vec<Recipient>> {
+        storage::get_recipient_set(&e, &name)
+    }
+
+    /// [`Distributor::distribute`] to the recipient set registered under
+    /// `name`.
+    pub fn distribute_set(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        name: Symbol,
+    ) -> Vec<(Address, i128)> {
+        let recipients = match storage::get_recipient_set(&e, &name) {
+            Some(set) => set,
+            None => panic!("recipient set not found"),
+        };
+        Self::distribute(e, caller, asset, vault, recipients)
+    }
+
+    /// [`Distributor::distribute`] for lists of any length.
+    ///
+    /// Up to [`MAX_RECIPIENTS_PER_TX`] recipients are paid in full right away.
+    /// Longer lists are only deposited and split: the minted shares stay
+    /// reserved in this contract, and `caller` pays them out by calling
+    /// [`Distributor::distribute_chunk`] once per returned chunk index.
+    ///
+    /// Chunking spans several transactions and so cannot be atomic: between
+    /// chunks some recipients are paid and others are not yet.  The split is
+    /// fixed at deposit time, so every recipient still gets exactly the share
+    /// the single-call `distribute` would have given them.  The reserved
+    /// shares count against the asset's escrow cap (see
+    /// [`Distributor::set_max_escrow`]) until paid, and the split becomes
+    /// `caller`'s last split for [`Distributor::repeat_last`] once every
+    /// chunk is paid.
+    pub fn distribute_auto(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> AutoDistribution {
+        if recipients.len() <= MAX_RECIPIENTS_PER_TX {
+            let results = Self::distribute(e.clone(), caller, asset, vault, recipients);
+            return AutoDistribution { results, batch_id: None, pending_chunks: vec![&e] };
+        }
+
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        let total = validate_recipients(&e, &recipients, false);
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        let rebate = pay_referral_rebate(&e, &vault, &vault, &caller, df_tokens_minted);
+        let shares = split_shares(&e, &recipients, total, df_tokens_minted - rebate);
+        let mut valued = shares.clone();
+        valued.push_back(rebate);
+        emit_rounding_loss(&e, &asset, &vault, total, &valued);
+        // One id names both the batch event and the chunked state, so the
+        // event's id is the one `distribute_chunk` and `resume_distribution` take.
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total,
+            df_tokens_minted,
+            batch_id.clone(),
+        );
+
+        let chunks = recipients.len().div_ceil(MAX_RECIPIENTS_PER_TX);
+        let mut pending: Vec<u32> = vec![&e];
+        for chunk in 0..chunks {
+            pending.push_back(chunk);
+        }
+        // Reserved shares sit in this contract like escrow, under its cap.
+        storage::add_escrow_held(&e, &asset, &vault, df_tokens_minted - rebate);
+        check_escrow_cap(&e, &asset);
+
+        storage::set_chunked(
+            &e,
+            &batch_id,
+            &ChunkedDistribution {
+                caller,
+                asset,
+                vault,
+                recipients,
+                shares,
+                pending: pending.clone(),
+                next_index: 0,
+                distributed_so_far: 0,
+            },
+        );
+
+        end_distribution(&e);
+        AutoDistribution { results: vec![&e], batch_id: Some(batch_id), pending_chunks: pending }
+    }
+
+    /// Pays the recipients in `chunk` of a chunked distribution created by
+    /// [`Distributor::distribute_auto`].  Chunks may be paid in any order,
+    /// each exactly once.
+    ///
+    /// Returns `(address, df_tokens)` for the recipients paid.
+    pub fn distribute_chunk(
+        e: Env,
+        caller: Address,
+        batch_id: BytesN<32>,
+        chunk: u32,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let mut dist = storage::get_chunked(&e, &batch_id);
+        if caller != dist.caller {
+            panic!("caller did not create this batch");
+        }
+        let idx = match dist.pending.first_index_of(chunk) {
+            Some(i) => i,
+            None => panic!("chunk is not pending"),
+        };
+        dist.pending.remove(idx);
+
+        // Part of the chunk may already have been paid by `resume_distribution`.
+        let start = (chunk * MAX_RECIPIENTS_PER_TX).max(dist.next_index);
+        let end = dist.recipients.len().min((chunk + 1) * MAX_RECIPIENTS_PER_TX);
+        let (results, paid) = pay_range(&e, &dist, start, end);
+        save_chunk_progress(&e, &batch_id, dist, paid);
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Continues a chunked distribution sequentially from where it stopped,
+    /// paying `recipients_slice`, which must be exactly the recipients
+    /// starting at the batch's `next_index` (see
+    /// [`Distributor::chunk_progress`]).  Passing the slice lets the contract
+    /// check the operator's view of the progress before paying anything.
+    ///
+    /// Panics if the slice is empty, longer than [`MAX_RECIPIENTS_PER_TX`],
+    /// does not continue from `next_index`, or reaches recipients already
+    /// paid by `distribute_chunk`.  Returns `(address, df_tokens)` for the
+    /// recipients paid.
+    pub fn resume_distribution(
+        e: Env,
+        caller: Address,
+        batch_id: BytesN<32>,
+        recipients_slice: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let mut dist = storage::get_chunked(&e, &batch_id);
+        if caller != dist.caller {
+            panic!("caller did not create this batch");
+        }
+        if recipients_slice.is_empty() || recipients_slice.len() > MAX_RECIPIENTS_PER_TX {
+            panic!("slice must hold 1 to MAX_RECIPIENTS_PER_TX recipients");
+        }
+        let start = dist.next_index;
+        let end = start + recipients_slice.len();
+        if end > dist.recipients.len() {
+            panic!("slice runs past the end of the batch");
+        }
+        for (k, r) in recipients_slice.iter().enumerate() {
+            let expected = dist.recipients.get_unchecked(start + k as u32);
+            if expected.address != r.address || expected.amount != r.amount {
+                panic!("slice does not continue from next_index");
+            }
+        }
+        for chunk in (start / MAX_RECIPIENTS_PER_TX)..end.div_ceil(MAX_RECIPIENTS_PER_TX) {
+            if !dist.pending.contains(chunk) {
+                panic!("slice reaches recipients already paid");
+            }
+        }
+
+        let (results, paid) = pay_range(&e, &dist, start, end);
+        dist.next_index = end;
+        let mut pending: Vec<u32> = vec![&e];
+        for chunk in dist.pending.iter() {
+            if dist.recipients.len().min((chunk + 1) * MAX_RECIPIENTS_PER_TX) > end {
+                pending.push_back(chunk);
+            }
+        }
+        dist.pending = pending;
+        save_chunk_progress(&e, &batch_id, dist, paid);
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Returns `(next_index, distributed_so_far)` for a chunked distribution
+    /// still in progress: every recipient below `next_index` has been paid,
+    /// and `distributed_so_far` df tokens have left the batch in total.
+    pub fn chunk_progress(e: Env, batch_id: BytesN<32>) -> (u32, i128) {
+        let dist = storage::get_chunked(&e, &batch_id);
+        (dist.next_index, dist.distributed_so_far)
+    }
+
+    /// [`Distributor::distribute`] with the behaviour switches in `options`.
+    pub fn distribute_with_options(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+        options: DistributeOptions,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        distribute_authorized(e, caller, asset, vault, recipients, options)
+    }
+
+    /// Best-effort [`Distributor::distribute`]: zero-amount rows are skipped as
+    /// under `skip_zero`, and a recipient whose df-token transfer fails is
+    /// skipped too, their shares going back to `caller` instead of reverting
+    /// the whole distribution.  Each skipped row emits `RecipientRejected`.
+    ///
+    /// Recipients are probed before any transfer: if the df token answers
+    /// the Stellar Asset Contract's `authorized(id)` with false (a frozen or
+    /// deauthorised trustline), the recipient's shares go straight back to
+    /// `caller` without a doomed transfer.  That flag is the only eligibility
+    /// a SAC exposes cheaply; balances, trustline limits and contract-side
+    /// rules cannot be read up front, so those failures are still caught at
+    /// transfer time.  A token without `authorized` is treated as eligible.
+    ///
+    /// With `max_balance`, a caller-supplied per-account cap (see
+    /// [`DistributeOptions::max_balance`]), each share is clamped to the room
+    /// left under the cap at the recipient's current balance and the excess
+    /// refunded to `caller`; a recipient already at the cap is skipped.
+    ///
+    /// Returns `(address, df_tokens, succeeded)` per input row, in input
+    /// order.  A failed row reports the df tokens refunded to `caller`, a
+    /// clamped row the df tokens it received; a zero row reports 0.  [`Distributor::distribute`] keeps its strict
+    /// all-or-nothing behaviour and plain return.  Emits `RoundingLoss` for
+    /// the whole split, refunds included.
+    pub fn distribute_best_effort(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+        max_balance: Option<i128>,
+    ) -> Vec<(Address, i128, bool)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        let kept = drop_zero_amounts(&e, &recipients);
+        if kept.is_empty() {
+            panic!("no non-zero recipients");
+        }
+        let total = validate_recipients(&e, &kept, false);
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        let split = split_shares(&e, &kept, total, df_tokens_minted);
+        let mut shares = split.iter();
+
+        let share_token = StellarAssetClient::new(&e, &vault);
+        let mut results: Vec<(Address, i128, bool)> = vec![&e];
+        let mut refund: i128 = 0;
+        for (i, r) in (0..recipients.len()).zip(recipients.iter()) {
+            if r.amount == 0 {
+                results.push_back((r.address, 0, false));
+                continue;
+            }
+            let mut user_df = match shares.next() {
+                Some(v) => v,
+                None => panic!("shares shorter than non-zero rows"),
+            };
+            if let Some(cap) = max_balance {
+                let room = (cap - share_token.balance(&r.address)).max(0);
+                if room == 0 {
+                    events::RecipientRejected {
+                        index: i,
+                        user: r.address.clone(),
+                        reason: RejectReason::BalanceCap,
+                    }
+                    .publish(&e);
+                    refund = add_refund(refund, user_df);
+                    results.push_back((r.address, user_df, false));
+                    continue;
+                }
+                if user_df > room {
+                    refund = add_refund(refund, user_df - room);
+                    user_df = room;
+                }
+            }
+            if let Ok(Ok(false)) = share_token.try_authorized(&r.address) {
+                events::RecipientRejected {
+                    index: i,
+                    user: r.address.clone(),
+                    reason: RejectReason::Ineligible,
+                }
+                .publish(&e);
+                refund = add_refund(refund, user_df);
+                results.push_back((r.address, user_df, false));
+            } else if try_transfer_df(&e, &vault, &r.address, user_df) {
+                storage::add_df_distributed(&e, &vault, user_df);
+                events::Distributed {
+                    asset: asset.clone(),
+                    vault: vault.clone(),
+                    user: r.address.clone(),
+                    underlying_amount: r.amount,
+                    df_tokens: user_df,
+                    seq: storage::next_event_seq(&e),
+                }
+                .publish(&e);
+                results.push_back((r.address, user_df, true));
+            } else {
+                events::RecipientRejected {
+                    index: i,
+                    user: r.address.clone(),
+                    reason: RejectReason::TransferFailed,
+                }
+                .publish(&e);
+                refund = add_refund(refund, user_df);
+                results.push_back((r.address, user_df, false));
+            }
+        }
+        if refund > 0 {
+            transfer_df(&e, &vault, &caller, refund);
+        }
+
+        // Refunded shares still hold their value, so the loss is the split's.
+        emit_rounding_loss(&e, &asset, &vault, total, &split);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(&e, &asset, &vault, kept.len(), total, df_tokens_minted, batch_id);
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Distributes a new `total` in the proportions of `caller`'s most recent
+    /// [`Distributor::distribute`] into `vault`, weighted by the df tokens
+    /// each recipient received then, so a recurring split need not be
+    /// re-specified.
+    ///
+    /// Each recipient's underlying amount is `floor(total * df / sum_df)`,
+    /// the last absorbing the remainder; the call then distributes like
+    /// `distribute` and becomes the new last split.  Panics if `caller` has
+    /// not distributed into `vault` yet, or if `total` is too small for
+    /// every recipient's floor to be positive, so that no one silently drops
+    /// out of the recurring split.
+    pub fn repeat_last(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        total: i128,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        if total <= 0 {
+            panic!("total must be positive");
+        }
+        let split = match storage::get_last_split(&e, &caller, &vault) {
+            Some(split) => split,
+            None => panic!("no previous distribution to repeat"),
+        };
+
+        let mut weight_total: i128 = 0;
+        for r in split.iter() {
+            weight_total = match weight_total.checked_add(r.amount) {
+                Some(v) => v,
+                None => panic!("weight total overflow"),
+            };
+        }
+        let mut recipients: Vec<Recipient> = vec![&e];
+        let mut assigned: i128 = 0;
+        for (i, r) in (0..split.len()).zip(split.iter()) {
+            let amount = if i + 1 == split.len() {
+                total - assigned
+            } else {
+                total.fixed_mul_floor(&e, &r.amount, &weight_total)
+            };
+            if amount == 0 {
+                panic!("total too small to give every recipient a share");
+            }
+            assigned = match assigned.checked_add(amount) {
+                Some(v) => v,
+                None => panic!("assigned overflow"),
+            };
+            recipients.push_back(Recipient { address: r.address, amount });
+        }
+
+        distribute_authorized(e, caller, asset, vault, recipients, DistributeOptions::default())
+    }
+
+    /// Runs one [`Distributor::distribute`] per `(asset, vault, recipients)`
+    /// entry under a single authorisation, e.g. a payout run paying USDC to
+    /// some recipients and XLM to others.  Each group gets its own deposit,
+    /// split and events; the call is atomic, so one failing group reverts
+    /// them all.
+    ///
+    /// Returns each group's results, in entry order.
+    pub fn distribute_mixed(
+        e: Env,
+        caller: Address,
+        entries: Vec<(Address, Address, Vec<Recipient>)>,
+    ) -> Vec<Vec<(Address, i128)>> {
+        caller.require_auth();
+        if entries.is_empty() {
+            panic!("entries must not be empty");
+        }
+        let mut vaults: Map<Address, ()> = Map::new(&e);
+        for (_, vault, _) in entries.iter() {
+            vaults.set(vault, ());
+        }
+        if vaults.len() > storage::get_max_vaults_per_call(&e) {
+            panic!("too many vaults in one call");
+        }
+
+        let mut results: Vec<Vec<(Address, i128)>> = vec![&e];
+        for (asset, vault, recipients) in entries.iter() {
+            let options = DistributeOptions::default();
+            results.push_back(distribute_authorized(
+                e.clone(),
+                caller.clone(),
+                asset,
+                vault,
+                recipients,
+                options,
+            ));
+        }
+        results
+    }
+
+    /// Deposits a round `total` and gives each named recipient a fixed share
+    /// of it, sending the residual to `change_to`.
+    ///
+    /// Unlike `distribute`, no named recipient absorbs the rounding remainder:
+    /// each receives `floor(amount * df_tokens_minted / total)`, and
+    /// `change_to` receives every remaining df token (the unassigned
+    /// `total - sum(amounts)` plus rounding dust).
+    ///
+    /// `change_to` is checked like a named recipient: it may not be a
+    /// placeholder address, this contract, or one of `recipients`.
+    ///
+    /// Returns the named recipients' results in input order followed by
+    /// `(change_to, change_df)`.  With no change left, `change_to` is
+    /// reported with 0 but gets no transfer or `Distributed` event and is
+    /// not counted in the batch.
+    pub fn distribute_with_change(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        total: i128,
+        recipients: Vec<Recipient>,
+        change_to: Address,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        let named = validate_recipients(&e, &recipients, false);
+        if named > total {
+            panic!("recipients exceed total");
+        }
+        require_valid_payout(&e, &change_to, "change_to");
+        if recipients.iter().any(|r| r.address == change_to) {
+            panic!("change_to is already a recipient");
+        }
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        let mut paid: Vec<i128> = vec![&e];
+        let mut distributed: i128 = 0;
+        for r in recipients.iter() {
+            let user_df = compute_share(&e, r.amount, total, df_tokens_minted, distributed, false);
+            distributed = match distributed.checked_add(user_df) {
+                Some(v) => v,
+                None => panic!("distributed overflow"),
+            };
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount,
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            paid.push_back(user_df);
+            results.push_back((r.address, user_df));
+        }
+
+        let change_df = compute_share(&e, 0, total, df_tokens_minted, distributed, true);
+        if change_df > 0 {
+            pay_df(&e, &vault, &change_to, change_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: change_to.clone(),
+                underlying_amount: total - named,
+                df_tokens: change_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            paid.push_back(change_df);
+        }
+        results.push_back((change_to, change_df));
+        emit_rounding_loss(&e, &asset, &vault, total, &paid);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            paid.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Admin-only: like `distribute`, but prices the minted df tokens at an
+    /// agreed rate instead of the vault's, for migrations that settle at a
+    /// fixed exchange rate.
+    ///
+    /// The deposit still goes through `vault`, which mints `df_tokens_minted`
+    /// as usual.  Those shares are then treated as worth
+    /// `underlying_for_minted_override` of underlying, in place of what
+    /// `get_asset_amounts_per_shares` reports, so each recipient receives
+    /// `floor(amount * df_tokens_minted / underlying_for_minted_override)`.
+    /// An override above the deposited total leaves df tokens unassigned;
+    /// they are returned to `caller`.  An override below the total would
+    /// promise more shares than were minted and panics.
+    ///
+    /// Trust assumptions: the contract does not check the override against
+    /// the live price.  Both `admin` and `caller` must sign: the admin vouches
+    /// for the rate, and the caller accepts that recipients may receive
+    /// shares worth more or less than their `amount` at the vault's actual
+    /// price, with the caller keeping the difference.  Price bounds and the
+    /// accounting check still apply to the deposit itself.  Intended for
+    /// migrations and tests, not routine payouts.
+    ///
+    /// Returns `(address, df_tokens)` per recipient in input order.
+    pub fn distribute_at_rate(
+        e: Env,
+        admin: Address,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+        underlying_for_minted_override: i128,
+    ) -> Vec<(Address, i128)> {
+        storage::require_admin(&e, &admin);
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        let total = validate_recipients(&e, &recipients, false);
+        if underlying_for_minted_override < total {
+            panic!("override rate undervalues minted shares");
+        }
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        let mut paid: Vec<i128> = vec![&e];
+        let mut distributed: i128 = 0;
+        for r in recipients.iter() {
+            let user_df = compute_share(
+                &e,
+                r.amount,
+                underlying_for_minted_override,
+                df_tokens_minted,
+                distributed,
+                false,
+            );
+            distributed = match distributed.checked_add(user_df) {
+                Some(v) => v,
+                None => panic!("distributed overflow"),
+            };
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount,
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            paid.push_back(user_df);
+            results.push_back((r.address, user_df));
+        }
+
+        let change_df = df_tokens_minted - distributed;
+        if change_df > 0 {
+            transfer_df(&e, &vault, &caller, change_df);
+            paid.push_back(change_df);
+        }
+        emit_rounding_loss(&e, &asset, &vault, total, &paid);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Like `distribute`, but each recipient's shares are minted straight to
+    /// them: for every recipient, `caller` transfers `amount` of `asset` to
+    /// the recipient and the vault deposits it with the recipient as
+    /// depositor.  Neither `caller` nor this contract ever holds the df
+    /// tokens, so there is no forwarding hop and no contract-side auth.
+    ///
+    /// The vault's `deposit` mints to the account it pulls funds from, so
+    /// every recipient must authorize their own deposit in this transaction:
+    /// besides `caller`'s tree (this call and one `asset.transfer` to each
+    /// recipient), each recipient signs `vault.deposit([amount], [amount],
+    /// recipient, true)`.  Without those entries the first deposit panics.
+    /// It also costs one vault deposit per recipient instead of one in total,
+    /// and each deposit floors its mint separately, so recipients lose up to
+    /// one df token each to rounding rather than sharing one remainder.  Use
+    /// it for small batches of co-signing recipients.
+    ///
+    /// Returns `(address, df_tokens)` per recipient in input order.
+    pub fn distribute_direct(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        let total = validate_recipients(&e, &recipients, false);
+        if let Some(max) = storage::get_max_deposit(&e, &vault) {
+            if total > max {
+                panic!("total exceeds vault max deposit");
+            }
+        }
+
+        let asset_token = TokenClient::new(&e, &asset);
+        let vault_client = vault::Client::new(&e, &vault);
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        let mut shares: Vec<i128> = vec![&e];
+        let mut df_tokens_minted: i128 = 0;
+        for r in recipients.iter() {
+            asset_token.transfer(&caller, &r.address, &r.amount);
+            let supply_before = vault_client.total_supply();
+            let (_deposited, user_df, _allocs) = vault_client.deposit(
+                &vec![&e, r.amount],
+                &vec![&e, r.amount],
+                &r.address,
+                &true,
+            );
+            if vault_client.total_supply() - supply_before != user_df {
+                panic!("vault supply change does not match minted df tokens");
+            }
+            df_tokens_minted = match df_tokens_minted.checked_add(user_df) {
+                Some(v) => v,
+                None => panic!("df tokens minted overflow"),
+            };
+            storage::add_df_distributed(&e, &vault, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount,
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            shares.push_back(user_df);
+            results.push_back((r.address, user_df));
+        }
+        record_deposit(&e, &caller, &asset, &vault, total, df_tokens_minted);
+
+        emit_rounding_loss(&e, &asset, &vault, total, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Splits df tokens already held by `holder`, e.g. a custodian, across
+    /// `recipients` without any deposit.  Recipient amounts are df-token
+    /// amounts and are transferred straight from `holder` to each recipient;
+    /// the contract never holds them.
+    ///
+    /// `Distributed` reports each transfer's underlying value at the current
+    /// share price.  No deposit is made, so nothing is added to the volume
+    /// or the audit log.
+    ///
+    /// # Auth
+    /// `caller` must authorise this invocation and `holder` the nested
+    /// df-token transfers.
+    pub fn distribute_from_holder(
+        e: Env,
+        caller: Address,
+        holder: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+
+        begin_distribution(&e);
+        let asset = vault_underlying(&e, &vault);
+        require_not_paused(&e, &asset);
+        let total = validate_recipients(&e, &recipients, false);
+        let (managed, supply) = vault_price(&e, &vault);
+
+        let vault_client = vault::Client::new(&e, &vault);
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for r in recipients.iter() {
+            vault_client.transfer(&holder, &r.address, &r.amount);
+            storage::add_df_distributed(&e, &vault, r.amount);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount.fixed_mul_floor(&e, &managed, &supply),
+                df_tokens: r.amount,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            results.push_back((r.address, r.amount));
+        }
+
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total.fixed_mul_floor(&e, &managed, &supply),
+            total,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Distributes two tranches through separate deposits: the
+    /// `invested_recipients` total is deposited with `invest = true`, so the
+    /// vault pushes it to its strategies, and the `idle_recipients` total with
+    /// `invest = false`, so it stays idle in the vault for liquidity.  Each
+    /// tranche's minted df tokens are split across its own recipients as by
+    /// `distribute`, the last of each absorbing its remainder.
+    ///
+    /// Either tranche may be empty, not both, and no address may appear in
+    /// both.  Returns `(address, df_tokens)` for the invested recipients
+    /// followed by the idle ones.
+    pub fn distribute_tranched(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        invested_recipients: Vec<Recipient>,
+        idle_recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        // Validating the union catches addresses listed in both tranches.
+        let mut all = invested_recipients.clone();
+        all.append(&idle_recipients);
+        let total = validate_recipients(&e, &all, false);
+        TokenClient::new(&e, &asset).transfer(&caller, e.current_contract_address(), &total);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        let mut shares: Vec<i128> = vec![&e];
+        let mut df_tokens_minted: i128 = 0;
+        for (tranche, invest) in [(invested_recipients, true), (idle_recipients, false)] {
+            if tranche.is_empty() {
+                continue;
+            }
+            let tranche_total: i128 = tranche.iter().map(|r| r.amount).sum();
+            let minted = deposit_held_with(&e, &caller, &asset, &vault, tranche_total, invest);
+            df_tokens_minted = match df_tokens_minted.checked_add(minted) {
+                Some(v) => v,
+                None => panic!("df tokens minted overflow"),
+            };
+            let split = split_shares(&e, &tranche, tranche_total, minted);
+            for (r, user_df) in tranche.iter().zip(split.iter()) {
+                pay_df(&e, &vault, &r.address, user_df);
+                events::Distributed {
+                    asset: asset.clone(),
+                    vault: vault.clone(),
+                    user: r.address.clone(),
+                    underlying_amount: r.amount,
+                    df_tokens: user_df,
+                    seq: storage::next_event_seq(&e),
+                }
+                .publish(&e);
+                shares.push_back(user_df);
+                results.push_back((r.address, user_df));
+            }
+        }
+
+        emit_rounding_loss(&e, &asset, &vault, total, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &all);
+        emit_distribution_batch(&e, &asset, &vault, all.len(), total, df_tokens_minted, batch_id);
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Deposits `total` and splits the minted df tokens across `entries`
+    /// pro-rata to tenure, `current_ledger - joined_ledger`, for loyalty
+    /// rewards.  Entries that joined this ledger have no tenure: they are
+    /// reported as `(address, 0)` with a `RecipientRejected` event and take
+    /// no part in the split, so the last entry *with* tenure absorbs the
+    /// rounding remainder.
+    ///
+    /// The entries with tenure are validated like a `distribute` list, each
+    /// with its pro-rata part of `total` as its amount, so invalid addresses,
+    /// `min_recipients` and `min_amount` apply.  Panics on a join ledger in
+    /// the future, duplicate addresses, or when no entry has any tenure yet.
+    ///
+    /// Returns `(address, df_tokens)` per entry in input order.
+    pub fn distribute_by_tenure(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        total: i128,
+        entries: Vec<(Address, u64)>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        if total <= 0 {
+            panic!("total must be positive");
+        }
+        if entries.is_empty() {
+            panic!("recipients must not be empty");
+        }
+
+        // Tenure weights reuse the pro-rata split: amount = ledgers held.
+        let now = e.ledger().sequence() as u64;
+        let mut seen: Map<Address, ()> = Map::new(&e);
+        let mut weighted: Vec<Recipient> = vec![&e];
+        let mut total_tenure: i128 = 0;
+        for (address, joined_ledger) in entries.iter() {
+            if joined_ledger > now {
+                panic!("join ledger is in the future");
+            }
+            if seen.contains_key(address.clone()) {
+                panic!("duplicate recipient address");
+            }
+            seen.set(address.clone(), ());
+            let tenure = (now - joined_ledger) as i128;
+            total_tenure = match total_tenure.checked_add(tenure) {
+                Some(v) => v,
+                None => panic!("total overflow"),
+            };
+            weighted.push_back(Recipient { address, amount: tenure });
+        }
+        if total_tenure == 0 {
+            panic!("no recipient has any tenure");
+        }
+        // Zero-tenure entries are owed nothing, so they must not absorb the
+        // remainder; the rest are checked at their underlying value.
+        let tenured = drop_zero_amounts(&e, &weighted);
+        let mut named: Vec<Recipient> = vec![&e];
+        for r in tenured.iter() {
+            let amount = total.fixed_mul_floor(&e, &r.amount, &total_tenure);
+            named.push_back(Recipient { address: r.address, amount });
+        }
+        validate_recipients(&e, &named, false);
+
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        let shares = split_shares(&e, &tenured, total_tenure, df_tokens_minted);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for (r, user_df) in named.iter().zip(shares.iter()) {
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount,
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            results.push_back((r.address, user_df));
+        }
+        emit_rounding_loss(&e, &asset, &vault, total, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &weighted);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            tenured.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        with_skipped_rows(&e, &weighted, &results)
+    }
+
+    /// Deposits `total` and splits the minted df tokens quadratic-funding
+    /// style: each entry is weighted by `floor(sqrt(contribution))`, so many
+    /// small contributors outweigh one large one.  Zero contributions are
+    /// reported as `(address, 0)` with a `RecipientRejected` event and take
+    /// no part in the split, so the last non-zero entry absorbs the rounding
+    /// remainder.
+    ///
+    /// The non-zero contributions are validated like a `distribute` list
+    /// before any weight is computed, so invalid addresses, duplicates,
+    /// `min_recipients` and `min_amount` apply.  Panics on a negative
+    /// contribution, or when every contribution is zero.
+    ///
+    /// Returns `(address, df_tokens)` per entry in input order.
+    pub fn distribute_quadratic(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        total: i128,
+        contributions: Vec<(Address, i128)>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        if total <= 0 {
+            panic!("total must be positive");
+        }
+        if contributions.is_empty() {
+            panic!("recipients must not be empty");
+        }
+
+        let mut listed: Vec<Recipient> = vec![&e];
+        for (address, contribution) in contributions.iter() {
+            if contribution < 0 {
+                panic!("contribution must not be negative");
+            }
+            listed.push_back(Recipient { address, amount: contribution });
+        }
+        let contributors = drop_zero_amounts(&e, &listed);
+        if contributors.is_empty() {
+            panic!("total weight is zero");
+        }
+        validate_recipients(&e, &contributors, false);
+
+        // Quadratic weights reuse the pro-rata split: amount = isqrt(contribution).
+        let mut weighted: Vec<Recipient> = vec![&e];
+        let mut total_weight: i128 = 0;
+        for r in contributors.iter() {
+            let weight = isqrt(r.amount);
+            total_weight = match total_weight.checked_add(weight) {
+                Some(v) => v,
+                None => panic!("total weight overflow"),
+            };
+            weighted.push_back(Recipient { address: r.address, amount: weight });
+        }
+
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        let shares = split_shares(&e, &weighted, total_weight, df_tokens_minted);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for (r, user_df) in weighted.iter().zip(shares.iter()) {
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: total.fixed_mul_floor(&e, &r.amount, &total_weight),
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            results.push_back((r.address, user_df));
+        }
+        emit_rounding_loss(&e, &asset, &vault, total, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &listed);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            weighted.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        with_skipped_rows(&e, &listed, &results)
+    }
+
+    /// Deposits `total`, pays `reserve_bps` of the minted df tokens to
+    /// `reserve_to` (e.g. a protocol treasury), and splits the rest across
+    /// `recipients` pro-rata to their amounts, which act as weights.  The
+    /// reserve is carved from the shares, not the underlying, so reserve and
+    /// recipient shares always sum to the mint.  The last recipient absorbs
+    /// the rounding remainder.
+    ///
+    /// `reserve_bps` must be below [`BPS_DENOMINATOR`], and `reserve_to`
+    /// must not be the distributor or a zero address.  Emits `Reserved`
+    /// for the reserve.  Returns `(address, df_tokens)` per recipient in
+    /// input order.
+    pub fn distribute_with_reserve(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        total: i128,
+        reserve_bps: u32,
+        reserve_to: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        if total <= 0 {
+            panic!("total must be positive");
+        }
+        if reserve_bps as i128 >= BPS_DENOMINATOR {
+            panic!("reserve_bps must be below 10_000");
+        }
+        require_valid_payout(&e, &reserve_to, "reserve_to");
+        let total_weight = validate_recipients(&e, &recipients, false);
+
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        let reserve = df_tokens_minted.fixed_mul_floor(&e, &(reserve_bps as i128), &BPS_DENOMINATOR);
+        if reserve > 0 {
+            pay_df(&e, &vault, &reserve_to, reserve);
+            events::Reserved { vault: vault.clone(), to: reserve_to, df_tokens: reserve }.publish(&e);
+        }
+        let shares = split_shares(&e, &recipients, total_weight, df_tokens_minted - reserve);
+        let net_total = total - total.fixed_mul_floor(&e, &(reserve_bps as i128), &BPS_DENOMINATOR);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for (r, user_df) in recipients.iter().zip(shares.iter()) {
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: net_total.fixed_mul_floor(&e, &r.amount, &total_weight),
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            results.push_back((r.address, user_df));
+        }
+        emit_rounding_loss(&e, &asset, &vault, net_total, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// [`Distributor::distribute`] that also pays every recipient a flat
+    /// `bonus_per_recipient` of `bonus_token` from `caller`, e.g. a loyalty
+    /// token paired with the vault shares.
+    ///
+    /// The bonus is checked up front: `caller` must hold at least
+    /// `recipients.len() * bonus_per_recipient` of `bonus_token`, plus the
+    /// distribution total when `bonus_token` is `asset`, or the call panics
+    /// before depositing.  The bonus transfers follow the share
+    /// transfers and go straight from `caller`, so its authorisation must
+    /// cover them.  Returns the `distribute` results.
+    pub fn distribute_with_bonus(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+        bonus_token: Address,
+        bonus_per_recipient: i128,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        if bonus_per_recipient <= 0 {
+            panic!("bonus_per_recipient must be positive");
+        }
+        let bonus_total = match bonus_per_recipient.checked_mul(recipients.len() as i128) {
+            Some(v) => v,
+            None => panic!("bonus total overflow"),
+        };
+        // Paid in the asset itself, the bonus comes on top of the deposit.
+        let required = if bonus_token == asset {
+            match validate_recipients(&e, &recipients, false).checked_add(bonus_total) {
+                Some(v) => v,
+                None => panic!("bonus total overflow"),
+            }
+        } else {
+            bonus_total
+        };
+        let bonus = TokenClient::new(&e, &bonus_token);
+        if bonus.balance(&caller) < required {
+            panic!("insufficient bonus balance");
+        }
+
+        let results = distribute_authorized(
+            e,
+            caller.clone(),
+            asset,
+            vault,
+            recipients,
+            DistributeOptions::default(),
+        );
+        for (address, _) in results.iter() {
+            bonus.transfer(&caller, &address, &bonus_per_recipient);
+        }
+        results
+    }
+
+    /// Opts `user` out of (or back into) auto-staking: distributions with a
+    /// `stake_contract` send an opted-out user's shares to them directly.
+    pub fn set_stake_opt_out(e: Env, user: Address, opt_out: bool) {
+        user.require_auth();
+        storage::extend_instance_ttl(&e);
+        storage::set_stake_opt_out(&e, &user, opt_out);
+    }
+
+    /// Returns whether `user` opted out of auto-staking.
+    pub fn get_stake_opt_out(e: Env, user: Address) -> bool {
+        storage::get_stake_opt_out(&e, &user)
+    }
+
+    /// Grants `spender` a standing budget to distribute up to `amount` of
+    /// `caller`'s `asset` through `distribute_from_budget`, replacing any
+    /// previous budget.
+    ///
+    /// Funds are pulled with SEP-41 `transfer_from`, so `caller` must also
+    /// `approve` this contract on `asset` for at least the budget.
+    pub fn set_budget(e: Env, caller: Address, spender: Address, asset: Address, amount: i128) {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        if amount < 0 {
+            panic!("budget must not be negative");
+        }
+        storage::set_budget(&e, &caller, &spender, &asset, amount);
+    }
+
+    /// Returns the remaining budget `spender` holds over `caller`'s `asset`.
+    pub fn get_budget(e: Env, caller: Address, spender: Address, asset: Address) -> i128 {
+        storage::get_budget(&e, &caller, &spender, &asset)
+    }
+
+    /// Like `distribute`, but signed by `spender` and funded from `caller`'s
+    /// pre-approved allowance, deducting the total from the budget `caller`
+    /// granted `spender`.  Panics if the total exceeds the remaining budget.
+    pub fn distribute_from_budget(
+        e: Env,
+        spender: Address,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        spender.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        let total = validate_recipients(&e, &recipients, false);
+        let budget = storage::get_budget(&e, &caller, &spender, &asset);
+        if total > budget {
+            panic!("distribution exceeds budget");
+        }
+        storage::set_budget(&e, &caller, &spender, &asset, budget - total);
+
+        TokenClient::new(&e, &asset).transfer_from(
+            &e.current_contract_address(),
+            &caller,
+            &e.current_contract_address(),
+            &total,
+        );
+        let df_tokens_minted = deposit_held(&e, &caller, &asset, &vault, total);
+        let shares = split_shares(&e, &recipients, total, df_tokens_minted);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for (r, user_df) in recipients.iter().zip(shares.iter()) {
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount,
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            results.push_back((r.address, user_df));
+        }
+        emit_rounding_loss(&e, &asset, &vault, total, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Tops each recipient up to a target df-token balance in `vault`.
+    ///
+    /// Each entry of `targets` is `(address, desired total df balance)`.
+    /// The deficit `target - current balance` is computed per recipient
+    /// (recipients at or above target are skipped), and just enough
+    /// underlying is deposited to mint the summed deficit:
+    /// `ceil(deficit * managed / supply)`.  Any df tokens minted beyond the
+    /// deficit are returned to `caller`.
+    ///
+    /// The recipients with a deficit are validated like a `distribute` list,
+    /// each at the underlying its deficit costs, so invalid addresses,
+    /// `min_recipients` and `min_amount` apply.
+    ///
+    /// Returns `(address, df_tokens_received)` index-aligned with `targets`,
+    /// with 0 for skipped recipients.
+    pub fn distribute_to_target(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        targets: Vec<(Address, i128)>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+        if targets.is_empty() {
+            panic!("recipients must not be empty");
+        }
+
+        let df_token = TokenClient::new(&e, &vault);
+        let mut seen: Map<Address, ()> = Map::new(&e);
+        let mut deficits: Vec<i128> = vec![&e];
+        let mut total_deficit: i128 = 0;
+        for (address, target) in targets.iter() {
+            if target < 0 {
+                panic!("target must not be negative");
+            }
+            if seen.contains_key(address.clone()) {
+                panic!("duplicate recipient address");
+            }
+            seen.set(address.clone(), ());
+
+            let deficit = (target - df_token.balance(&address)).max(0);
+            total_deficit = match total_deficit.checked_add(deficit) {
+                Some(v) => v,
+                None => panic!("total overflow"),
+            };
+            deficits.push_back(deficit);
+        }
+        if total_deficit == 0 {
+            panic!("all targets already met");
+        }
+
+        let (managed, supply) = vault_price(&e, &vault);
+        let mut topped_up: Vec<Recipient> = vec![&e];
+        for ((address, _), deficit) in targets.iter().zip(deficits.iter()) {
+            if deficit > 0 {
+                let amount = deficit.fixed_mul_ceil(&e, &managed, &supply);
+                topped_up.push_back(Recipient { address, amount });
+            }
+        }
+        validate_recipients(&e, &topped_up, false);
+        let total = total_deficit.fixed_mul_ceil(&e, &managed, &supply);
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        if df_tokens_minted < total_deficit {
+            panic!("deposit minted less than the deficit");
+        }
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for ((address, _), deficit) in targets.iter().zip(deficits.iter()) {
+            if deficit > 0 {
+                pay_df(&e, &vault, &address, deficit);
+                events::Distributed {
+                    asset: asset.clone(),
+                    vault: vault.clone(),
+                    user: address.clone(),
+                    underlying_amount: deficit.fixed_mul_floor(&e, &managed, &supply),
+                    df_tokens: deficit,
+                    seq: storage::next_event_seq(&e),
+                }
+                .publish(&e);
+            }
+            results.push_back((address, deficit));
+        }
+
+        let surplus = df_tokens_minted - total_deficit;
+        if surplus > 0 {
+            transfer_df(&e, &vault, &caller, surplus);
+            let residual = storage::get_residual(&e, &caller, &vault);
+            let residual = match residual.checked_add(surplus) {
+                Some(v) => v,
+                None => panic!("residual overflow"),
+            };
+            storage::set_residual(&e, &caller, &vault, residual);
+            deficits.push_back(surplus);
+        }
+        emit_rounding_loss(&e, &asset, &vault, total, &deficits);
+        let mut id_rows: Vec<Recipient> = vec![&e];
+        for (address, target) in targets.iter() {
+            id_rows.push_back(Recipient { address, amount: target });
+        }
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &id_rows);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            targets.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Commits `caller` to a recipient set without revealing it.
+    ///
+    /// `commitment` must be `sha256(xdr(recipients))` of the exact
+    /// `Vec<Recipient>` later passed to `reveal_and_distribute`.  A new
+    /// commitment replaces any pending one.
+    pub fn commit_distribution(e: Env, caller: Address, commitment: BytesN<32>) {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        storage::set_commitment(&e, &caller, &commitment);
+    }
+
+    /// Reveals the recipient set committed by `commit_distribution` and runs
+    /// `distribute` with it.  Panics if the hash of `recipients` does not
+    /// match the pending commitment; the commitment is consumed on success.
+    pub fn reveal_and_distribute(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        let commitment = storage::take_commitment(&e, &caller);
+        let revealed: BytesN<32> = e.crypto().sha256(&recipients.clone().to_xdr(&e)).into();
+        if revealed != commitment {
+            panic!("revealed recipients do not match commitment");
+        }
+
+        Self::distribute_with_options(
+            e,
+            caller,
+            asset,
+            vault,
+            recipients,
+            DistributeOptions::default(),
+        )
+    }
+
+    /// Like `distribute`, but caps what any single recipient receives at
+    /// `max_underlying_per_recipient` of underlying value.
+    ///
+    /// The cap is converted to a df-token limit at the post-deposit share
+    /// price, so it stays stable as the price drifts.  Df tokens clamped off
+    /// capped recipients are sent to `overflow_to` in a single transfer;
+    /// `overflow_to` must not be the distributor or a zero address.
+    ///
+    /// The returned Vec is index-aligned with `recipients` and holds the
+    /// (possibly clamped) df tokens each recipient received.
+    pub fn distribute_capped_underlying(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+        max_underlying_per_recipient: i128,
+        overflow_to: Address,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        begin_distribution(&e);
+
+        if max_underlying_per_recipient <= 0 {
+            panic!("cap must be positive");
+        }
+        require_valid_payout(&e, &overflow_to, "overflow_to");
+        let total = validate_recipients(&e, &recipients, false);
+        let df_tokens_minted = deposit_from_caller(&e, &caller, &asset, &vault, total);
+        let shares = split_shares(&e, &recipients, total, df_tokens_minted);
+
+        let (managed, supply) = vault_price(&e, &vault);
+        let max_df = max_underlying_per_recipient.fixed_mul_floor(&e, &supply, &managed);
+        if max_df == 0 {
+            panic!("cap is below one df token");
+        }
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        let mut paid: Vec<i128> = vec![&e];
+        let mut overflow: i128 = 0;
+        for (r, user_df) in recipients.iter().zip(shares.iter()) {
+            let capped_df = user_df.min(max_df);
+            overflow = match overflow.checked_add(user_df - capped_df) {
+                Some(v) => v,
+                None => panic!("overflow amount overflow"),
+            };
+
+            pay_df(&e, &vault, &r.address, capped_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount,
+                df_tokens: capped_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            paid.push_back(capped_df);
+            results.push_back((r.address, capped_df));
+        }
+
+        if overflow > 0 {
+            pay_df(&e, &vault, &overflow_to, overflow);
+            events::Overflowed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                to: overflow_to,
+                df_tokens: overflow,
+            }
+            .publish(&e);
+            paid.push_back(overflow);
+        }
+        emit_rounding_loss(&e, &asset, &vault, total, &paid);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            total,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Swaps the caller's `reward_asset` into the vault's underlying through
+    /// the Soroswap `router`, deposits the swap output into `vault`, and
+    /// distributes the minted df tokens pro-rata.
+    ///
+    /// Recipient amounts are denominated in `reward_asset`; their sum is the
+    /// exact swap input.  `path` is the router path, starting at
+    /// `reward_asset` and ending at the vault's underlying; it may route
+    /// through intermediate tokens when no direct pair exists.  Panics if
+    /// the swap yields less than `min_out` underlying.
+    ///
+    /// # Auth
+    /// `caller` must authorise this invocation and the nested
+    /// `reward_asset.transfer(caller → distributor, total)`.
+    pub fn distribute_with_swap(
+        e: Env,
+        caller: Address,
+        reward_asset: Address,
+        router: Address,
+        path: Vec<Address>,
+        vault: Address,
+        min_out: i128,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let total_in = validate_recipients(&e, &recipients, false);
+        if min_out <= 0 {
+            panic!("min_out must be positive");
+        }
+        let asset = match path.last() {
+            Some(a) => a,
+            None => panic!("swap path must not be empty"),
+        };
+        if path.first() != Some(reward_asset.clone()) {
+            panic!("swap path must start at reward_asset");
+        }
+        if asset != vault_underlying(&e, &vault) {
+            panic!("swap path must end at the vault underlying");
+        }
+        require_not_paused(&e, &asset);
+
+        TokenClient::new(&e, &reward_asset).transfer(
+            &caller,
+            e.current_contract_address(),
+            &total_in,
+        );
+        let amount_out = swap_exact_in(&e, &router, &path, total_in, min_out);
+        events::Swapped {
+            reward_asset: reward_asset.clone(),
+            asset: asset.clone(),
+            amount_in: total_in,
+            amount_out,
+        }
+        .publish(&e);
+
+        let df_tokens_minted = deposit_held(&e, &caller, &asset, &vault, amount_out);
+        let shares = split_shares(&e, &recipients, total_in, df_tokens_minted);
+
+        let mut results: Vec<(Address, i128)> = vec![&e];
+        for (r, user_df) in recipients.iter().zip(shares.iter()) {
+            pay_df(&e, &vault, &r.address, user_df);
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: r.amount.fixed_div_floor(&e, &total_in, &amount_out),
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+            results.push_back((r.address, user_df));
+        }
+        emit_rounding_loss(&e, &asset, &vault, amount_out, &shares);
+        let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &recipients);
+        emit_distribution_batch(
+            &e,
+            &asset,
+            &vault,
+            recipients.len(),
+            amount_out,
+            df_tokens_minted,
+            batch_id,
+        );
+
+        end_distribution(&e);
+        results
+    }
+
+    /// Harvests `strategy` if the BLND emissions it has accrued in the Blend
+    /// `pool` reach `min_pending`, then runs [`Distributor::distribute`].
+    ///
+    /// Blend keeps a supplier's emissions in the pool until they are
+    /// claimed, so the trigger is what the strategy's harvest would claim
+    /// now: its recorded accrual plus what its b-tokens of the strategy's
+    /// asset have earned since.  Below `min_pending` the harvest is skipped,
+    /// saving its claim, swap and reinvest cost on negligible amounts.
+    /// Harvesting before the deposit lets the reinvested rewards accrue to
+    /// existing holders rather than to the recipients.  Emits `Harvested`
+    /// when the harvest runs.
+    ///
+    /// # Auth
+    /// `caller` must be the strategy's keeper, and must authorise this
+    /// invocation, the nested `strategy.harvest` and the deposit transfer.
+    pub fn distribute_with_harvest(
+        e: Env,
+        caller: Address,
+        strategy: Address,
+        pool: Address,
+        min_pending: i128,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> Vec<(Address, i128)> {
+        caller.require_auth();
+        if min_pending <= 0 {
+            panic!("min_pending must be positive");
+        }
+
+        begin_distribution(&e);
+        let pending = pending_emissions(&e, &pool, &strategy);
+        if pending >= min_pending {
+            strategy::StrategyClient::new(&e, &strategy).harvest(&caller, &None);
+            events::Harvested {
+                strategy,
+                pool,
+                pending,
+            }
+            .publish(&e);
+        }
+        end_distribution(&e);
+
+        distribute_authorized(e, caller, asset, vault, recipients, DistributeOptions::default())
+    }
+
+    /// Returns the cumulative underlying distributed per asset across every
+    /// distribution mode, for accounting exports.
+    ///
+    /// The map holds one entry per distinct asset ever distributed.
+    pub fn volume_by_asset(e: Env) -> Map<Address, i128> {
+        storage::get_asset_volumes(&e)
+    }
+
+    /// Total df tokens of `vault` paid out to recipients over the contract's
+    /// lifetime, including escrow claims.  Complements the underlying-side
+    /// [`Distributor::volume_by_asset`].
+    pub fn df_distributed(e: Env, vault: Address) -> i128 {
+        storage::get_df_distributed(&e, &vault)
+    }
+
+    /// Returns the retained summaries of distributions `start` to
+    /// `start + count - 1`, oldest first, for compliance exports.
+    ///
+    /// Only the last [`AUDIT_LOG_CAPACITY`] distributions are kept; ids in the
+    /// window that were overwritten or not yet recorded are left out.  At
+    /// most [`MAX_QUERY_LEN`] entries per call.
+    pub fn audit_log(e: Env, start: u64, count: u32) -> Vec<DistributionSummary> {
+        if count > MAX_QUERY_LEN {
+            panic!("too many entries in query");
+        }
+        let recorded = storage::get_distribution_count(&e);
+        let oldest = recorded.saturating_sub(AUDIT_LOG_CAPACITY as u64);
+        let end = start.saturating_add(count as u64).min(recorded);
+
+        let mut log: Vec<DistributionSummary> = vec![&e];
+        for id in start.max(oldest)..end {
+            log.push_back(storage::get_audit_entry(&e, id));
+        }
+        log
+    }
+
+    /// Number of distributions recorded so far.  Ids run from 0 to
+    /// `distribution_count() - 1` in execution order.
+    pub fn distribution_count(e: Env) -> u64 {
+        storage::get_distribution_count(&e)
+    }
+
+    /// Returns `(underlying deposited, df tokens minted)` for distribution
+    /// `id`, from which auditors can compute the realized exchange rate.
+    pub fn distribution_rate(e: Env, id: u64) -> (i128, i128) {
+        let record = storage::get_distribution(&e, id);
+        (record.underlying, record.df_tokens)
+    }
+
+    // ── Escrow ────────────────────────────────────────────────────────────────
+
+    /// Same deposit and pro-rata split as [`Distributor::distribute`], but the
+    /// df tokens stay in this contract as per-recipient allocations that each
+    /// recipient later withdraws with [`Distributor::claim`].
+    ///
+    /// Returns the id of the new escrow batch.
+    pub fn distribute_escrow(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> BytesN<32> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+
+        begin_distribution(&e);
+        let batch_id = escrow_batch(&e, &caller, asset, vault, &recipients);
+        end_distribution(&e);
+        batch_id
+    }
+
+    /// Escrows `recipients`' shares as offers that each recipient must
+    /// explicitly take with [`Distributor::accept_offer`].  The batch is
+    /// marked as an offer, so `claim` and `claim_all` do not release it.
+    ///
+    /// Requires a non-zero escrow expiry so that offers nobody accepts can
+    /// always be swept back with [`Distributor::reclaim`] after the deadline.
+    ///
+    /// Returns the id of the new escrow batch.
+    pub fn offer_distribution(
+        e: Env,
+        caller: Address,
+        asset: Address,
+        vault: Address,
+        recipients: Vec<Recipient>,
+    ) -> BytesN<32> {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        require_not_paused(&e, &asset);
+        if storage::get_escrow_expiry(&e) == 0 {
+            panic!("offers require an escrow expiry");
+        }
+
+        begin_distribution(&e);
+        let batch_id = escrow_batch(&e, &caller, asset, vault, &recipients);
+        storage::set_offer(&e, &batch_id);
+        end_distribution(&e);
+        batch_id
+    }
+
+    /// Accepts `recipient`'s offer in `batch_id`, releasing their df tokens.
+    /// Only `recipient` can accept, and only batches created by
+    /// `offer_distribution` can be accepted.
+    ///
+    /// Returns the df tokens released.
+    pub fn accept_offer(e: Env, recipient: Address, batch_id: BytesN<32>) -> i128 {
+        recipient.require_auth();
+        storage::extend_instance_ttl(&e);
+        if !storage::is_offer(&e, &batch_id) {
+            panic!("batch is not an offer");
+        }
+
+        begin_distribution(&e);
+        let df_tokens = release_allocation(&e, &recipient, &batch_id);
+        end_distribution(&e);
+        df_tokens
+    }
+
+    /// Transfers `user`'s unclaimed allocation in `batch_id` to `user`.
+    /// Offers are taken with `accept_offer` instead.
+    ///
+    /// Returns the df tokens claimed.
+    pub fn claim(e: Env, user: Address, batch_id: BytesN<32>) -> i128 {
+        user.require_auth();
+        storage::extend_instance_ttl(&e);
+        if storage::is_offer(&e, &batch_id) {
+            panic!("offers must be accepted");
+        }
+
+        begin_distribution(&e);
+        let df_tokens = release_allocation(&e, &user, &batch_id);
+        end_distribution(&e);
+        df_tokens
+    }
+
+    /// Claims `user`'s unclaimed allocations across `vault`'s escrow batches
+    /// in one call, oldest first.  At most [`MAX_CLAIMS_PER_TX`] batches are
+    /// released per call; any beyond that stay claimable by calling again.
+    /// Offers are skipped; they are taken with `accept_offer`.
+    ///
+    /// Returns the total df tokens claimed.
+    pub fn claim_all(e: Env, user: Address, vault: Address) -> i128 {
+        user.require_auth();
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let mut batch_ids: Vec<BytesN<32>> = vec![&e];
+        for batch_id in storage::get_user_batches(&e, &vault, &user).iter() {
+            if !storage::is_offer(&e, &batch_id) {
+                batch_ids.push_back(batch_id);
+            }
+        }
+        if batch_ids.is_empty() {
+            panic!("nothing to claim");
+        }
+        let mut claimed: i128 = 0;
+        for batch_id in batch_ids.iter().take(MAX_CLAIMS_PER_TX as usize) {
+            let amount = release_allocation(&e, &user, &batch_id);
+            claimed = match claimed.checked_add(amount) {
+                Some(v) => v,
+                None => panic!("claimed overflow"),
+            };
+        }
+        end_distribution(&e);
+        claimed
+    }
+
+    /// Voids an escrow batch created in error and returns every escrowed df
+    /// token to `caller`.
+    ///
+    /// Only the batch's original caller may cancel, and only while no
+    /// recipient has claimed.  All allocations in the batch are dropped.
+    ///
+    /// Returns the df tokens returned to `caller`.
+    pub fn cancel_distribution(e: Env, caller: Address, batch_id: BytesN<32>) -> i128 {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let mut batch = storage::get_batch(&e, &batch_id);
+        if caller != batch.caller {
+            panic!("caller did not create this batch");
+        }
+        if batch.canceled {
+            panic!("escrow batch already canceled");
+        }
+        if batch.claims > 0 {
+            panic!("escrow batch has claims");
+        }
+
+        for user in storage::get_batch_recipients(&e, &batch_id).iter() {
+            storage::take_allocation(&e, &batch_id, &batch.vault, &user);
+        }
+        storage::remove_batch_recipients(&e, &batch_id);
+
+        let amount = batch.df_remaining;
+        batch.df_remaining = 0;
+        batch.canceled = true;
+        storage::set_batch(&e, &batch_id, &batch);
+        storage::remove_caller_batch(&e, &caller, &batch_id);
+
+        if amount > 0 {
+            storage::add_escrow_held(&e, &batch.asset, &batch.vault, -amount);
+            transfer_df(&e, &batch.vault, &caller, amount);
+        }
+        events::Canceled {
+            batch_id,
+            vault: batch.vault,
+            caller,
+            df_tokens: amount,
+        }
+        .publish(&e);
+
+        end_distribution(&e);
+        amount
+    }
+
+    /// Sweeps every unclaimed allocation of an expired escrow batch back to
+    /// its `caller`.
+    ///
+    /// With `as_underlying`, the swept df tokens are redeemed through the
+    /// vault and the underlying is sent to `caller`; the redemption panics if
+    /// it yields less than `min_underlying_out`.  Otherwise the df tokens are
+    /// returned as-is and `min_underlying_out` is ignored.
+    ///
+    /// Returns the amount sent to `caller`: underlying or df tokens.
+    pub fn reclaim(
+        e: Env,
+        caller: Address,
+        batch_id: BytesN<32>,
+        as_underlying: bool,
+        min_underlying_out: i128,
+    ) -> i128 {
+        caller.require_auth();
+        storage::extend_instance_ttl(&e);
+        begin_distribution(&e);
+
+        let mut batch = storage::get_batch(&e, &batch_id);
+        if caller != batch.caller {
+            panic!("caller did not create this batch");
+        }
+        if batch.expiry_ledger == 0 || e.ledger().sequence() <= batch.expiry_ledger {
+            panic!("escrow batch has not expired");
+        }
+        if batch.df_remaining == 0 {
+            panic!("nothing to reclaim");
+        }
+
+        for user in storage::get_batch_recipients(&e, &batch_id).iter() {
+            storage::take_allocation(&e, &batch_id, &batch.vault, &user);
+        }
+        storage::remove_batch_recipients(&e, &batch_id);
+
+        let df_tokens = batch.df_remaining;
+        batch.df_remaining = 0;
+        storage::set_batch(&e, &batch_id, &batch);
+        storage::remove_caller_batch(&e, &caller, &batch_id);
+        storage::add_escrow_held(&e, &batch.asset, &batch.vault, -df_tokens);
+
+        let underlying = if as_underlying {
+            let out = withdraw_held(&e, &batch.vault, df_tokens, min_underlying_out);
+            TokenClient::new(&e, &batch.asset).transfer(
+                &e.current_contract_address(),
+                &caller,
+                &out,
+            );
+            out
+        } else {
+            transfer_df(&e, &batch.vault, &caller, df_tokens);
+            0
+        };
+
+        events::Reclaimed {
+            batch_id,
+            vault: batch.vault,
+            caller,
+            df_tokens,
+            underlying,
+        }
+        .publish(&e);
+
+        end_distribution(&e);
+        if as_underlying {
+            underlying
+        } else {
+            df_tokens
+        }
+    }
+
+    /// Returns the ids of `caller`'s escrow batches that are not yet fully
+    /// claimed, canceled or reclaimed, in creation order.
+    pub fn caller_batches(e: Env, caller: Address) -> Vec<BytesN<32>> {
+        storage::get_caller_batches(&e, &caller)
+    }
+
+    /// Returns each user's total unclaimed escrow (df tokens) for `vault`
+    /// across all batches, in the same order as `users`.
+    ///
+    /// At most [`MAX_QUERY_LEN`] users per call.
+    pub fn escrow_balances(e: Env, vault: Address, users: Vec<Address>) -> Vec<i128> {
+        if users.len() > MAX_QUERY_LEN {
+            panic!("too many users in query");
+        }
+        let mut balances: Vec<i128> = vec![&e];
+        for user in users.iter() {
+            balances.push_back(storage::escrow_balance(&e, &vault, &user));
+        }
+        balances
+    }
+
+    /// Returns `user`'s receipt for escrow batch `batch_id`: the df tokens
+    /// allocated to them and the ledger the batch was created at, or `None`
+    /// if they had no allocation in it.  Receipts outlive the allocation, so
+    /// they stay queryable after a claim.
+    pub fn receipt(e: Env, user: Address, batch_id: BytesN<32>) -> Option<DistributionReceipt> {
+        storage::get_receipt(&e, &user, &batch_id)
+    }
+
+    /// Moves every unclaimed allocation `from_recipient` holds in `vault`'s
+    /// escrow batches to `to_recipient`, e.g. when the original address turns
+    /// out to be unusable.
+    pub fn reassign(
+        e: Env,
+        admin: Address,
+        vault: Address,
+        from_recipient: Address,
+        to_recipient: Address,
+    ) {
+        storage::require_admin(&e, &admin);
+        storage::extend_instance_ttl(&e);
+        move_allocations(&e, vault, from_recipient, to_recipient);
+    }
+
+    /// Moves every unclaimed allocation `old` holds in `vault`'s escrow
+    /// batches to `new`, for a recipient rotating wallets.  Unlike
+    /// [`Distributor::reassign`] it needs `old`'s consent as well as the
+    /// admin's.
+    ///
+    /// Only escrowed allocations, still held by this contract, can be
+    /// migrated; df tokens already paid out belong to the recipient and must
+    /// be transferred by them.
+    pub fn migrate_recipient(e: Env, admin: Address, vault: Address, old: Address, new: Address) {
+        storage::require_admin(&e, &admin);
+        old.require_auth();
+        storage::extend_instance_ttl(&e);
+        move_allocations(&e, vault, old, new);
+    }
+}
+
+// ── Internal helpers ──────────────────────────────────────────────────────────
+
+/// Body of [`Distributor::distribute_with_options`], for callers that have
+/// already checked `caller`'s authorisation.
+fn distribute_authorized(
+    e: Env,
+    caller: Address,
+    asset: Address,
+    vault: Address,
+    recipients: Vec<Recipient>,
+    options: DistributeOptions,
+) -> Vec<(Address, i128)> {
+    distribute_authorized_with_id(e, caller, asset, vault, recipients, options).1
+}
+
+/// [`distribute_authorized`] that also returns the distribution's id, the
+/// `batch_id` of its `DistributionBatch` event.
+fn distribute_authorized_with_id(
+    e: Env,
+    caller: Address,
+    asset: Address,
+    vault: Address,
+    recipients: Vec<Recipient>,
+    options: DistributeOptions,
+) -> (BytesN<32>, Vec<(Address, i128)>) {
+    storage::extend_instance_ttl(&e);
+    require_not_paused(&e, &asset);
+    begin_distribution(&e);
+
+    if recipients.len() == 1
+        && options == DistributeOptions::default()
+        && storage::get_referral_rebate(&e) == 0
+        && storage::get_underlying_fee(&e).is_none()
+    {
+        let single = distribute_single(&e, &caller, &asset, &vault, &recipients);
+        end_distribution(&e);
+        return single;
+    }
+
+    // ── 1. Validate and sum all input amounts ─────────────────────────────────
+    let input = recipients.clone();
+    let recipients = if options.skip_zero {
+        let kept = drop_zero_amounts(&e, &input);
+        if kept.is_empty() && !input.is_empty() {
+            panic!("no non-zero recipients");
+        }
+        kept
+    } else {
+        input.clone()
+    };
+    let total = validate_recipients(&e, &recipients, options.assume_sorted);
+    if let Some(expected) = options.expected_total {
+        if expected != total {
+            panic!("total does not match expected_total");
+        }
+    }
+    if let Some(max_bps) = options.max_single_bps {
+        require_max_concentration(&e, &recipients, total, max_bps);
+    }
+    if let Some(referral) = &options.referral {
+        require_valid_payout(&e, referral, "referral");
+    }
+    if let Some(stake_contract) = &options.stake_contract {
+        require_valid_payout(&e, stake_contract, "stake_contract");
+    }
+    if options.share_increment <= 0 {
+        panic!("share_increment must be positive");
+    }
+    if options.spread_remainder
+        && (options.remainder_to_largest_address || options.share_increment > 1)
+    {
+        panic!("spread_remainder conflicts with the remainder options");
+    }
+
+    // ── 2–3. Pull underlying from caller and deposit into the vault ───────────
+    // Any underlying fee is withheld here, so only `deposited` enters the vault.
+    TokenClient::new(&e, &asset).transfer(&caller, e.current_contract_address(), &total);
+    let fee = withhold_underlying_fee(&e, &asset, total);
+    let deposited = total - fee;
+    let df_tokens_minted = deposit_held(&e, &caller, &asset, &vault, deposited);
+    if !options.allow_loss {
+        require_no_loss(&e, &vault, deposited, df_tokens_minted);
+    }
+
+    // ── 4. Distribute df tokens pro-rata to each recipient ────────────────────
+    // Each recipient contributed r.amount / total of the deposit, so they
+    // receive r.amount / total * df_tokens_minted shares.
+    // floor(r.amount * df_tokens_minted / total) — no extra vault call needed.
+    // Only the reported mint is split, never this contract's live df
+    // balance, so tokens a vault injects mid-call are not paid out.
+    // The referral rebate, if any, comes off the top.
+    let share_token = options.share_token.clone().unwrap_or(vault.clone());
+    let rebate_to = options.referral.clone().unwrap_or(caller.clone());
+    let rebate = pay_referral_rebate(&e, &vault, &share_token, &rebate_to, df_tokens_minted);
+    let absorber = if options.remainder_to_largest_address {
+        largest_address_index(&recipients)
+    } else {
+        recipients.len() - 1
+    };
+    let mut shares = if options.spread_remainder {
+        split_shares_spread(&e, &recipients, total, df_tokens_minted - rebate)
+    } else {
+        split_shares_to(&e, &recipients, total, df_tokens_minted - rebate, absorber)
+    };
+    let pool = df_tokens_minted - rebate;
+    check_dust(&e, &recipients, total, pool, &shares, absorber, options.max_dust);
+    if options.share_increment > 1 {
+        shares = round_to_increment(&shares, absorber, options.share_increment);
+    }
+    if let Some(cap) = options.max_balance {
+        require_within_balance_cap(&e, &share_token, &recipients, &shares, cap);
+    }
+    // Each recipient's part of the net deposit, for their `Distributed` event.
+    let net_amounts = if fee > 0 {
+        split_shares_to(&e, &recipients, total, deposited, absorber)
+    } else {
+        let mut amounts: Vec<i128> = vec![&e];
+        for r in recipients.iter() {
+            amounts.push_back(r.amount);
+        }
+        amounts
+    };
+
+    let mut results: Vec<(Address, i128)> = vec![&e];
+    for ((r, user_df), net_amount) in recipients.iter().zip(shares.iter()).zip(net_amounts.iter()) {
+        match &options.stake_contract {
+            Some(stake_contract) if !storage::get_stake_opt_out(&e, &r.address) => {
+                pay_shares(&e, &vault, &share_token, stake_contract, user_df);
+                if user_df > 0 {
+                    stake::StakeClient::new(&e, stake_contract).stake(&r.address, &user_df);
+                }
+            }
+            _ => pay_shares(&e, &vault, &share_token, &r.address, user_df),
+        }
+        if options.emit_per_recipient {
+            events::Distributed {
+                asset: asset.clone(),
+                vault: vault.clone(),
+                user: r.address.clone(),
+                underlying_amount: net_amount,
+                df_tokens: user_df,
+                seq: storage::next_event_seq(&e),
+            }
+            .publish(&e);
+        }
+        results.push_back((r.address, user_df));
+    }
+    let mut valued = shares.clone();
+    valued.push_back(rebate);
+    if options.fallback_valuation {
+        let price = try_vault_price(&e, &vault).unwrap_or((deposited, df_tokens_minted));
+        emit_rounding_loss_at(&e, &asset, &vault, deposited, &valued, price);
+    } else {
+        emit_rounding_loss(&e, &asset, &vault, deposited, &valued);
+    }
+    let batch_id = storage::next_distribution_id(&e, &caller, &asset, &vault, &input);
+    emit_distribution_batch(
+        &e,
+        &asset,
+        &vault,
+        recipients.len(),
+        deposited,
+        df_tokens_minted,
+        batch_id.clone(),
+    );
+    if let Some(hook) = &options.hook {
+        let count = recipients.len();
+        call_hook(&e, hook, &vault, deposited, df_tokens_minted, count, options.hook_failure_ok);
+    }
+
+    record_last_split(&e, &caller, &vault, &results);
+    end_distribution(&e);
+
+    if options.skip_zero {
+        return (batch_id, with_skipped_rows(&e, &input, &results));
+    }
+    (batch_id, results)
+}
+
+/// [`distribute_authorized_with_id`] for exactly one recipient and default
+/// options, with no rebate or fee configured.  Same checks, transfers and
+/// events as the general path, minus the pro-rata split: every minted df
+/// token goes to the one recipient.  The caller holds the distribution lock.
+///
+/// The vault mints shares to the depositor only (its `deposit` has no
+/// recipient parameter), so the contract still holds the shares briefly
+/// before forwarding them.
+fn distribute_single(
+    e: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    recipients: &Vec<Recipient>,
+) -> (BytesN<32>, Vec<(Address, i128)>) {
+    let total = validate_recipients(e, recipients, false);
+    let r = match recipients.get(0) {
+        Some(r) => r,
+        None => panic!("recipients must not be empty"),
+    };
+
+    let df_tokens_minted = deposit_from_caller(e, caller, asset, vault, total);
+    pay_df(e, vault, &r.address, df_tokens_minted);
+    events::Distributed {
+        asset: asset.clone(),
+        vault: vault.clone(),
+        user: r.address.clone(),
+        underlying_amount: total,
+        df_tokens: df_tokens_minted,
+        seq: storage::next_event_seq(e),
+    }
+    .publish(e);
+    emit_rounding_loss(e, asset, vault, total, &vec![e, df_tokens_minted]);
+    let batch_id = storage::next_distribution_id(e, caller, asset, vault, recipients);
+    emit_distribution_batch(e, asset, vault, 1, total, df_tokens_minted, batch_id.clone());
+
+    let results = vec![e, (r.address, df_tokens_minted)];
+    record_last_split(e, caller, vault, &results);
+    (batch_id, results)
+}
+
+/// Stores the non-zero `(address, df_tokens)` pairs of `results` as
+/// `caller`'s last split into `vault`, for [`Distributor::repeat_last`].
+fn record_last_split(e: &Env, caller: &Address, vault: &Address, results: &Vec<(Address, i128)>) {
+    let mut split: Vec<Recipient> = vec![e];
+    for (address, df_tokens) in results.iter() {
+        if df_tokens > 0 {
+            split.push_back(Recipient { address, amount: df_tokens });
+        }
+    }
+    if !split.is_empty() {
+        storage::set_last_split(e, caller, vault, &split);
+    }
+}
+
+/// Validates `recipients` (non-empty, positive amounts, valid and unique
+/// addresses) and returns the sum of their amounts.
+///
+/// With `assume_sorted`, duplicates are found by comparing each address with
+/// its predecessor, which also enforces strictly ascending order.
+fn validate_recipients(e: &Env, recipients: &Vec<Recipient>, assume_sorted: bool) -> i128 {
+    if recipients.is_empty() {
+        panic!("recipients must not be empty");
+    }
+    if recipients.len() < storage::get_min_recipients(e) {
+        panic!("fewer recipients than min_recipients");
+    }
+
+    let min_amount = storage::get_min_amount(e);
+    let invalid = invalid_addresses(e);
+    // A single row cannot repeat, so it skips the dedup `Map`.
+    let single = recipients.len() == 1;
+    let mut seen: Map<Address, ()> = Map::new(e);
+    let mut prev: Option<Address> = None;
+    let mut total: i128 = 0;
+    for (i, r) in recipients.iter().enumerate() {
+        if r.amount <= 0 {
+            panic!("each recipient amount must be positive");
+        }
+        if r.amount < min_amount {
+            panic!("recipient {} amount below min_amount", i);
+        }
+        if invalid.contains(&r.address) {
+            panic!("recipient {} address is invalid", i);
+        }
+        if assume_sorted {
+            if let Some(p) = prev {
+                if r.address == p {
+                    panic!("duplicate recipient address");
+                }
+                if r.address < p {
+                    panic!("recipients are not sorted by address");
+                }
+            }
+            prev = Some(r.address.clone());
+        } else if !single {
+            if seen.contains_key(r.address.clone()) {
+                panic!("duplicate recipient address");
+            }
+            seen.set(r.address.clone(), ());
+        }
+        total = match total.checked_add(r.amount) {
+            Some(v) => v,
+            None => panic!("total overflow"),
+        };
+    }
+    total
+}
+
+/// Panics with `"<name> address is invalid"` if `to`, an address that
+/// receives a payout outside the recipient list, is one of
+/// [`invalid_addresses`].
+fn require_valid_payout(e: &Env, to: &Address, name: &str) {
+    if invalid_addresses(e).contains(to) {
+        panic!("{} address is invalid", name);
+    }
+}
+
+/// Addresses no recipient may have: the zero placeholders and this contract.
+fn invalid_addresses(e: &Env) -> Vec<Address> {
+    vec![
+        e,
+        Address::from_str(e, ZERO_ACCOUNT),
+        Address::from_str(e, ZERO_CONTRACT),
+        e.current_contract_address(),
+    ]
+}
+
+/// Pulls `total` of `asset` from `caller` into this contract and deposits it
+/// into `vault`.  Returns the df tokens the vault minted to this contract.
+fn deposit_from_caller(
+    e: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    total: i128,
+) -> i128 {
+    // Pull underlying asset from caller into this contract
+    let asset_token = TokenClient::new(e, asset);
+    asset_token.transfer(caller, &e.current_contract_address(), &total);
+
+    deposit_held(e, caller, asset, vault, total)
+}
+
+/// Deposits `total` of `asset` already held by this contract into `vault` on
+/// behalf of `caller`.  Returns the df tokens the vault minted to this
+/// contract.
+fn deposit_held(e: &Env, caller: &Address, asset: &Address, vault: &Address, total: i128) -> i128 {
+    deposit_held_with(e, caller, asset, vault, total, true)
+}
+
+/// [`deposit_held`] with an explicit `invest` flag: false leaves the deposit
+/// idle in the vault instead of pushing it to its strategies.
+fn deposit_held_with(
+    e: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    total: i128,
+    invest: bool,
+) -> i128 {
+    if let Some(max) = storage::get_max_deposit(e, vault) {
+        if total > max {
+            panic!("total exceeds vault max deposit");
+        }
+    }
+
+    // The vault pulls `total` of the underlying asset from this contract and
+    // mints df tokens back to this contract.
+    let vault_client = vault::Client::new(e, vault);
+    // Snapshot before authorizing: the auth entries only cover the next call.
+    let supply_before = vault_client.total_supply();
+
+    e.authorize_as_current_contract(vec![
+        e,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: asset.clone(),
+                fn_name: Symbol::new(e, "transfer"),
+                args: (
+                    e.current_contract_address(),
+                    vault.clone(),
+                    total,
+                )
+                    .into_val(e),
+            },
+            sub_invocations: vec![e],
+        }),
+    ]);
+
+    let (_deposited, df_tokens_minted, _allocs) = vault_client.deposit(
+        &vec![e, total],
+        &vec![e, total],
+        &e.current_contract_address(),
+        &invest,
+    );
+    // A vault that mints to another account, or reports a different mint
+    // than it made, leaves the supply delta out of line with its report.
+    if vault_client.total_supply() - supply_before != df_tokens_minted {
+        panic!("vault supply change does not match minted df tokens");
+    }
+    record_deposit(e, caller, asset, vault, total, df_tokens_minted);
+
+    df_tokens_minted
+}
+
+/// Post-deposit checks and bookkeeping for `total` of `asset` deposited into
+/// `vault` for `caller`'s distribution: the price-bounds and accounting
+/// checks, volume, the distribution record and its audit-log entry.
+fn record_deposit(
+    e: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    total: i128,
+    df_tokens_minted: i128,
+) {
+    let vault_client = vault::Client::new(e, vault);
+    if let Some((min_price, max_price)) = storage::get_price_bounds(e, vault) {
+        let price = vault_client
+            .get_asset_amounts_per_shares(&PRICE_PROBE_SHARES)
+            .get(0)
+            .unwrap_or(0);
+        if price < min_price || price > max_price {
+            panic!("vault share price out of bounds");
+        }
+    }
+    if let Some(tolerance) = storage::get_accounting_tolerance(e, vault) {
+        require_consistent_accounting(&vault_client, tolerance);
+    }
+    storage::add_asset_volume(e, asset, total);
+    let id = storage::record_distribution(
+        e,
+        &DistributionRecord {
+            asset: asset.clone(),
+            vault: vault.clone(),
+            underlying: total,
+            df_tokens: df_tokens_minted,
+        },
+    );
+    storage::set_audit_entry(
+        e,
+        &DistributionSummary {
+            id,
+            caller: caller.clone(),
+            asset: asset.clone(),
+            vault: vault.clone(),
+            total,
+            df_minted: df_tokens_minted,
+            ledger: e.ledger().sequence(),
+        },
+    );
+}
+
+/// Panics if, for any asset, the vault's reported total differs from its idle
+/// balance plus strategy allocations by more than `tolerance`.
+fn require_consistent_accounting(vault_client: &vault::Client, tolerance: i128) {
+    for funds in vault_client.fetch_total_managed_funds().iter() {
+        let mut sum = funds.idle_amount;
+        for allocation in funds.strategy_allocations.iter() {
+            sum = match sum.checked_add(allocation.amount) {
+                Some(v) => v,
+                None => panic!("vault balances overflow"),
+            };
+        }
+        let gap = match funds.total_amount.checked_sub(sum).and_then(i128::checked_abs) {
+            Some(v) => v,
+            None => panic!("vault balances overflow"),
+        };
+        if gap > tolerance {
+            panic!("vault accounting is inconsistent");
+        }
+    }
+}
+
+/// Swaps exactly `amount_in` of `path[0]` held by this contract along `path`
+/// through the Soroswap `router`, with the output sent back to this contract.
+/// Returns the amount of `path[last]` received (at least `min_out`).
+fn swap_exact_in(
+    e: &Env,
+    router: &Address,
+    path: &Vec<Address>,
+    amount_in: i128,
+    min_out: i128,
+) -> i128 {
+    if path.len() < 2 {
+        panic!("swap path must have at least two assets");
+    }
+    let router_client = soroswap_router::Client::new(e, router);
+    let token_in = path.get_unchecked(0);
+    let first_pair = router_client.router_pair_for(&token_in, &path.get_unchecked(1));
+
+    // The router pulls `amount_in` from this contract into the first pair.
+    e.authorize_as_current_contract(vec![
+        e,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token_in,
+                fn_name: Symbol::new(e, "transfer"),
+                args: (e.current_contract_address(), first_pair, amount_in).into_val(e),
+            },
+            sub_invocations: vec![e],
+        }),
+    ]);
+
+    // The router requires `deadline > now`; the swap executes in this ledger.
+    let deadline = e.ledger().timestamp() + 1;
+    let amounts = router_client.swap_exact_tokens_for_tokens(
+        &amount_in,
+        &min_out,
+        path,
+        &e.current_contract_address(),
+        &deadline,
+    );
+    let amount_out = amounts.last().unwrap_or(0);
+    if amount_out < min_out {
+        panic!("swap output below min_out");
+    }
+    amount_out
+}
+
+/// df tokens owed to a recipient who contributed `amount` of `total`.
+///
+/// Non-last recipients get `floor(amount * df_tokens_minted / total)`; the
+/// last recipient gets whatever is left (`df_tokens_minted - distributed`) so
+/// no dust is lost.
+fn compute_share(
+    e: &Env,
+    amount: i128,
+    total: i128,
+    df_tokens_minted: i128,
+    distributed: i128,
+    is_last: bool,
+) -> i128 {
+    if is_last {
+        match df_tokens_minted.checked_sub(distributed) {
+            Some(v) => v,
+            None => panic!("underflow distributing last recipient"),
+        }
+    } else {
+        amount.fixed_div_floor(e, &total, &df_tokens_minted)
+    }
+}
+
+/// Subdivides group `index`'s `group_total` among `members` by bps; the last
+/// member absorbs the rounding remainder.
+fn split_group(
+    e: &Env,
+    index: u32,
+    group_total: i128,
+    members: &Vec<RecipientBps>,
+) -> Vec<Recipient> {
+    if group_total <= 0 {
+        panic!("group {} total must be positive", index);
+    }
+    if members.is_empty() {
+        panic!("group {} has no members", index);
+    }
+    if bps_sum(members) != BPS_DENOMINATOR {
+        panic!("group {} bps must sum to 10000", index);
+    }
+    split_by_bps(e, group_total, members)
+}
+
+/// Sum of `members`' bps.
+fn bps_sum(members: &Vec<RecipientBps>) -> i128 {
+    members.iter().map(|m| m.bps as i128).sum()
+}
+
+/// Splits `group_total` among `members` by bps, which must sum to
+/// [`BPS_DENOMINATOR`]; the last member absorbs the rounding remainder.
+fn split_by_bps(e: &Env, group_total: i128, members: &Vec<RecipientBps>) -> Vec<Recipient> {
+    let mut assigned: i128 = 0;
+    let mut split: Vec<Recipient> = vec![e];
+    for (i, m) in members.iter().enumerate() {
+        let amount = if i as u32 + 1 == members.len() {
+            group_total - assigned
+        } else {
+            group_total.fixed_mul_floor(e, &(m.bps as i128), &BPS_DENOMINATOR)
+        };
+        assigned = match assigned.checked_add(amount) {
+            Some(v) => v,
+            None => panic!("assigned overflow"),
+        };
+        split.push_back(Recipient { address: m.address, amount });
+    }
+    split
+}
+
+/// Returns `recipients` without its zero-amount rows, publishing a
+/// `RecipientRejected` event for each row dropped.  Negative amounts panic.
+fn drop_zero_amounts(e: &Env, recipients: &Vec<Recipient>) -> Vec<Recipient> {
+    let mut kept: Vec<Recipient> = vec![e];
+    for (i, r) in recipients.iter().enumerate() {
+        if r.amount < 0 {
+            panic!("each recipient amount must be positive");
+        }
+        if r.amount > 0 {
+            kept.push_back(r);
+        } else {
+            events::RecipientRejected {
+                index: i as u32,
+                user: r.address,
+                reason: RejectReason::ZeroAmount,
+            }
+            .publish(e);
+        }
+    }
+    kept
+}
+
+/// Re-expands `results` (computed over the non-zero rows of `input`) so it
+/// is index-aligned with `input`, with `(address, 0)` for each zero row.
+fn with_skipped_rows(
+    e: &Env,
+    input: &Vec<Recipient>,
+    results: &Vec<(Address, i128)>,
+) -> Vec<(Address, i128)> {
+    let mut aligned: Vec<(Address, i128)> = vec![e];
+    let mut paid = results.iter();
+    for r in input.iter() {
+        if r.amount == 0 {
+            aligned.push_back((r.address, 0));
+        } else {
+            match paid.next() {
+                Some(p) => aligned.push_back(p),
+                None => panic!("results shorter than non-zero rows"),
+            }
+        }
+    }
+    aligned
+}
+
+/// Records `paid` df tokens leaving the chunked batch `dist`, moves
+/// `next_index` past any chunks already paid, and deletes the batch once no
+/// chunk is pending, recording its split for [`Distributor::repeat_last`].
+fn save_chunk_progress(e: &Env, batch_id: &BytesN<32>, mut dist: ChunkedDistribution, paid: i128) {
+    storage::add_escrow_held(e, &dist.asset, &dist.vault, -paid);
+    if dist.pending.is_empty() {
+        let mut results: Vec<(Address, i128)> = vec![e];
+        for (r, share) in dist.recipients.iter().zip(dist.shares.iter()) {
+            results.push_back((r.address, share));
+        }
+        record_last_split(e, &dist.caller, &dist.vault, &results);
+        storage::remove_chunked(e, batch_id);
+        return;
+    }
+    dist.distributed_so_far = match dist.distributed_so_far.checked_add(paid) {
+        Some(v) => v,
+        None => panic!("distributed overflow"),
+    };
+    while dist.next_index < dist.recipients.len()
+        && !dist.pending.contains(dist.next_index / MAX_RECIPIENTS_PER_TX)
+    {
+        let chunk_end = (dist.next_index / MAX_RECIPIENTS_PER_TX + 1) * MAX_RECIPIENTS_PER_TX;
+        dist.next_index = dist.recipients.len().min(chunk_end);
+    }
+    storage::set_chunked(e, batch_id, &dist);
+}
+
+/// Pays recipients `start..end` of `dist` their precomputed shares.
+/// Returns their `(address, df_tokens)` and the df tokens paid in total.
+fn pay_range(
+    e: &Env,
+    dist: &ChunkedDistribution,
+    start: u32,
+    end: u32,
+) -> (Vec<(Address, i128)>, i128) {
+    let mut results: Vec<(Address, i128)> = vec![e];
+    let mut paid: i128 = 0;
+    for i in start..end {
+        let r = dist.recipients.get_unchecked(i);
+        let user_df = dist.shares.get_unchecked(i);
+        pay_df(e, &dist.vault, &r.address, user_df);
+        events::Distributed {
+            asset: dist.asset.clone(),
+            vault: dist.vault.clone(),
+            user: r.address.clone(),
+            underlying_amount: r.amount,
+            df_tokens: user_df,
+            seq: storage::next_event_seq(e),
+        }
+        .publish(e);
+        paid = match paid.checked_add(user_df) {
+            Some(v) => v,
+            None => panic!("distributed overflow"),
+        };
+        results.push_back((r.address, user_df));
+    }
+    (results, paid)
+}
+
+/// Splits `df_tokens_minted` across `recipients` pro-rata to their amounts.
+/// The returned Vec is index-aligned with `recipients`.
+fn split_shares(
+    e: &Env,
+    recipients: &Vec<Recipient>,
+    total: i128,
+    df_tokens_minted: i128,
+) -> Vec<i128> {
+    split_shares_to(e, recipients, total, df_tokens_minted, recipients.len() - 1)
+}
+
+/// [`split_shares`] with the rounding remainder going to the recipient at
+/// index `absorber` instead of the last one.
+fn split_shares_to(
+    e: &Env,
+    recipients: &Vec<Recipient>,
+    total: i128,
+    df_tokens_minted: i128,
+    absorber: u32,
+) -> Vec<i128> {
+    let mut distributed: i128 = 0;
+    let mut shares: Vec<i128> = vec![e];
+
+    // Indices stay u32, the type of `Vec::len`, so no cast can truncate.
+    for (i, r) in (0..recipients.len()).zip(recipients.iter()) {
+        // The absorber's share depends on everyone else's, so it is filled in last.
+        let user_df = if i == absorber {
+            0
+        } else {
+            compute_share(e, r.amount, total, df_tokens_minted, distributed, false)
+        };
+        distributed = match distributed.checked_add(user_df) {
+            Some(v) => v,
+            None => panic!("distributed overflow"),
+        };
+        shares.push_back(user_df);
+    }
+    shares.set(absorber, compute_share(e, 0, total, df_tokens_minted, distributed, true));
+    shares
+}
+
+/// Pro-rata split with no single absorber: every share is floored, and the
+/// `k` df tokens the floors leave over go one each to the first `k`
+/// recipients.  Each floor is short by less than one token, so `k` is less
+/// than the recipient count.
+fn split_shares_spread(
+    e: &Env,
+    recipients: &Vec<Recipient>,
+    total: i128,
+    df_tokens_minted: i128,
+) -> Vec<i128> {
+    let mut distributed: i128 = 0;
+    let mut shares: Vec<i128> = vec![e];
+    for r in recipients.iter() {
+        let user_df = compute_share(e, r.amount, total, df_tokens_minted, distributed, false);
+        distributed = match distributed.checked_add(user_df) {
+            Some(v) => v,
+            None => panic!("distributed overflow"),
+        };
+        shares.push_back(user_df);
+    }
+    let remainder = compute_share(e, 0, total, df_tokens_minted, distributed, true);
+    for i in 0..(remainder as u32) {
+        let bumped = match shares.get(i) {
+            Some(v) => v + 1,
+            None => panic!("remainder exceeds recipient count"),
+        };
+        shares.set(i, bumped);
+    }
+    shares
+}
+
+/// Panics if any recipient's amount is more than `max_bps` basis points of
+/// `total`.  `ceil(amount * 10_000 / total) > max_bps` exactly when the
+/// unrounded fraction exceeds it.
+fn require_max_concentration(e: &Env, recipients: &Vec<Recipient>, total: i128, max_bps: u32) {
+    if max_bps as i128 > BPS_DENOMINATOR {
+        panic!("max_single_bps must be at most 10_000");
+    }
+    for r in recipients.iter() {
+        if r.amount.fixed_mul_ceil(e, &BPS_DENOMINATOR, &total) > max_bps as i128 {
+            panic!("recipient exceeds max_single_bps");
+        }
+    }
+}
+
+/// Panics if any recipient's current `share_token` balance plus their share
+/// would exceed `cap`.
+fn require_within_balance_cap(
+    e: &Env,
+    share_token: &Address,
+    recipients: &Vec<Recipient>,
+    shares: &Vec<i128>,
+    cap: i128,
+) {
+    let token = TokenClient::new(e, share_token);
+    for (r, share) in recipients.iter().zip(shares.iter()) {
+        let after = match token.balance(&r.address).checked_add(share) {
+            Some(v) => v,
+            None => panic!("recipient balance overflow"),
+        };
+        if after > cap {
+            panic!("recipient balance would exceed max_balance");
+        }
+    }
+}
+
+/// `refund + df_tokens` for best-effort refunds, panicking on overflow.
+fn add_refund(refund: i128, df_tokens: i128) -> i128 {
+    match refund.checked_add(df_tokens) {
+        Some(v) => v,
+        None => panic!("refund overflow"),
+    }
+}
+
+/// Integer square root: the largest `r` with `r * r <= n`, for `n >= 0`.
+/// Newton's iteration from `n` itself decreases monotonically to the floor
+/// root, so the result is exact and deterministic.  Its first step is
+/// `ceil(n / 2)`, written so it cannot overflow at `i128::MAX`.
+fn isqrt(n: i128) -> i128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = n / 2 + n % 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Post-condition on a split of `pool` df tokens: `absorber`'s share may
+/// exceed its own floored pro-rata share by at most one df token per other
+/// recipient, the most consistent floors can leave, and by no more than
+/// `max_dust` when the caller sets a tighter bound.
+fn check_dust(
+    e: &Env,
+    recipients: &Vec<Recipient>,
+    total: i128,
+    pool: i128,
+    shares: &Vec<i128>,
+    absorber: u32,
+    max_dust: Option<i128>,
+) {
+    let absorber_floor = match recipients.get(absorber) {
+        Some(r) => r.amount.fixed_div_floor(e, &total, &pool),
+        None => panic!("absorber out of range"),
+    };
+    let dust = shares.get(absorber).unwrap_or(0) - absorber_floor;
+    if dust > (recipients.len() - 1) as i128 {
+        panic!("rounding remainder exceeds one token per recipient");
+    }
+    if let Some(max_dust) = max_dust {
+        if dust > max_dust {
+            panic!("rounding remainder exceeds max_dust");
+        }
+    }
+}
+
+/// Rounds every share but `absorber`'s down to a multiple of `increment`,
+/// moving the freed df tokens to `absorber` so the total is unchanged.
+fn round_to_increment(shares: &Vec<i128>, absorber: u32, increment: i128) -> Vec<i128> {
+    let mut rounded = shares.clone();
+    let mut freed: i128 = 0;
+    for (i, share) in (0..shares.len()).zip(shares.iter()) {
+        if i != absorber {
+            let excess = share % increment;
+            rounded.set(i, share - excess);
+            freed = match freed.checked_add(excess) {
+                Some(v) => v,
+                None => panic!("freed overflow"),
+            };
+        }
+    }
+    let absorbed = match shares.get(absorber) {
+        Some(share) => match share.checked_add(freed) {
+            Some(v) => v,
+            None => panic!("absorber share overflow"),
+        },
+        None => panic!("absorber out of range"),
+    };
+    rounded.set(absorber, absorbed);
+    rounded
+}
+
+/// Index of the recipient with the largest address.
+fn largest_address_index(recipients: &Vec<Recipient>) -> u32 {
+    let mut largest: u32 = 0;
+    for (i, r) in (0..recipients.len()).zip(recipients.iter()) {
+        if let Some(current) = recipients.get(largest) {
+            if r.address > current.address {
+                largest = i;
+            }
+        }
+    }
+    largest
+}
+
+/// BLND emissions `strategy` could claim from the Blend `pool` right now on
+/// its b-tokens of the strategy's asset, mirroring the pool's own accrual:
+/// the reserve's emission index is brought up to the current time, and the
+/// strategy earns its balance times the index growth since its last update.
+fn pending_emissions(e: &Env, pool: &Address, strategy: &Address) -> i128 {
+    const SCALAR_7: i128 = 1_0000000;
+
+    let pool_client = blend_pool::Client::new(e, pool);
+    let asset = strategy::StrategyClient::new(e, strategy).asset();
+    let reserve = pool_client.get_reserve(&asset);
+    let token_id = reserve.config.index * 2 + 1;
+    let user = match pool_client.get_user_emissions(strategy, &token_id) {
+        Some(u) => u,
+        None => return 0,
+    };
+    let mut index = match pool_client.get_reserve_emissions(&token_id) {
+        Some(data) => {
+            let now = e.ledger().timestamp().min(data.expiration);
+            if now > data.last_time && data.eps > 0 && reserve.data.b_supply > 0 {
+                let elapsed = ((now - data.last_time) as i128) * (data.eps as i128);
+                data.index + elapsed.fixed_div_floor(e, &reserve.data.b_supply, &reserve.scalar)
+            } else {
+                data.index
+            }
+        }
+        None => return user.accrued,
+    };
+    if index < user.index {
+        index = user.index;
+    }
+
+    let positions = pool_client.get_positions(strategy);
+    let balance = positions.supply.get(reserve.config.index).unwrap_or(0)
+        + positions.collateral.get(reserve.config.index).unwrap_or(0);
+    let earned = balance.fixed_mul_floor(e, &(index - user.index), &(reserve.scalar * SCALAR_7));
+    match user.accrued.checked_add(earned) {
+        Some(v) => v,
+        None => panic!("pending emissions overflow"),
+    }
+}
+
+/// Takes the distribution lock for an entry point that calls into a vault,
+/// panicking if one is already held.  Soroban already rejects contract
+/// re-entry, so a vault or hook cannot call back into this contract; the lock
+/// makes the invariant explicit and keeps admin setters from interleaving
+/// should that ever change.  Released by [`end_distribution`]; a panic in
+/// between reverts it with everything else.
+fn begin_distribution(e: &Env) {
+    if storage::is_distributing(e) {
+        panic!("distribution in progress");
+    }
+    storage::set_distributing(e, true);
+}
+
+/// Releases the lock taken by [`begin_distribution`].
+fn end_distribution(e: &Env) {
+    storage::set_distributing(e, false);
+}
+
+/// Panics if distributions are paused globally or for `asset`.
+fn require_not_paused(e: &Env, asset: &Address) {
+    if storage::is_paused(e) {
+        panic!("distributions are paused");
+    }
+    if storage::is_asset_paused(e, asset) {
+        panic!("asset is paused");
+    }
+}
+
+/// Panics if the live escrow for `asset`, valued in underlying at each
+/// vault's current price, exceeds its configured cap.
+fn check_escrow_cap(e: &Env, asset: &Address) {
+    let cap = match storage::get_escrow_cap(e, asset) {
+        Some(c) => c,
+        None => return,
+    };
+    let mut value: i128 = 0;
+    for (vault, df_held) in storage::get_escrow_held(e, asset).iter() {
+        let (managed, supply) = vault_price(e, &vault);
+        value = match value.checked_add(df_held.fixed_mul_floor(e, &managed, &supply)) {
+            Some(v) => v,
+            None => panic!("escrow value overflow"),
+        };
+    }
+    if value > cap {
+        panic!("escrow cap exceeded");
+    }
+}
+
+/// Redeems `shares` df tokens held by this contract through the vault and
+/// returns the underlying received, which stays in this contract.
+fn withdraw_held(e: &Env, vault: &Address, shares: i128, min_out: i128) -> i128 {
+    let amounts = vault::Client::new(e, vault).withdraw(
+        &shares,
+        &vec![e, min_out],
+        &e.current_contract_address(),
+    );
+    let out = match amounts.get(0) {
+        Some(v) => v,
+        None => panic!("vault returned no asset amounts"),
+    };
+    if out < min_out {
+        panic!("withdraw output below min_underlying_out");
+    }
+    out
+}
+
+/// The account address whose ed25519 master key is `key`.
+///
+/// Decodes the `ScVal::Address(ScAddress::Account(PublicKeyTypeEd25519))`
+/// XDR directly.  The SDK's `Address::from_payload` does the same but sits
+/// behind the `hazmat-address` feature, which this contract does not enable.
+fn account_address(e: &Env, key: &BytesN<32>) -> Address {
+    let mut xdr = Bytes::from_array(e, &[0, 0, 0, 18, 0, 0, 0, 0, 0, 0, 0, 0]);
+    xdr.append(&Bytes::from(key));
+    match Address::from_xdr(e, &xdr) {
+        Ok(address) => address,
+        Err(_) => panic!("invalid account key"),
+    }
+}
+
+/// Publishes the `DistributionBatch` summary and `DistributionCost` receipt
+/// that close every distribution mode, under `batch_id`.
+fn emit_distribution_batch(
+    e: &Env,
+    asset: &Address,
+    vault: &Address,
+    recipients: u32,
+    total_underlying: i128,
+    df_tokens: i128,
+    batch_id: BytesN<32>,
+) {
+    events::DistributionBatch {
+        asset: asset.clone(),
+        vault: vault.clone(),
+        recipients,
+        total_underlying,
+        df_tokens,
+        batch_id,
+    }
+    .publish(e);
+    emit_distribution_cost(e, vault, recipients);
+}
+
+/// Returns the vault's (single) underlying asset.
+fn vault_underlying(e: &Env, vault: &Address) -> Address {
+    match vault::Client::new(e, vault).get_assets().get(0) {
+        Some(a) => a.address,
+        None => panic!("vault has no assets"),
+    }
+}
+
+/// Returns the vault's current share price as `(managed, supply)`: the total
+/// underlying `M` backing the total df-token supply `S`.
+fn vault_price(e: &Env, vault: &Address) -> (i128, i128) {
+    let vault_client = vault::Client::new(e, vault);
+    let supply = vault_client.total_supply();
+    if supply <= 0 {
+        panic!("vault has no supply");
+    }
+    let managed = match vault_client.get_asset_amounts_per_shares(&supply).get(0) {
+        Some(v) => v,
+        None => panic!("vault returned no asset amounts"),
+    };
+    (managed, supply)
+}
+
+/// [`vault_price`], or `None` if the vault's valuation queries revert or
+/// report no supply.
+fn try_vault_price(e: &Env, vault: &Address) -> Option<(i128, i128)> {
+    let vault_client = vault::Client::new(e, vault);
+    let supply = match vault_client.try_total_supply() {
+        Ok(Ok(s)) if s > 0 => s,
+        _ => return None,
+    };
+    match vault_client.try_get_asset_amounts_per_shares(&supply) {
+        Ok(Ok(amounts)) => amounts.get(0).map(|managed| (managed, supply)),
+        _ => None,
+    }
+}
+
+/// Publishes a `DistributionCost` receipt for a distribution paying
+/// `recipients` recipients, priced at [`INSTRUCTIONS_PER_RECIPIENT`].
+fn emit_distribution_cost(e: &Env, vault: &Address, recipients: u32) {
+    events::DistributionCost {
+        vault: vault.clone(),
+        recipients,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT,
+        estimated_instructions: (recipients as u64) * INSTRUCTIONS_PER_RECIPIENT,
+    }
+    .publish(e);
+}
+
+/// Panics if `df_tokens_minted` shares of `vault` are worth less than the
+/// `deposited` underlying.  Flooring the mint forfeits less than one share, so
+/// the check values `df_tokens_minted + 1` shares: only a genuine loss leaves
+/// even that short of `deposited`.
+fn require_no_loss(e: &Env, vault: &Address, deposited: i128, df_tokens_minted: i128) {
+    let probe = match df_tokens_minted.checked_add(1) {
+        Some(v) => v,
+        None => panic!("df tokens overflow"),
+    };
+    // Value only the shares this deposit minted.  Passing the vault's total
+    // supply instead would weigh the whole vault against this one deposit
+    // and pass vacuously; the probe is pinned by a mock-vault test.
+    let value = vault::Client::new(e, vault)
+        .get_asset_amounts_per_shares(&probe)
+        .get(0)
+        .unwrap_or(0);
+    if value < deposited {
+        panic!("minted shares are worth less than the deposit");
+    }
+}
+
+/// Publishes a `RoundingLoss` event for a distribution of `deposited`
+/// underlying split into `shares`.
+///
+/// Each share is valued as `floor(share * M / S)`, the vault's own
+/// redemption formula, using a single price read instead of one vault call
+/// per recipient.
+fn emit_rounding_loss(
+    e: &Env,
+    asset: &Address,
+    vault: &Address,
+    deposited: i128,
+    shares: &Vec<i128>,
+) {
+    emit_rounding_loss_at(e, asset, vault, deposited, shares, vault_price(e, vault));
+}
+
+/// [`emit_rounding_loss`] valuing shares at a given `(managed, supply)` price.
+fn emit_rounding_loss_at(
+    e: &Env,
+    asset: &Address,
+    vault: &Address,
+    deposited: i128,
+    shares: &Vec<i128>,
+    (managed, supply): (i128, i128),
+) {
+    let mut recoverable: i128 = 0;
+    for share in shares.iter() {
+        recoverable = match recoverable.checked_add(share.fixed_mul_floor(e, &managed, &supply)) {
+            Some(v) => v,
+            None => panic!("recoverable overflow"),
+        };
+    }
+
+    events::RoundingLoss {
+        vault: vault.clone(),
+        asset: asset.clone(),
+        deposited,
+        recoverable,
+    }
+    .publish(e);
+}
+
+/// Pays the configured referral rebate out of `df_tokens_minted` to `to`
+/// and returns it; the remainder is left for the recipients.
+fn pay_referral_rebate(
+    e: &Env,
+    vault: &Address,
+    share_token: &Address,
+    to: &Address,
+    df_tokens_minted: i128,
+) -> i128 {
+    let bps = storage::get_referral_rebate(e) as i128;
+    let rebate = df_tokens_minted.fixed_mul_floor(e, &bps, &BPS_DENOMINATOR);
+    if rebate > 0 {
+        pay_shares(e, vault, share_token, to, rebate);
+        events::ReferralRebate { vault: vault.clone(), to: to.clone(), df_tokens: rebate }
+            .publish(e);
+    }
+    rebate
+}
+
+/// Sends the underlying fee on `total` of `asset`, held by this contract, to
+/// the fee recipient.  Returns the fee withheld (0 if none is set).
+fn withhold_underlying_fee(e: &Env, asset: &Address, total: i128) -> i128 {
+    let UnderlyingFeeConfig { to, bps } = match storage::get_underlying_fee(e) {
+        Some(fee) => fee,
+        None => return 0,
+    };
+    let fee = total.fixed_mul_floor(e, &(bps as i128), &BPS_DENOMINATOR);
+    if fee > 0 {
+        TokenClient::new(e, asset).transfer(&e.current_contract_address(), &to, &fee);
+        events::UnderlyingFee { asset: asset.clone(), to, amount: fee }.publish(e);
+    }
+    fee
+}
+
+/// Notifies `hook` of a completed distribution.  With `failure_ok`, a
+/// failing hook only emits `HookFailed`; otherwise its failure panics.
+fn call_hook(
+    e: &Env,
+    hook: &Address,
+    vault: &Address,
+    total: i128,
+    df_minted: i128,
+    recipient_count: u32,
+    failure_ok: bool,
+) {
+    let client = hook::HookClient::new(e, hook);
+    if !failure_ok {
+        client.on_distributed(vault, &total, &df_minted, &recipient_count);
+        return;
+    }
+    let outcome = client.try_on_distributed(vault, &total, &df_minted, &recipient_count);
+    if !matches!(outcome, Ok(Ok(()))) {
+        events::HookFailed { hook: hook.clone(), vault: vault.clone() }.publish(e);
+    }
+}
+
+/// Transfers `amount` df tokens to a recipient and counts them towards the
+/// vault's lifetime [`Distributor::df_distributed`].
+fn pay_df(e: &Env, vault: &Address, to: &Address, amount: i128) {
+    pay_shares(e, vault, vault, to, amount);
+}
+
+/// [`pay_df`] for a vault whose shares are held in `share_token`, which is
+/// the vault itself unless the vault uses a separate share-token contract.
+fn pay_shares(e: &Env, vault: &Address, share_token: &Address, to: &Address, amount: i128) {
+    transfer_df(e, share_token, to, amount);
+    storage::add_df_distributed(e, vault, amount);
+}
+
+/// Transfers `amount` df tokens held by this contract to `to`.
+fn transfer_df(e: &Env, share_token: &Address, to: &Address, amount: i128) {
+    authorize_df_transfer(e, share_token, to, amount);
+    TokenClient::new(e, share_token).transfer(&e.current_contract_address(), to, &amount);
+}
+
+/// [`transfer_df`] that reports a failed transfer instead of reverting.
+/// Returns whether the df tokens left this contract.
+fn try_transfer_df(e: &Env, share_token: &Address, to: &Address, amount: i128) -> bool {
+    authorize_df_transfer(e, share_token, to, amount);
+    matches!(
+        TokenClient::new(e, share_token).try_transfer(&e.current_contract_address(), to, &amount),
+        Ok(Ok(()))
+    )
+}
+
+/// Authorises this contract's next call, a df-token transfer of `amount`
+/// to `to`.
+fn authorize_df_transfer(e: &Env, share_token: &Address, to: &Address, amount: i128) {
+    // df tokens are held by this contract (the vault minted them to
+    // e.current_contract_address()); the df token is normally the vault
+    // itself.
+    //
+    // Entries passed to `authorize_as_current_contract` only cover the
+    // invocation tree of this contract's *next* call (the transfer) and are
+    // dropped when it returns, even if it fails.  They are re-issued per
+    // transfer, so an authorisation granted for one recipient cannot be
+    // replayed by any later call (e.g. a stake contract).
+    e.authorize_as_current_contract(vec![
+        e,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: share_token.clone(),
+                fn_name: Symbol::new(e, "transfer"),
+                args: (
+                    e.current_contract_address(),
+                    to.clone(),
+                    amount,
+                )
+                    .into_val(e),
+            },
+            sub_invocations: vec![e],
+        }),
+    ]);
+}
+
+/// Deposits `recipients`' total and records their shares as a new escrow
+/// batch owned by `caller`.  Returns the batch id.
+fn escrow_batch(
+    e: &Env,
+    caller: &Address,
+    asset: Address,
+    vault: Address,
+    recipients: &Vec<Recipient>,
+) -> BytesN<32> {
+    let total = validate_recipients(e, recipients, false);
+    let df_tokens_minted = deposit_from_caller(e, caller, &asset, &vault, total);
+    let shares = split_shares(e, recipients, total, df_tokens_minted);
+
+    let expiry = storage::get_escrow_expiry(e);
+    let expiry_ledger = if expiry == 0 {
+        0
+    } else {
+        match e.ledger().sequence().checked_add(expiry) {
+            Some(v) => v,
+            None => panic!("escrow expiry overflow"),
+        }
+    };
+
+    let batch_id = storage::next_batch_id(e, caller, &vault);
+    for (r, user_df) in recipients.iter().zip(shares.iter()) {
+        storage::credit_allocation(e, &batch_id, &vault, &r.address, user_df);
+        storage::set_receipt(
+            e,
+            &r.address,
+            &DistributionReceipt {
+                batch_id: batch_id.clone(),
+                vault: vault.clone(),
+                shares: user_df,
+                ledger: e.ledger().sequence(),
+            },
+        );
+        events::Escrowed {
+            batch_id: batch_id.clone(),
+            vault: vault.clone(),
+            user: r.address.clone(),
+            underlying_amount: r.amount,
+            df_tokens: user_df,
+        }
+        .publish(e);
+    }
+    emit_rounding_loss(e, &asset, &vault, total, &shares);
+    // The escrow batch id doubles as the distribution id, so the summary
+    // names the batch recipients claim from.
+    emit_distribution_batch(
+        e,
+        &asset,
+        &vault,
+        recipients.len(),
+        total,
+        df_tokens_minted,
+        batch_id.clone(),
+    );
+    storage::add_escrow_held(e, &asset, &vault, df_tokens_minted);
+    check_escrow_cap(e, &asset);
+    storage::set_batch(
+        e,
+        &batch_id,
+        &EscrowBatch {
+            caller: caller.clone(),
+            asset,
+            vault,
+            df_remaining: df_tokens_minted,
+            created_ledger: e.ledger().sequence(),
+            claims: 0,
+            canceled: false,
+            expiry_ledger,
+        },
+    );
+
+    storage::add_caller_batch(e, caller, &batch_id);
+
+    batch_id
+}
+
+/// Moves all of `from`'s unclaimed allocations in `vault`'s escrow batches
+/// to `to`, for [`Distributor::reassign`] and
+/// [`Distributor::migrate_recipient`].  `to` gets a receipt for what it now
+/// holds in each batch; `from` keeps its receipt as a record of the original
+/// allocation.
+fn move_allocations(e: &Env, vault: Address, from: Address, to: Address) {
+    if from == to {
+        panic!("cannot reassign to the same address");
+    }
+    require_valid_payout(e, &to, "reassign target");
+
+    let batch_ids = storage::get_user_batches(e, &vault, &from);
+    if batch_ids.is_empty() {
+        panic!("no escrowed allocation to reassign");
+    }
+
+    let mut moved: i128 = 0;
+    for batch_id in batch_ids.iter() {
+        let amount = storage::take_allocation(e, &batch_id, &vault, &from);
+        storage::credit_allocation(e, &batch_id, &vault, &to, amount);
+        let receipt = match storage::get_receipt(e, &to, &batch_id) {
+            Some(held) => match held.shares.checked_add(amount) {
+                Some(shares) => DistributionReceipt { shares, ..held },
+                None => panic!("receipt shares overflow"),
+            },
+            None => DistributionReceipt {
+                batch_id: batch_id.clone(),
+                vault: vault.clone(),
+                shares: amount,
+                ledger: storage::get_batch(e, &batch_id).created_ledger,
+            },
+        };
+        storage::set_receipt(e, &to, &receipt);
+        moved = match moved.checked_add(amount) {
+            Some(v) => v,
+            None => panic!("reassigned overflow"),
+        };
+    }
+
+    events::Reassigned {
+        vault,
+        from,
+        to,
+        df_tokens: moved,
+    }
+    .publish(e);
+}
+
+/// Moves `user`'s allocation in `batch_id` out of escrow to `user`.
+fn release_allocation(e: &Env, user: &Address, batch_id: &BytesN<32>) -> i128 {
+    let mut batch = storage::get_batch(e, batch_id);
+    let amount = storage::take_allocation(e, batch_id, &batch.vault, user);
+    if amount == 0 {
+        panic!("nothing to claim");
+    }
+    batch.df_remaining = match batch.df_remaining.checked_sub(amount) {
+        Some(v) => v,
+        None => panic!("batch remaining underflow"),
+    };
+    batch.claims = match batch.claims.checked_add(1) {
+        Some(v) => v,
+        None => panic!("claims overflow"),
+    };
+    storage::set_batch(e, batch_id, &batch);
+    if batch.df_remaining == 0 {
+        storage::remove_caller_batch(e, &batch.caller, batch_id);
+    }
+
+    storage::add_escrow_held(e, &batch.asset, &batch.vault, -amount);
+    pay_df(e, &batch.vault, user, amount);
+    events::Claimed {
+        batch_id: batch_id.clone(),
+        vault: batch.vault,
+        user: user.clone(),
+        df_tokens: amount,
+    }
+    .publish(e);
+
+    amount
 }
 
 mod events;
+mod storage;
 
 #[cfg(test)]
 mod testutils;
@@ -0,0 +1,882 @@
+use soroban_sdk::{
+    contracttype, vec, xdr::ToXdr, Address, BytesN, Env, IntoVal, Map, Symbol, Val, Vec,
+};
+
+use crate::Recipient;
+
+/// Default instance TTL threshold: one day of ledgers (5 s per ledger).
+pub const DEFAULT_TTL_THRESHOLD: u32 = 17280;
+/// Default instance TTL extension target: one week of ledgers.
+pub const DEFAULT_TTL_EXTEND_TO: u32 = 17280 * 7;
+
+#[contracttype]
+#[derive(Clone)]
+pub enum DataKey {
+    Admin,
+    TtlParams,
+    BatchNonce,
+    Batch(BytesN<32>),
+    Allocation(BytesN<32>, Address),
+    UserBatches(Address, Address),
+    BatchRecipients(BytesN<32>),
+    AssetVolumes,
+    Commitment(Address),
+    EventSeq,
+    MinAmount,
+    EscrowExpiry,
+    EscrowCap(Address),
+    EscrowHeld(Address),
+    Paused,
+    AssetPaused(Address),
+    DistributionCount,
+    Distribution(u64),
+    Budget(Address, Address, Address),
+    CallerBatches(Address),
+    MaxDeposit(Address),
+    StakeOptOut(Address),
+    DfDistributed(Address),
+    PriceBounds(Address),
+    RecipientSet(Symbol),
+    Chunked(BytesN<32>),
+    ReferralRebate,
+    AccountingTolerance(Address),
+    MinRecipients,
+    MaxVaultsPerCall,
+    Residual(Address, Address),
+    AuditEntry(u32),
+    UnderlyingFee,
+    Receipt(Address, BytesN<32>),
+    LastSplit(Address, Address),
+    Distributing,
+    Offer(BytesN<32>),
+}
+
+/// An escrow batch created by `distribute_escrow`.
+///
+/// - `df_remaining`   - df tokens still held by this contract for the batch
+/// - `created_ledger` - ledger sequence at creation
+/// - `claims`         - number of successful claims against the batch
+/// - `canceled`       - set once the caller voids the batch
+/// - `expiry_ledger`  - after this ledger the caller may reclaim unclaimed
+///                      allocations (0 = never expires)
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowBatch {
+    pub caller: Address,
+    pub asset: Address,
+    pub vault: Address,
+    pub df_remaining: i128,
+    pub created_ledger: u32,
+    pub claims: u32,
+    pub canceled: bool,
+    pub expiry_ledger: u32,
+}
+
+/// A distribution too large for one transaction, created by
+/// `distribute_auto` and paid out chunk by chunk.
+///
+/// - `shares`             - df tokens owed to each recipient, index-aligned
+/// - `pending`            - chunk indices not yet fully paid
+/// - `next_index`         - every recipient below this index has been paid
+/// - `distributed_so_far` - df tokens paid out of the batch so far
+#[contracttype]
+#[derive(Clone)]
+pub struct ChunkedDistribution {
+    pub caller: Address,
+    pub asset: Address,
+    pub vault: Address,
+    pub recipients: Vec<Recipient>,
+    pub shares: Vec<i128>,
+    pub pending: Vec<u32>,
+    pub next_index: u32,
+    pub distributed_so_far: i128,
+}
+
+/// Totals recorded for each vault deposit made by a distribution.
+///
+/// - `underlying` - underlying deposited into the vault
+/// - `df_tokens`  - df tokens the vault minted for it
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributionRecord {
+    pub asset: Address,
+    pub vault: Address,
+    pub underlying: i128,
+    pub df_tokens: i128,
+}
+
+/// One distribution as kept in the audit log.
+///
+/// - `id`        - distribution id, as counted by `distribution_count`
+/// - `caller`    - account whose funds were distributed
+/// - `total`     - underlying deposited into the vault
+/// - `df_minted` - df tokens the vault minted for it
+/// - `ledger`    - ledger sequence of the distribution
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributionSummary {
+    pub id: u64,
+    pub caller: Address,
+    pub asset: Address,
+    pub vault: Address,
+    pub total: i128,
+    pub df_minted: i128,
+    pub ledger: u32,
+}
+
+/// A recipient's allocation terms in an escrow batch, as recorded when the
+/// batch was created.
+///
+/// - `shares` - df tokens allocated to the recipient
+/// - `ledger` - ledger sequence at creation
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributionReceipt {
+    pub batch_id: BytesN<32>,
+    pub vault: Address,
+    pub shares: i128,
+    pub ledger: u32,
+}
+
+/// Operator fee taken in underlying, set by `set_underlying_fee`.
+///
+/// - `to`  - receives the fee
+/// - `bps` - basis points of each distributed total
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnderlyingFeeConfig {
+    pub to: Address,
+    pub bps: u32,
+}
+
+/// Instance-TTL extension parameters applied on every state-touching call.
+///
+/// - `threshold` - extend only when the remaining TTL drops below this many ledgers
+/// - `extend_to` - new TTL (in ledgers) after the extension
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TtlParams {
+    pub threshold: u32,
+    pub extend_to: u32,
+}
+
+// ── Admin ─────────────────────────────────────────────────────────────────────
+
+pub fn set_admin(e: &Env, admin: &Address) {
+    e.storage().instance().set(&DataKey::Admin, admin);
+}
+
+pub fn get_admin(e: &Env) -> Address {
+    match e.storage().instance().get(&DataKey::Admin) {
+        Some(a) => a,
+        None => panic!("admin not set"),
+    }
+}
+
+/// Requires `admin` to authorise the current invocation AND to be the stored admin.
+pub fn require_admin(e: &Env, admin: &Address) {
+    admin.require_auth();
+    if *admin != get_admin(e) {
+        panic!("caller is not the admin");
+    }
+    if is_distributing(e) {
+        panic!("distribution in progress");
+    }
+}
+
+/// Whether a distribution is mid-flight.  Held by every entry point that
+/// calls into a vault, from validation to its last event; admin calls refuse
+/// to run while it is set.
+pub fn is_distributing(e: &Env) -> bool {
+    e.storage().instance().has(&DataKey::Distributing)
+}
+
+pub fn set_distributing(e: &Env, on: bool) {
+    if on {
+        e.storage().instance().set(&DataKey::Distributing, &true);
+    } else {
+        e.storage().instance().remove(&DataKey::Distributing);
+    }
+}
+
+/// Minimum per-recipient amount accepted by every distribution (0 = no minimum).
+pub fn get_min_amount(e: &Env) -> i128 {
+    e.storage().instance().get(&DataKey::MinAmount).unwrap_or(0)
+}
+
+pub fn set_min_amount(e: &Env, min_amount: i128) {
+    e.storage().instance().set(&DataKey::MinAmount, &min_amount);
+}
+
+/// Basis points of each distribution's minted df tokens rebated to the
+/// caller or its referral address (0 = no rebate).
+pub fn get_referral_rebate(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::ReferralRebate).unwrap_or(0)
+}
+
+pub fn set_referral_rebate(e: &Env, bps: u32) {
+    e.storage().instance().set(&DataKey::ReferralRebate, &bps);
+}
+
+/// The underlying fee withheld from each distribution (None = no fee).
+pub fn get_underlying_fee(e: &Env) -> Option<UnderlyingFeeConfig> {
+    e.storage().instance().get(&DataKey::UnderlyingFee)
+}
+
+pub fn set_underlying_fee(e: &Env, fee: &Option<UnderlyingFeeConfig>) {
+    match fee {
+        Some(fee) => e.storage().instance().set(&DataKey::UnderlyingFee, fee),
+        None => e.storage().instance().remove(&DataKey::UnderlyingFee),
+    }
+}
+
+/// Fewest recipients accepted by every distribution (default 1).
+pub fn get_min_recipients(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::MinRecipients).unwrap_or(1)
+}
+
+pub fn set_min_recipients(e: &Env, min_recipients: u32) {
+    e.storage().instance().set(&DataKey::MinRecipients, &min_recipients);
+}
+
+/// Most distinct vaults one multi-vault call may touch.
+pub fn get_max_vaults_per_call(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::MaxVaultsPerCall)
+        .unwrap_or(crate::DEFAULT_MAX_VAULTS_PER_CALL)
+}
+
+pub fn set_max_vaults_per_call(e: &Env, max_vaults: u32) {
+    e.storage().instance().set(&DataKey::MaxVaultsPerCall, &max_vaults);
+}
+
+/// Ledgers after creation at which new escrow batches expire (0 = never).
+pub fn get_escrow_expiry(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::EscrowExpiry).unwrap_or(0)
+}
+
+pub fn set_escrow_expiry(e: &Env, ledgers: u32) {
+    e.storage().instance().set(&DataKey::EscrowExpiry, &ledgers);
+}
+
+// ── Pause ─────────────────────────────────────────────────────────────────────
+
+pub fn is_paused(e: &Env) -> bool {
+    e.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+}
+
+pub fn set_paused(e: &Env, paused: bool) {
+    e.storage().instance().set(&DataKey::Paused, &paused);
+}
+
+pub fn is_asset_paused(e: &Env, asset: &Address) -> bool {
+    e.storage()
+        .instance()
+        .has(&DataKey::AssetPaused(asset.clone()))
+}
+
+pub fn set_asset_paused(e: &Env, asset: &Address, paused: bool) {
+    let key = DataKey::AssetPaused(asset.clone());
+    if paused {
+        e.storage().instance().set(&key, &());
+    } else {
+        e.storage().instance().remove(&key);
+    }
+}
+
+// ── TTL ───────────────────────────────────────────────────────────────────────
+
+pub fn get_ttl_params(e: &Env) -> TtlParams {
+    e.storage()
+        .instance()
+        .get(&DataKey::TtlParams)
+        .unwrap_or(TtlParams {
+            threshold: DEFAULT_TTL_THRESHOLD,
+            extend_to: DEFAULT_TTL_EXTEND_TO,
+        })
+}
+
+pub fn set_ttl_params(e: &Env, params: &TtlParams) {
+    e.storage().instance().set(&DataKey::TtlParams, params);
+}
+
+/// Extends the contract instance TTL using the configured (or default) parameters.
+pub fn extend_instance_ttl(e: &Env) {
+    let p = get_ttl_params(e);
+    e.storage().instance().extend_ttl(p.threshold, p.extend_to);
+}
+
+/// Extends a persistent entry's TTL using the same parameters as the instance.
+fn extend_persistent<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    let p = get_ttl_params(e);
+    e.storage().persistent().extend_ttl(key, p.threshold, p.extend_to);
+}
+
+// ── Event sequence ────────────────────────────────────────────────────────────
+
+/// Returns the next global `Distributed` event sequence number and bumps it.
+pub fn next_event_seq(e: &Env) -> u64 {
+    let seq: u64 = e.storage().instance().get(&DataKey::EventSeq).unwrap_or(0);
+    let next = match seq.checked_add(1) {
+        Some(v) => v,
+        None => panic!("event seq overflow"),
+    };
+    e.storage().instance().set(&DataKey::EventSeq, &next);
+    seq
+}
+
+// ── Volume ────────────────────────────────────────────────────────────────────
+
+/// Cumulative underlying deposited for distribution, keyed by asset.  One
+/// entry per distinct asset ever distributed.
+pub fn get_asset_volumes(e: &Env) -> Map<Address, i128> {
+    e.storage()
+        .instance()
+        .get(&DataKey::AssetVolumes)
+        .unwrap_or(Map::new(e))
+}
+
+pub fn add_asset_volume(e: &Env, asset: &Address, amount: i128) {
+    let mut volumes = get_asset_volumes(e);
+    let updated = match volumes.get(asset.clone()).unwrap_or(0).checked_add(amount) {
+        Some(v) => v,
+        None => panic!("asset volume overflow"),
+    };
+    volumes.set(asset.clone(), updated);
+    e.storage().instance().set(&DataKey::AssetVolumes, &volumes);
+}
+
+/// Lifetime df tokens paid out to recipients for `vault`.
+pub fn get_df_distributed(e: &Env, vault: &Address) -> i128 {
+    e.storage()
+        .instance()
+        .get(&DataKey::DfDistributed(vault.clone()))
+        .unwrap_or(0)
+}
+
+pub fn add_df_distributed(e: &Env, vault: &Address, amount: i128) {
+    let updated = match get_df_distributed(e, vault).checked_add(amount) {
+        Some(v) => v,
+        None => panic!("df distributed overflow"),
+    };
+    e.storage()
+        .instance()
+        .set(&DataKey::DfDistributed(vault.clone()), &updated);
+}
+
+// ── Distribution records ──────────────────────────────────────────────────────
+
+/// Number of distributions recorded so far; ids run `0..count`.
+pub fn get_distribution_count(e: &Env) -> u64 {
+    e.storage()
+        .instance()
+        .get(&DataKey::DistributionCount)
+        .unwrap_or(0)
+}
+
+/// Stores `record` under the next distribution id and returns that id.
+pub fn record_distribution(e: &Env, record: &DistributionRecord) -> u64 {
+    let id = get_distribution_count(e);
+    let count = match id.checked_add(1) {
+        Some(v) => v,
+        None => panic!("distribution count overflow"),
+    };
+    e.storage()
+        .instance()
+        .set(&DataKey::DistributionCount, &count);
+    let key = DataKey::Distribution(id);
+    e.storage().persistent().set(&key, record);
+    extend_persistent(e, &key);
+    id
+}
+
+pub fn get_distribution(e: &Env, id: u64) -> DistributionRecord {
+    match e.storage().persistent().get(&DataKey::Distribution(id)) {
+        Some(r) => r,
+        None => panic!("distribution not found"),
+    }
+}
+
+/// Writes `summary` into its audit-log slot, overwriting the entry
+/// [`crate::AUDIT_LOG_CAPACITY`] distributions older.
+pub fn set_audit_entry(e: &Env, summary: &DistributionSummary) {
+    let key = DataKey::AuditEntry((summary.id % crate::AUDIT_LOG_CAPACITY as u64) as u32);
+    e.storage().persistent().set(&key, summary);
+    extend_persistent(e, &key);
+}
+
+pub fn get_audit_entry(e: &Env, id: u64) -> DistributionSummary {
+    let key = DataKey::AuditEntry((id % crate::AUDIT_LOG_CAPACITY as u64) as u32);
+    match e.storage().persistent().get::<_, DistributionSummary>(&key) {
+        Some(s) if s.id == id => s,
+        _ => panic!("audit entry not found"),
+    }
+}
+
+// ── Budgets ───────────────────────────────────────────────────────────────────
+
+/// Remaining underlying `spender` may distribute from `owner`'s funds in `asset`.
+pub fn get_budget(e: &Env, owner: &Address, spender: &Address, asset: &Address) -> i128 {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Budget(owner.clone(), spender.clone(), asset.clone()))
+        .unwrap_or(0)
+}
+
+pub fn set_budget(e: &Env, owner: &Address, spender: &Address, asset: &Address, amount: i128) {
+    let key = DataKey::Budget(owner.clone(), spender.clone(), asset.clone());
+    if amount == 0 {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, &amount);
+        extend_persistent(e, &key);
+    }
+}
+
+// ── Residuals ─────────────────────────────────────────────────────────────────
+
+/// df tokens of `vault` minted beyond what `caller`'s distributions needed
+/// and returned to `caller`, not yet reconciled.
+pub fn get_residual(e: &Env, caller: &Address, vault: &Address) -> i128 {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Residual(caller.clone(), vault.clone()))
+        .unwrap_or(0)
+}
+
+pub fn set_residual(e: &Env, caller: &Address, vault: &Address, amount: i128) {
+    let key = DataKey::Residual(caller.clone(), vault.clone());
+    if amount == 0 {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, &amount);
+        extend_persistent(e, &key);
+    }
+}
+
+// ── Last split ────────────────────────────────────────────────────────────────
+
+/// Recipients of `caller`'s most recent `distribute` into `vault`, each with
+/// the df tokens they received, for `repeat_last`.
+pub fn get_last_split(e: &Env, caller: &Address, vault: &Address) -> Option<Vec<Recipient>> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::LastSplit(caller.clone(), vault.clone()))
+}
+
+pub fn set_last_split(e: &Env, caller: &Address, vault: &Address, split: &Vec<Recipient>) {
+    let key = DataKey::LastSplit(caller.clone(), vault.clone());
+    e.storage().persistent().set(&key, split);
+    extend_persistent(e, &key);
+}
+
+// ── Staking ───────────────────────────────────────────────────────────────────
+
+/// Whether `user` asked to receive shares directly even when a distribution
+/// auto-stakes.
+pub fn get_stake_opt_out(e: &Env, user: &Address) -> bool {
+    e.storage()
+        .persistent()
+        .get(&DataKey::StakeOptOut(user.clone()))
+        .unwrap_or(false)
+}
+
+pub fn set_stake_opt_out(e: &Env, user: &Address, opt_out: bool) {
+    let key = DataKey::StakeOptOut(user.clone());
+    if opt_out {
+        e.storage().persistent().set(&key, &true);
+        extend_persistent(e, &key);
+    } else {
+        e.storage().persistent().remove(&key);
+    }
+}
+
+// ── Recipient sets ────────────────────────────────────────────────────────────
+
+/// The recipients registered under `name`, if any.
+pub fn get_recipient_set(e: &Env, name: &Symbol) -> Option<Vec<Recipient>> {
+    let key = DataKey::RecipientSet(name.clone());
+    let set = e.storage().persistent().get(&key);
+    if set.is_some() {
+        extend_persistent(e, &key);
+    }
+    set
+}
+
+pub fn set_recipient_set(e: &Env, name: &Symbol, recipients: &Vec<Recipient>) {
+    let key = DataKey::RecipientSet(name.clone());
+    e.storage().persistent().set(&key, recipients);
+    extend_persistent(e, &key);
+}
+
+pub fn remove_recipient_set(e: &Env, name: &Symbol) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::RecipientSet(name.clone()));
+}
+
+// ── Chunked distributions ─────────────────────────────────────────────────────
+
+pub fn get_chunked(e: &Env, batch_id: &BytesN<32>) -> ChunkedDistribution {
+    let key = DataKey::Chunked(batch_id.clone());
+    match e.storage().persistent().get(&key) {
+        Some(d) => {
+            extend_persistent(e, &key);
+            d
+        }
+        None => panic!("chunked distribution not found"),
+    }
+}
+
+pub fn set_chunked(e: &Env, batch_id: &BytesN<32>, dist: &ChunkedDistribution) {
+    let key = DataKey::Chunked(batch_id.clone());
+    e.storage().persistent().set(&key, dist);
+    extend_persistent(e, &key);
+}
+
+pub fn remove_chunked(e: &Env, batch_id: &BytesN<32>) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::Chunked(batch_id.clone()));
+}
+
+// ── Commit-reveal ─────────────────────────────────────────────────────────────
+
+pub fn set_commitment(e: &Env, caller: &Address, commitment: &BytesN<32>) {
+    let key = DataKey::Commitment(caller.clone());
+    e.storage().persistent().set(&key, commitment);
+    extend_persistent(e, &key);
+}
+
+/// Removes and returns `caller`'s pending commitment.
+pub fn take_commitment(e: &Env, caller: &Address) -> BytesN<32> {
+    let key = DataKey::Commitment(caller.clone());
+    match e.storage().persistent().get(&key) {
+        Some(c) => {
+            e.storage().persistent().remove(&key);
+            c
+        }
+        None => panic!("no commitment for caller"),
+    }
+}
+
+// ── Deposit limit ─────────────────────────────────────────────────────────────
+
+/// Admin-set ceiling on a single deposit into `vault` (None = no limit).
+pub fn get_max_deposit(e: &Env, vault: &Address) -> Option<i128> {
+    e.storage().instance().get(&DataKey::MaxDeposit(vault.clone()))
+}
+
+pub fn set_max_deposit(e: &Env, vault: &Address, amount: i128) {
+    e.storage()
+        .instance()
+        .set(&DataKey::MaxDeposit(vault.clone()), &amount);
+}
+
+// ── Price bounds ──────────────────────────────────────────────────────────────
+
+/// Admin-set `(min_price, max_price)` band for `vault`'s share price, in
+/// underlying per `PRICE_PROBE_SHARES` shares (None = unchecked).
+pub fn get_price_bounds(e: &Env, vault: &Address) -> Option<(i128, i128)> {
+    e.storage().instance().get(&DataKey::PriceBounds(vault.clone()))
+}
+
+pub fn set_price_bounds(e: &Env, vault: &Address, bounds: &(i128, i128)) {
+    e.storage()
+        .instance()
+        .set(&DataKey::PriceBounds(vault.clone()), bounds);
+}
+
+// ── Accounting check ──────────────────────────────────────────────────────────
+
+/// Admin-set tolerance, in underlying, between `vault`'s reported total and
+/// its idle plus strategy balances (None = unchecked).
+pub fn get_accounting_tolerance(e: &Env, vault: &Address) -> Option<i128> {
+    e.storage()
+        .instance()
+        .get(&DataKey::AccountingTolerance(vault.clone()))
+}
+
+pub fn set_accounting_tolerance(e: &Env, vault: &Address, tolerance: i128) {
+    e.storage()
+        .instance()
+        .set(&DataKey::AccountingTolerance(vault.clone()), &tolerance);
+}
+
+// ── Escrow cap ────────────────────────────────────────────────────────────────
+
+/// Admin-set ceiling on live escrow for `asset`, in underlying (None = no cap).
+pub fn get_escrow_cap(e: &Env, asset: &Address) -> Option<i128> {
+    e.storage().instance().get(&DataKey::EscrowCap(asset.clone()))
+}
+
+pub fn set_escrow_cap(e: &Env, asset: &Address, amount: i128) {
+    e.storage()
+        .instance()
+        .set(&DataKey::EscrowCap(asset.clone()), &amount);
+}
+
+/// Live escrowed df tokens for `asset`, keyed by vault.
+pub fn get_escrow_held(e: &Env, asset: &Address) -> Map<Address, i128> {
+    e.storage()
+        .instance()
+        .get(&DataKey::EscrowHeld(asset.clone()))
+        .unwrap_or(Map::new(e))
+}
+
+/// Adjusts the live escrowed df tokens for `(asset, vault)` by `delta`.
+pub fn add_escrow_held(e: &Env, asset: &Address, vault: &Address, delta: i128) {
+    let mut held = get_escrow_held(e, asset);
+    let updated = match held.get(vault.clone()).unwrap_or(0).checked_add(delta) {
+        Some(v) if v >= 0 => v,
+        _ => panic!("escrow held out of range"),
+    };
+    if updated == 0 {
+        held.remove(vault.clone());
+    } else {
+        held.set(vault.clone(), updated);
+    }
+    let key = DataKey::EscrowHeld(asset.clone());
+    if held.is_empty() {
+        e.storage().instance().remove(&key);
+    } else {
+        e.storage().instance().set(&key, &held);
+    }
+}
+
+// ── Escrow ────────────────────────────────────────────────────────────────────
+
+/// Returns the batch nonce and bumps it.  Escrow, chunked and distribution
+/// ids all draw from it, so no two ids repeat.
+fn next_nonce(e: &Env) -> u64 {
+    let nonce: u64 = e.storage().instance().get(&DataKey::BatchNonce).unwrap_or(0);
+    let next = match nonce.checked_add(1) {
+        Some(v) => v,
+        None => panic!("batch nonce overflow"),
+    };
+    e.storage().instance().set(&DataKey::BatchNonce, &next);
+    nonce
+}
+
+/// Derives a fresh batch id as `sha256(xdr(caller, vault, nonce))` and bumps
+/// the nonce.
+pub fn next_batch_id(e: &Env, caller: &Address, vault: &Address) -> BytesN<32> {
+    let preimage = (caller.clone(), vault.clone(), next_nonce(e)).to_xdr(e);
+    e.crypto().sha256(&preimage).into()
+}
+
+/// Id of a distribution: `sha256(xdr(caller, asset, vault, recipients, nonce))`.
+pub fn distribution_id(
+    e: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    recipients: &Vec<Recipient>,
+    nonce: u64,
+) -> BytesN<32> {
+    let preimage =
+        (caller.clone(), asset.clone(), vault.clone(), recipients.clone(), nonce).to_xdr(e);
+    e.crypto().sha256(&preimage).into()
+}
+
+/// [`distribution_id`] under the next nonce, bumping it.
+pub fn next_distribution_id(
+    e: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    recipients: &Vec<Recipient>,
+) -> BytesN<32> {
+    distribution_id(e, caller, asset, vault, recipients, next_nonce(e))
+}
+
+pub fn get_batch(e: &Env, batch_id: &BytesN<32>) -> EscrowBatch {
+    let key = DataKey::Batch(batch_id.clone());
+    match e.storage().persistent().get(&key) {
+        Some(b) => {
+            extend_persistent(e, &key);
+            b
+        }
+        None => panic!("escrow batch not found"),
+    }
+}
+
+pub fn set_batch(e: &Env, batch_id: &BytesN<32>, batch: &EscrowBatch) {
+    let key = DataKey::Batch(batch_id.clone());
+    e.storage().persistent().set(&key, batch);
+    extend_persistent(e, &key);
+}
+
+/// Whether `batch_id` was created by `offer_distribution`, so its
+/// allocations are taken with `accept_offer` rather than claimed.
+pub fn is_offer(e: &Env, batch_id: &BytesN<32>) -> bool {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Offer(batch_id.clone()))
+        .unwrap_or(false)
+}
+
+pub fn set_offer(e: &Env, batch_id: &BytesN<32>) {
+    let key = DataKey::Offer(batch_id.clone());
+    e.storage().persistent().set(&key, &true);
+    extend_persistent(e, &key);
+}
+
+pub fn get_allocation(e: &Env, batch_id: &BytesN<32>, user: &Address) -> i128 {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Allocation(batch_id.clone(), user.clone()))
+        .unwrap_or(0)
+}
+
+/// Batch ids in which `user` holds an unclaimed allocation for `vault`.
+pub fn get_user_batches(e: &Env, vault: &Address, user: &Address) -> Vec<BytesN<32>> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::UserBatches(vault.clone(), user.clone()))
+        .unwrap_or(vec![e])
+}
+
+fn set_user_batches(e: &Env, vault: &Address, user: &Address, ids: &Vec<BytesN<32>>) {
+    let key = DataKey::UserBatches(vault.clone(), user.clone());
+    if ids.is_empty() {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, ids);
+        extend_persistent(e, &key);
+    }
+}
+
+/// Ids of `caller`'s escrow batches that still hold unclaimed df tokens.
+pub fn get_caller_batches(e: &Env, caller: &Address) -> Vec<BytesN<32>> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::CallerBatches(caller.clone()))
+        .unwrap_or(vec![e])
+}
+
+fn set_caller_batches(e: &Env, caller: &Address, ids: &Vec<BytesN<32>>) {
+    let key = DataKey::CallerBatches(caller.clone());
+    if ids.is_empty() {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, ids);
+        extend_persistent(e, &key);
+    }
+}
+
+pub fn add_caller_batch(e: &Env, caller: &Address, batch_id: &BytesN<32>) {
+    let mut ids = get_caller_batches(e, caller);
+    ids.push_back(batch_id.clone());
+    set_caller_batches(e, caller, &ids);
+}
+
+pub fn remove_caller_batch(e: &Env, caller: &Address, batch_id: &BytesN<32>) {
+    let mut ids = get_caller_batches(e, caller);
+    if let Some(idx) = ids.first_index_of(batch_id) {
+        ids.remove(idx);
+        set_caller_batches(e, caller, &ids);
+    }
+}
+
+/// Every address that has held an allocation in `batch_id`.  Entries are
+/// never pruned on claim, so some may have a zero allocation.
+pub fn get_batch_recipients(e: &Env, batch_id: &BytesN<32>) -> Vec<Address> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::BatchRecipients(batch_id.clone()))
+        .unwrap_or(vec![e])
+}
+
+pub fn remove_batch_recipients(e: &Env, batch_id: &BytesN<32>) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::BatchRecipients(batch_id.clone()));
+}
+
+fn add_batch_recipient(e: &Env, batch_id: &BytesN<32>, user: &Address) {
+    let mut users = get_batch_recipients(e, batch_id);
+    if users.contains(user) {
+        return;
+    }
+    users.push_back(user.clone());
+    let key = DataKey::BatchRecipients(batch_id.clone());
+    e.storage().persistent().set(&key, &users);
+    extend_persistent(e, &key);
+}
+
+/// Sum of `user`'s unclaimed allocations across every `vault` batch.
+pub fn escrow_balance(e: &Env, vault: &Address, user: &Address) -> i128 {
+    let mut total: i128 = 0;
+    for batch_id in get_user_batches(e, vault, user).iter() {
+        total = match total.checked_add(get_allocation(e, &batch_id, user)) {
+            Some(v) => v,
+            None => panic!("escrow balance overflow"),
+        };
+    }
+    total
+}
+
+/// Adds `amount` df tokens to `user`'s allocation in `batch_id` and indexes
+/// the batch under `(vault, user)` and `batch_id`.  Zero amounts are ignored.
+pub fn credit_allocation(
+    e: &Env,
+    batch_id: &BytesN<32>,
+    vault: &Address,
+    user: &Address,
+    amount: i128,
+) {
+    if amount == 0 {
+        return;
+    }
+    let current = get_allocation(e, batch_id, user);
+    let updated = match current.checked_add(amount) {
+        Some(v) => v,
+        None => panic!("allocation overflow"),
+    };
+    let key = DataKey::Allocation(batch_id.clone(), user.clone());
+    e.storage().persistent().set(&key, &updated);
+    extend_persistent(e, &key);
+
+    if current == 0 {
+        let mut ids = get_user_batches(e, vault, user);
+        ids.push_back(batch_id.clone());
+        set_user_batches(e, vault, user, &ids);
+        add_batch_recipient(e, batch_id, user);
+    }
+}
+
+/// Removes and returns `user`'s allocation in `batch_id` (0 if none),
+/// dropping the batch from the `(vault, user)` index.
+pub fn take_allocation(e: &Env, batch_id: &BytesN<32>, vault: &Address, user: &Address) -> i128 {
+    let amount = get_allocation(e, batch_id, user);
+    if amount == 0 {
+        return 0;
+    }
+    e.storage()
+        .persistent()
+        .remove(&DataKey::Allocation(batch_id.clone(), user.clone()));
+
+    let mut ids = get_user_batches(e, vault, user);
+    if let Some(idx) = ids.first_index_of(batch_id) {
+        ids.remove(idx);
+    }
+    set_user_batches(e, vault, user, &ids);
+    amount
+}
+
+pub fn get_receipt(e: &Env, user: &Address, batch_id: &BytesN<32>) -> Option<DistributionReceipt> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Receipt(user.clone(), batch_id.clone()))
+}
+
+/// Records `user`'s receipt for `receipt.batch_id`.  Receipts are kept after
+/// the allocation is claimed, canceled or reassigned; a reassignment also
+/// writes one for the new holder.
+pub fn set_receipt(e: &Env, user: &Address, receipt: &DistributionReceipt) {
+    let key = DataKey::Receipt(user.clone(), receipt.batch_id.clone());
+    e.storage().persistent().set(&key, receipt);
+    extend_persistent(e, &key);
+}
@@ -4,16 +4,23 @@ extern crate std;
 
 use super::*;
 use soroban_sdk::{
-    testutils::{Address as _, Events as _},
+    testutils::{Address as _, Events as _, Ledger as _},
     token::StellarAssetClient,
-    vec, Address, Env, Event as _, Vec,
+    vec, Address, Env, Event as _, Symbol, Vec,
 };
-use super::events::Distributed;
+use super::events::{
+    Canceled, Distributed, DistributionBatch, DistributionCost, Harvested, HookFailed,
+    Reassigned, RecipientRejected, RoundingLoss,
+};
+use soroban_sdk::address_payload::AddressPayload;
+use super::storage::{DistributionReceipt, DistributionSummary, TtlParams, UnderlyingFeeConfig};
+use crate::testutils::invariants::{assert_floor_remainder, Lcg};
 
 mod integration {
     use super::*;
     use crate::testutils::{
         DistributorTestFixture, EnvTestUtils,
+        create_soroswap_pool, create_token,
         INITIAL_DEPOSIT, MINIMUM_LIQUIDITY, ONE_DAY_LEDGERS,
         blend_setup::Request,
     };
@@ -313,6 +320,38 @@ mod integration {
         assert_eq!(f.vault.balance(&caller), 0);
     }
 
+    /// With `allow_loss` off, a healthy vault whose share price has risen
+    /// above 1:1 still passes: the deposit's own rounding is not a loss.
+    #[test]
+    fn test_disallow_loss_passes_after_yield_accrual() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let borrower = Address::generate(env);
+        f.usdc_admin.mint(&borrower, &500_0000000_i128);
+        f.blend_pool.submit(&borrower, &borrower, &borrower, &vec![
+            env,
+            Request { request_type: 2, address: f.usdc.address.clone(), amount: 500_0000000_i128 },
+        ]);
+        env.jump(ONE_DAY_LEDGERS * 7);
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &200_0000003_i128);
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 120_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 80_0000003_i128 },
+        ];
+        let options = DistributeOptions { allow_loss: false, ..Default::default() };
+        let results = f.distributor.distribute_with_options(
+            &caller, &f.usdc.address, &f.vault.address, &recipients, &options,
+        );
+
+        assert!(results.get(0).unwrap().1 > 0);
+        assert!(results.get(1).unwrap().1 > 0);
+    }
+
     /// Every recipient generates exactly one `Distributed` event with correct
     /// asset, vault, user, underlying_amount, and df_tokens fields.
     /// Uses the real blend-backed vault so the exchange rate is non-trivial.
@@ -338,426 +377,6549 @@ mod integration {
         let results = f.distributor.distribute(
             &caller, &f.usdc.address, &f.vault.address, &recipients,
         );
+        let events = env.events().all().filter_by_contract(&f.distributor.address);
         let df1 = results.get(0).unwrap().1;
         let df2 = results.get(1).unwrap().1;
 
         let ev0 = Distributed {
             asset: f.usdc.address.clone(), vault: f.vault.address.clone(), user: recipient1.clone(),
-            underlying_amount: amount1, df_tokens: df1,
+            underlying_amount: amount1, df_tokens: df1, seq: 0,
         };
         let ev1 = Distributed {
             asset: f.usdc.address.clone(), vault: f.vault.address.clone(), user: recipient2.clone(),
-            underlying_amount: amount2, df_tokens: df2,
+            underlying_amount: amount2, df_tokens: df2, seq: 1,
+        };
+        let recoverable = f.vault.get_asset_amounts_per_shares(&df1).get(0).unwrap()
+            + f.vault.get_asset_amounts_per_shares(&df2).get(0).unwrap();
+        let ev2 = RoundingLoss {
+            vault: f.vault.address.clone(), asset: f.usdc.address.clone(),
+            deposited: amount1 + amount2, recoverable,
+        };
+        let ev3 = DistributionBatch {
+            asset: f.usdc.address.clone(), vault: f.vault.address.clone(),
+            recipients: 2, total_underlying: amount1 + amount2, df_tokens: df1 + df2,
+            batch_id: distribution_batch_id(env, &caller, &f.usdc.address, &f.vault.address, &recipients, 0),
+        };
+        let ev4 = DistributionCost {
+            vault: f.vault.address.clone(), recipients: 2,
+            unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 2 * INSTRUCTIONS_PER_RECIPIENT,
         };
 
         assert_eq!(
-            env.events().all().filter_by_contract(&f.distributor.address),
+            events,
             vec![
                 env,
                 (f.distributor.address.clone(), ev0.topics(env), ev0.data(env)),
                 (f.distributor.address.clone(), ev1.topics(env), ev1.data(env)),
+                (f.distributor.address.clone(), ev2.topics(env), ev2.data(env)),
+                (f.distributor.address.clone(), ev3.topics(env), ev3.data(env)),
+                (f.distributor.address.clone(), ev4.topics(env), ev4.data(env)),
             ]
         );
     }
-}
-
-// ── Mock vault ────────────────────────────────────────────────────────────────
-//
-// Stands in for the real defindex vault on the SAME address for both roles:
-//
-//   • vault::Client::deposit()  — the distributor calls this to deposit and
-//                                 receive df_tokens_minted back.
-//   • TokenClient::transfer()   — the distributor calls this to move df tokens
-//                                 from caller to each recipient.
-//
-// By default deposit mints df tokens 1:1 with the input.
-// Call preset_df_mint() before distribute() to override the minted amount and
-// test non-trivial exchange rates / floor rounding.
-
-mod mock_vault {
-    use soroban_sdk::{contract, contractimpl, symbol_short, vec, Address, Env, Map, Vec};
 
-    fn balances(e: &Env) -> Map<Address, i128> {
-        e.storage()
-            .instance()
-            .get(&symbol_short!("bals"))
-            .unwrap_or(Map::new(e))
-    }
+    /// Depositing exactly `min_total_for_recipients(vault, n)` split evenly
+    /// across `n` recipients gives every recipient a non-zero df-token amount,
+    /// even after yield has pushed the share price above 1:1.
+    #[test]
+    fn test_min_total_for_recipients_yields_nonzero_floors() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
 
-    fn save_bals(e: &Env, b: &Map<Address, i128>) {
-        e.storage().instance().set(&symbol_short!("bals"), b);
-    }
+        // Accrue some yield so the share price is not 1:1
+        let borrower = Address::generate(env);
+        f.usdc_admin.mint(&borrower, &500_0000000_i128);
+        f.blend_pool.submit(
+            &borrower,
+            &borrower,
+            &borrower,
+            &vec![
+                env,
+                Request {
+                    request_type: 2, // borrow
+                    address: f.usdc.address.clone(),
+                    amount: 500_0000000_i128,
+                },
+            ],
+        );
+        env.jump(ONE_DAY_LEDGERS * 7);
 
-    #[contract]
-    pub struct MockVault;
+        let n: u32 = 7;
+        let min_total = f.distributor.min_total_for_recipients(&f.vault.address, &n);
+        assert!(min_total > 0);
+        assert_eq!(min_total % n as i128, 0, "min total must split evenly");
 
-    #[contractimpl]
-    impl MockVault {
-        /// Override how many df tokens deposit() will mint (ignores actual amounts).
-        /// Call this before distribute() to simulate non-1:1 exchange rates.
-        pub fn preset_df_mint(e: Env, amount: i128) {
-            e.storage().instance().set(&symbol_short!("preset"), &amount);
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &min_total);
+
+        let mut recipients: Vec<Recipient> = vec![env];
+        for _ in 0..n {
+            recipients.push_back(Recipient {
+                address: Address::generate(env),
+                amount: min_total / n as i128,
+            });
         }
 
-        // ── vault interface ───────────────────────────────────────────────────
-
-        /// Mints df tokens to `from`.  Uses preset if set, otherwise 1:1.
-        /// Third element is `()` which decodes as `Option::None` on the caller
-        /// side — matching the real vault's return type.
-        /// Also accumulates the exchange-rate state used by
-        /// `get_asset_amounts_per_shares`.
-        pub fn deposit(
-            e: Env,
-            amounts_desired: Vec<i128>,
-            _amounts_min: Vec<i128>,
-            from: Address,
-            _invest: bool,
-        ) -> (Vec<i128>, i128, ()) {
-            let mut total: i128 = 0;
-            for a in amounts_desired.iter() {
-                total += a;
-            }
-            let df_minted: i128 = e
-                .storage()
-                .instance()
-                .get(&symbol_short!("preset"))
-                .unwrap_or(total); // default: 1:1
+        let results = f.distributor.distribute(
+            &caller, &f.usdc.address, &f.vault.address, &recipients,
+        );
 
-            // Track cumulative underlying and supply for get_asset_amounts_per_shares.
-            let prev_und: i128 = e.storage().instance().get(&symbol_short!("und")).unwrap_or(0);
-            let prev_sup: i128 = e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0);
-            e.storage().instance().set(&symbol_short!("und"), &(prev_und + total));
-            e.storage().instance().set(&symbol_short!("sup"), &(prev_sup + df_minted));
+        for (user, df) in results.iter() {
+            assert!(df > 0, "every recipient must receive df-tokens");
+            assert_eq!(f.vault.balance(&user), df);
+        }
+    }
 
-            let mut bals = balances(&e);
-            let cur = bals.get(from.clone()).unwrap_or(0);
-            bals.set(from, cur + df_minted);
-            save_bals(&e, &bals);
+    /// `preview_mints` grows with the candidate total and matches what a real
+    /// deposit mints at the same price.
+    #[test]
+    fn test_preview_mints_monotonic_and_matches_deposit() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
 
-            (amounts_desired, df_minted, ())
-        }
+        let borrower = Address::generate(env);
+        f.usdc_admin.mint(&borrower, &500_0000000_i128);
+        f.blend_pool.submit(&borrower, &borrower, &borrower, &vec![
+            env,
+            Request { request_type: 2, address: f.usdc.address.clone(), amount: 500_0000000_i128 },
+        ]);
+        env.jump(ONE_DAY_LEDGERS * 7);
 
-        /// Returns the underlying value of `vault_shares` shares.
-        /// Mirrors the real vault's `get_asset_amounts_per_shares` interface
-        /// (returns a single-element Vec for the one underlying asset).
-        pub fn get_asset_amounts_per_shares(e: Env, vault_shares: i128) -> Vec<i128> {
-            let total_und: i128 =
-                e.storage().instance().get(&symbol_short!("und")).unwrap_or(0);
-            let total_sup: i128 =
-                e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0);
-            let amount = if total_sup == 0 {
-                0
-            } else {
-                vault_shares * total_und / total_sup
-            };
-            vec![&e, amount]
+        let totals = vec![env, 1_0000000_i128, 10_0000000_i128, 100_0000000_i128, 1000_0000000_i128];
+        let mints = f.distributor.preview_mints(&f.vault.address, &totals);
+        assert_eq!(mints.len(), totals.len());
+        for i in 1..mints.len() {
+            assert!(mints.get(i).unwrap() > mints.get(i - 1).unwrap(), "mints must increase");
         }
 
-        // ── SEP-41 token interface (df token = vault address) ─────────────────
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &100_0000000_i128);
+        let recipients = vec![env, Recipient { address: Address::generate(env), amount: 100_0000000_i128 }];
+        let results = f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &recipients);
+        assert_eq!(results.get(0).unwrap().1, mints.get(2).unwrap());
+    }
 
-        pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
-            let mut bals = balances(&e);
-            let f = bals.get(from.clone()).unwrap_or(0);
-            let t = bals.get(to.clone()).unwrap_or(0);
-            bals.set(from, f - amount);
-            bals.set(to, t + amount);
-            save_bals(&e, &bals);
-        }
+    /// `share_prices` values the probe in the DeFindex vault and in a mock
+    /// vault minting two shares per underlying, returned in input order.
+    #[test]
+    fn test_share_prices_two_vaults_in_order() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
 
-        // ── test helper ───────────────────────────────────────────────────────
+        let (xlm, xlm_admin) = create_token(env, &Address::generate(env));
+        let xlm_vault_id = env.register(mock_vault::MockVault, ());
+        let xlm_vault = MockVaultClient::new(env, &xlm_vault_id);
+        xlm_vault.preset_df_mint(&100_i128);
 
-        pub fn balance(e: Env, address: Address) -> i128 {
-            balances(&e).get(address).unwrap_or(0)
-        }
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &100_0000000_i128);
+        xlm_admin.mint(&caller, &50_i128);
+        f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 100_0000000_i128 },
+        ]);
+        f.distributor.distribute(&caller, &xlm.address, &xlm_vault_id, &vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 50_i128 },
+        ]);
+
+        let probe = 10_0000000_i128;
+        let vaults = vec![env, f.vault.address.clone(), xlm_vault_id.clone()];
+        let prices = f.distributor.share_prices(&vaults, &probe);
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices.get(0).unwrap(), f.vault.get_asset_amounts_per_shares(&probe).get(0).unwrap());
+        assert_eq!(prices.get(1).unwrap(), probe / 2);
     }
-}
 
-use mock_vault::MockVaultClient;
+    /// `distribute_with_swap` converts the caller's BLND into USDC through the
+    /// Soroswap BLND/USDC pair, deposits the output, and splits the minted
+    /// shares 60 / 40 by the recipients' BLND amounts.
+    #[test]
+    fn test_distribute_with_swap_blnd_to_usdc() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
 
-// ── setup helper ──────────────────────────────────────────────────────────────
+        let caller     = Address::generate(env);
+        let recipient1 = Address::generate(env);
+        let recipient2 = Address::generate(env);
 
-fn setup(e: &Env) -> (Address, Address, DistributorClient<'_>) {
-    let admin = Address::generate(e);
-    let asset_id = e.register_stellar_asset_contract_v2(admin).address();
-    let vault_id = e.register(mock_vault::MockVault, ());
-    let distributor_id = e.register(Distributor, ());
-    (asset_id, vault_id, DistributorClient::new(e, &distributor_id))
-}
+        let amount1 = 120_0000000_i128; // BLND
+        let amount2 = 80_0000000_i128;
+        f.blnd_admin.mint(&caller, &(amount1 + amount2));
 
-// ── tests ─────────────────────────────────────────────────────────────────────
+        let path = vec![env, f.blnd.address.clone(), f.usdc.address.clone()];
+        let quoted = f
+            .soroswap_router
+            .router_get_amounts_out(&(amount1 + amount2), &path)
+            .get(1)
+            .unwrap();
+        assert!(quoted > 0);
 
-/// Basic two-recipient, 1:1 mock rate — exact split, no rounding.
-#[test]
-fn test_two_recipients_exact_split() {
-    let env = Env::default();
-    env.mock_all_auths();
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: recipient1.clone(), amount: amount1 },
+            Recipient { address: recipient2.clone(), amount: amount2 },
+        ];
 
-    let (asset_id, vault_id, client) = setup(&env);
-    let vault = MockVaultClient::new(&env, &vault_id);
+        let supply_before = f.vault.total_supply();
+        let results = f.distributor.distribute_with_swap(
+            &caller,
+            &f.blnd.address,
+            &f.soroswap_router.address,
+            &path,
+            &f.vault.address,
+            &quoted,
+            &recipients,
+        );
 
-    let caller     = Address::generate(&env);
-    let recipient1 = Address::generate(&env);
-    let recipient2 = Address::generate(&env);
+        let df1 = results.get(0).unwrap().1;
+        let df2 = results.get(1).unwrap().1;
+        assert!(df1 > 0 && df2 > 0);
+        assert_eq!(df1 + df2, f.vault.total_supply() - supply_before, "all minted shares distributed");
+        assert_eq!(f.vault.balance(&recipient1), df1);
+        assert_eq!(f.vault.balance(&recipient2), df2);
+        // 60 / 40 split of the minted shares (floor for the first recipient)
+        assert_eq!(df1, (df1 + df2) * 3 / 5);
 
-    // total=1000, df_minted=1000 (1:1 default)
-    // user1: floor(300*1000/1000) = 300
-    // user2 (last): 1000 - 300 = 700
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+        assert_eq!(f.blnd.balance(&caller), 0);
+        assert_eq!(f.blnd.balance(&f.distributor.address), 0);
+        assert_eq!(f.usdc.balance(&f.distributor.address), 0);
+    }
 
-    let recipients: Vec<Recipient> = vec![
-        &env,
-        Recipient { address: recipient1.clone(), amount: 300_i128 },
-        Recipient { address: recipient2.clone(), amount: 700_i128 },
-    ];
+    /// A reward token with no direct pair to the underlying is routed through
+    /// an intermediate token with a two-hop path.
+    #[test]
+    fn test_distribute_with_swap_two_hop_path() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
 
-    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+        // Seed BLND/MID and MID/USDC pairs; there is no direct use of BLND/USDC
+        let liquidity = 10_000_000_000_000_i128; // 1 000 000 tokens (7 decimals)
+        let (mid, mid_admin) = create_token(env, &Address::generate(env));
+        let lp = Address::generate(env);
+        f.blnd_admin.mint(&lp, &liquidity);
+        mid_admin.mint(&lp, &(2 * liquidity));
+        f.usdc_admin.mint(&lp, &liquidity);
+        create_soroswap_pool(
+            env, &f.soroswap_router, &lp, &f.blnd.address, &mid.address, liquidity, liquidity,
+        );
+        create_soroswap_pool(
+            env, &f.soroswap_router, &lp, &mid.address, &f.usdc.address, liquidity, liquidity,
+        );
 
-    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 300_i128));
-    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 700_i128));
-    assert_eq!(vault.balance(&recipient1), 300_i128);
-    assert_eq!(vault.balance(&recipient2), 700_i128);
-    assert_eq!(vault.balance(&caller), 0_i128);
-}
+        let caller     = Address::generate(env);
+        let recipient1 = Address::generate(env);
+        let recipient2 = Address::generate(env);
 
-/// Vault rate is non-1:1 (3 input → 10 df tokens).
-/// Verifies floor division: floor(1 * 10 / 3) = 3, remainder goes to last.
-#[test]
-fn test_uneven_split_floors_correctly() {
-    let env = Env::default();
-    env.mock_all_auths();
+        let amount1 = 30_0000000_i128; // BLND
+        let amount2 = 70_0000000_i128;
+        f.blnd_admin.mint(&caller, &(amount1 + amount2));
 
-    let (asset_id, vault_id, client) = setup(&env);
-    let vault = MockVaultClient::new(&env, &vault_id);
+        let path = vec![env, f.blnd.address.clone(), mid.address.clone(), f.usdc.address.clone()];
+        let quoted = f
+            .soroswap_router
+            .router_get_amounts_out(&(amount1 + amount2), &path)
+            .get(2)
+            .unwrap();
+        assert!(quoted > 0);
 
-    let caller     = Address::generate(&env);
-    let recipient1 = Address::generate(&env);
-    let recipient2 = Address::generate(&env);
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: recipient1.clone(), amount: amount1 },
+            Recipient { address: recipient2.clone(), amount: amount2 },
+        ];
 
-    // Simulate vault issuing 10 df tokens for a 3-unit deposit (non-1:1)
-    vault.preset_df_mint(&10_i128);
+        let supply_before = f.vault.total_supply();
+        let results = f.distributor.distribute_with_swap(
+            &caller,
+            &f.blnd.address,
+            &f.soroswap_router.address,
+            &path,
+            &f.vault.address,
+            &quoted,
+            &recipients,
+        );
 
-    // total=3, df_minted=10
-    // user1: floor(1 * 10 / 3) = floor(3.33) = 3
-    // user2 (last): 10 - 3 = 7
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &3_i128);
+        let df1 = results.get(0).unwrap().1;
+        let df2 = results.get(1).unwrap().1;
+        assert!(df1 > 0 && df2 > 0);
+        assert_eq!(df1 + df2, f.vault.total_supply() - supply_before, "all minted shares distributed");
+        assert_eq!(f.vault.balance(&recipient1), df1);
+        assert_eq!(f.vault.balance(&recipient2), df2);
 
-    let recipients: Vec<Recipient> = vec![
-        &env,
-        Recipient { address: recipient1.clone(), amount: 1_i128 },
-        Recipient { address: recipient2.clone(), amount: 2_i128 },
-    ];
+        assert_eq!(f.blnd.balance(&caller), 0);
+        assert_eq!(mid.balance(&f.distributor.address), 0);
+        assert_eq!(f.usdc.balance(&f.distributor.address), 0);
+    }
 
-    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+    /// A path that does not end at the vault's underlying is rejected.
+    #[test]
+    #[should_panic(expected = "swap path must end at the vault underlying")]
+    fn test_distribute_with_swap_wrong_path_end_panics() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
 
-    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 3_i128));
-    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 7_i128));
-    assert_eq!(vault.balance(&recipient1), 3_i128);
-    assert_eq!(vault.balance(&recipient2), 7_i128);
-}
+        let caller = Address::generate(env);
+        let amount = 10_0000000_i128;
+        f.usdc_admin.mint(&caller, &amount);
+
+        let path = vec![env, f.usdc.address.clone(), f.blnd.address.clone()];
+        let recipients = vec![env, Recipient { address: Address::generate(env), amount }];
+        f.distributor.distribute_with_swap(
+            &caller,
+            &f.usdc.address,
+            &f.soroswap_router.address,
+            &path,
+            &f.vault.address,
+            &1_i128,
+            &recipients,
+        );
+    }
+
+    /// A `min_out` above what the pair can deliver rejects the swap.
+    #[test]
+    #[should_panic]
+    fn test_distribute_with_swap_min_out_not_met_panics() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller = Address::generate(env);
+        let amount = 100_0000000_i128;
+        f.blnd_admin.mint(&caller, &amount);
+
+        let path = vec![env, f.blnd.address.clone(), f.usdc.address.clone()];
+        let quoted = f.soroswap_router.router_get_amounts_out(&amount, &path).get(1).unwrap();
+
+        let recipients = vec![env, Recipient { address: Address::generate(env), amount }];
+        f.distributor.distribute_with_swap(
+            &caller,
+            &f.blnd.address,
+            &f.soroswap_router.address,
+            &path,
+            &f.vault.address,
+            &(quoted + 1),
+            &recipients,
+        );
+    }
+
+    /// `distribute_with_harvest` skips the harvest while the BLND the
+    /// strategy has accrued in the pool is below `min_pending`, leaving the
+    /// vault's managed funds at exactly the deposit, and harvests once it is
+    /// above, so the reinvested rewards lift the funds behind existing shares.
+    #[test]
+    fn test_distribute_with_harvest_threshold() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let keeper = f.keeper.clone();
+        let min_pending = 50_0000000_i128; // 50 BLND
+        let amount = 100_0000000_i128;
+        f.usdc_admin.mint(&keeper, &(2 * amount));
+        let managed = || f.vault.fetch_total_managed_funds().get(0).unwrap().total_amount;
+        let pending = || {
+            env.as_contract(&f.distributor.address, || {
+                crate::pending_emissions(env, &f.blend_pool.address, &f.strategy.address)
+            })
+        };
+
+        // 100 ledgers of emissions accrue about 37.5 BLND: no harvest, only the
+        // deposit moves the funds
+        env.jump(100);
+        let accrued = pending();
+        assert!(accrued > 0 && accrued < min_pending);
+        let before = managed();
+        f.distributor.distribute_with_harvest(
+            &keeper,
+            &f.strategy.address,
+            &f.blend_pool.address,
+            &min_pending,
+            &f.usdc.address,
+            &f.vault.address,
+            &vec![env, Recipient { address: Address::generate(env), amount }],
+        );
+        assert_eq!(pending(), accrued, "emissions left unclaimed in the pool");
+        assert_eq!(managed(), before + amount);
+
+        // 100 more reach about 75 BLND, still unclaimed in the pool: harvested and
+        // reinvested before the deposit
+        env.jump(100);
+        let accrued = pending();
+        let before = managed();
+        f.distributor.distribute_with_harvest(
+            &keeper,
+            &f.strategy.address,
+            &f.blend_pool.address,
+            &min_pending,
+            &f.usdc.address,
+            &f.vault.address,
+            &vec![env, Recipient { address: Address::generate(env), amount }],
+        );
+        let event = Harvested {
+            strategy: f.strategy.address.clone(),
+            pool: f.blend_pool.address.clone(),
+            pending: accrued,
+        };
+        assert!(env
+            .events()
+            .all()
+            .filter_by_contract(&f.distributor.address)
+            .events()
+            .contains(&event.to_xdr(env, &f.distributor.address)));
+        assert!(managed() > before + amount, "harvest grows managed funds");
+        assert_eq!(f.blend_pool.claim(&f.strategy.address, &vec![env, 1_u32], &keeper), 0);
+    }
+
+    /// `distribute_with_bonus` pays the vault shares as `distribute` does and
+    /// a flat 5 BLND bonus from the caller to each recipient.
+    #[test]
+    fn test_distribute_with_bonus_pays_shares_and_bonus() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller     = Address::generate(env);
+        let recipient1 = Address::generate(env);
+        let recipient2 = Address::generate(env);
+        let bonus = 5_0000000_i128;
+        f.usdc_admin.mint(&caller, &100_0000000_i128);
+        f.blnd_admin.mint(&caller, &(2 * bonus + 1));
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: recipient1.clone(), amount: 40_0000000_i128 },
+            Recipient { address: recipient2.clone(), amount: 60_0000000_i128 },
+        ];
+        let results = f.distributor.distribute_with_bonus(
+            &caller,
+            &f.usdc.address,
+            &f.vault.address,
+            &recipients,
+            &f.blnd.address,
+            &bonus,
+        );
+
+        for (address, df) in results.iter() {
+            assert!(df > 0);
+            assert_eq!(f.vault.balance(&address), df);
+            assert_eq!(f.blnd.balance(&address), bonus);
+        }
+        assert_eq!(f.blnd.balance(&caller), 1);
+        assert_eq!(f.usdc.balance(&caller), 0);
+    }
+
+    /// A caller short of `n * bonus_per_recipient` is rejected up front.
+    #[test]
+    #[should_panic(expected = "insufficient bonus balance")]
+    fn test_distribute_with_bonus_insufficient_balance_panics() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &100_0000000_i128);
+        f.blnd_admin.mint(&caller, &(9_9999999_i128));
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 40_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 60_0000000_i128 },
+        ];
+        f.distributor.distribute_with_bonus(
+            &caller,
+            &f.usdc.address,
+            &f.vault.address,
+            &recipients,
+            &f.blnd.address,
+            &5_0000000_i128,
+        );
+    }
+
+    /// With the bonus paid in the asset itself, the caller must hold the
+    /// distribution total and the bonus: exactly that much succeeds.
+    #[test]
+    fn test_distribute_with_bonus_in_asset() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller = Address::generate(env);
+        let bonus = 5_0000000_i128;
+        f.usdc_admin.mint(&caller, &(100_0000000_i128 + 2 * bonus));
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 40_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 60_0000000_i128 },
+        ];
+        let results = f.distributor.distribute_with_bonus(
+            &caller,
+            &f.usdc.address,
+            &f.vault.address,
+            &recipients,
+            &f.usdc.address,
+            &bonus,
+        );
+
+        for (address, df) in results.iter() {
+            assert_eq!(f.vault.balance(&address), df);
+            assert_eq!(f.usdc.balance(&address), bonus);
+        }
+        assert_eq!(f.usdc.balance(&caller), 0);
+    }
+
+    /// Holding the bonus alone is not enough when it is paid in the asset:
+    /// the deposit needs the total on top, so the call is rejected up front.
+    #[test]
+    #[should_panic(expected = "insufficient bonus balance")]
+    fn test_distribute_with_bonus_in_asset_needs_total_and_bonus() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller = Address::generate(env);
+        let bonus = 5_0000000_i128;
+        f.usdc_admin.mint(&caller, &(100_0000000_i128 + 2 * bonus - 1));
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 40_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 60_0000000_i128 },
+        ];
+        f.distributor.distribute_with_bonus(
+            &caller,
+            &f.usdc.address,
+            &f.vault.address,
+            &recipients,
+            &f.usdc.address,
+            &bonus,
+        );
+    }
+
+    /// A recipient whose pro-rata share is worth more than the underlying cap
+    /// is clamped to the cap; the excess df tokens land at `overflow_to`.
+    #[test]
+    fn test_distribute_capped_underlying_routes_overflow() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller      = Address::generate(env);
+        let recipient1  = Address::generate(env);
+        let recipient2  = Address::generate(env);
+        let overflow_to = Address::generate(env);
+
+        let amount1 = 800_0000000_i128;
+        let amount2 = 200_0000000_i128;
+        let cap     = 500_0000000_i128;
+        f.usdc_admin.mint(&caller, &(amount1 + amount2));
+
+        let supply_before = f.vault.total_supply();
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: recipient1.clone(), amount: amount1 },
+            Recipient { address: recipient2.clone(), amount: amount2 },
+        ];
+        let results = f.distributor.distribute_capped_underlying(
+            &caller, &f.usdc.address, &f.vault.address, &recipients, &cap, &overflow_to,
+        );
+        let minted = f.vault.total_supply() - supply_before;
+
+        let df1 = results.get(0).unwrap().1;
+        let df2 = results.get(1).unwrap().1;
+        assert_eq!(f.vault.balance(&recipient1), df1);
+        assert_eq!(f.vault.balance(&recipient2), df2);
+
+        // Recipient 1 is clamped to (at most) the cap in underlying value
+        let value1 = f.vault.get_asset_amounts_per_shares(&df1).get(0).unwrap();
+        assert!(value1 <= cap, "capped value {} exceeds cap {}", value1, cap);
+        assert!(cap - value1 <= 1, "capped value {} too far below cap {}", value1, cap);
+
+        // Recipient 2 is under the cap and keeps its full share
+        assert_eq!(df2, minted - minted * amount1 / (amount1 + amount2));
+
+        // Everything clamped off recipient 1 went to the overflow address
+        let overflow = f.vault.balance(&overflow_to);
+        assert!(overflow > 0);
+        assert_eq!(df1 + df2 + overflow, minted, "all minted shares distributed");
+        assert_eq!(f.vault.balance(&f.distributor.address), 0);
+    }
+
+    /// A distribution above the admin-set vault deposit ceiling is rejected
+    /// before anything reaches the vault.
+    #[test]
+    #[should_panic(expected = "total exceeds vault max deposit")]
+    fn test_distribute_above_vault_max_deposit_panics() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+        let admin = f.distributor.get_admin();
+
+        f.distributor.set_max_deposit(&admin, &f.vault.address, &500_0000000_i128);
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &1000_0000000_i128);
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 300_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 300_0000000_i128 },
+        ];
+        f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &recipients);
+    }
+
+    /// `distribute_mixed` pays a USDC group into the DeFindex vault and a
+    /// second asset's group into its own vault, each with its own deposit
+    /// and split, under one call.
+    #[test]
+    fn test_distribute_mixed_two_asset_groups() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let (xlm, xlm_admin) = create_token(env, &Address::generate(env));
+        let xlm_vault_id = env.register(mock_vault::MockVault, ());
+        let xlm_vault = MockVaultClient::new(env, &xlm_vault_id);
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &1000_0000000_i128);
+        xlm_admin.mint(&caller, &50_i128);
+
+        let usdc_recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 600_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 400_0000000_i128 },
+        ];
+        let xlm_recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 30_i128 },
+            Recipient { address: Address::generate(env), amount: 20_i128 },
+        ];
+        let entries = vec![
+            env,
+            (f.usdc.address.clone(), f.vault.address.clone(), usdc_recipients.clone()),
+            (xlm.address.clone(), xlm_vault_id.clone(), xlm_recipients.clone()),
+        ];
+
+        let supply_before = f.vault.total_supply();
+        let results = f.distributor.distribute_mixed(&caller, &entries);
+        assert_eq!(results.len(), 2);
+
+        // USDC group: every df token minted by the real vault reaches its recipients
+        let usdc_results = results.get(0).unwrap();
+        let minted = f.vault.total_supply() - supply_before;
+        let mut usdc_paid = 0_i128;
+        for (r, (address, df)) in usdc_recipients.iter().zip(usdc_results.iter()) {
+            assert_eq!(address, r.address);
+            assert_eq!(f.vault.balance(&address), df);
+            usdc_paid += df;
+        }
+        assert_eq!(usdc_paid, minted);
+
+        // Second group: 1:1 mock vault, exact amounts, untouched by the USDC group
+        let xlm_results = results.get(1).unwrap();
+        for (r, (address, df)) in xlm_recipients.iter().zip(xlm_results.iter()) {
+            assert_eq!(df, r.amount);
+            assert_eq!(xlm_vault.balance(&address), r.amount);
+            assert_eq!(f.vault.balance(&address), 0);
+        }
+
+        assert_eq!(f.usdc.balance(&caller), 0);
+        assert_eq!(xlm.balance(&caller), 0);
+        assert_eq!(f.vault.balance(&f.distributor.address), 0);
+        assert_eq!(xlm_vault.balance(&f.distributor.address), 0);
+    }
+
+    /// `quote_rounding_loss` taken before a real distribution into a vault
+    /// with accrued yield matches the loss actually incurred within one strop.
+    #[test]
+    fn test_quote_rounding_loss_matches_actual() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        // Accrue some yield so the share price is not 1:1
+        let borrower = Address::generate(env);
+        f.usdc_admin.mint(&borrower, &500_0000000_i128);
+        f.blend_pool.submit(
+            &borrower,
+            &borrower,
+            &borrower,
+            &vec![
+                env,
+                Request {
+                    request_type: 2, // borrow
+                    address: f.usdc.address.clone(),
+                    amount: 500_0000000_i128,
+                },
+            ],
+        );
+        env.jump(ONE_DAY_LEDGERS * 7);
+
+        let amounts = [333_3333333_i128, 7_3456789_i128, 99_9999999_i128, 1_i128];
+        let deposited: i128 = amounts.iter().sum();
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &deposited);
+
+        let mut recipients: Vec<Recipient> = vec![env];
+        for amount in amounts {
+            recipients.push_back(Recipient { address: Address::generate(env), amount });
+        }
+
+        let quoted = f.distributor.quote_rounding_loss(&f.vault.address, &recipients);
+        let results = f.distributor.distribute(
+            &caller, &f.usdc.address, &f.vault.address, &recipients,
+        );
+
+        let mut recoverable: i128 = 0;
+        for (_, df) in results.iter() {
+            recoverable += f.vault.get_asset_amounts_per_shares(&df).get(0).unwrap();
+        }
+        let actual = deposited - recoverable;
+        assert!(quoted >= 0);
+        assert!(
+            (quoted - actual).abs() <= 1,
+            "quoted loss {} vs actual {}", quoted, actual
+        );
+    }
+
+    /// With a 1% underlying fee and a 5% referral rebate, `quote_caller_cost`
+    /// matches the underlying the caller actually spends and the df tokens
+    /// rebated back to them.
+    #[test]
+    fn test_quote_caller_cost_matches_actual() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+        let admin = f.distributor.get_admin();
+        f.distributor.set_underlying_fee(&admin, &Address::generate(env), &100_u32);
+        f.distributor.set_referral_rebate(&admin, &500_u32);
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &1000_0000000_i128);
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 123_4567890_i128 },
+            Recipient { address: Address::generate(env), amount: 76_5432110_i128 },
+        ];
+
+        let (underlying_out, df_retained) =
+            f.distributor.quote_caller_cost(&f.vault.address, &recipients, &100_u32);
+        let usdc_before = f.usdc.balance(&caller);
+        f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &recipients);
+
+        assert_eq!(underlying_out, 200_0000000_i128);
+        assert_eq!(usdc_before - f.usdc.balance(&caller), underlying_out);
+        assert!(df_retained > 0);
+        assert_eq!(f.vault.balance(&caller), df_retained);
+    }
+
+    /// `quote_redemption` matches the vault's gross valuation, and a real
+    /// `withdraw` of the same shares pays out exactly that much.
+    #[test]
+    fn test_quote_redemption_matches_withdraw() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller = Address::generate(env);
+        let user = Address::generate(env);
+        f.usdc_admin.mint(&caller, &1000_0000000_i128);
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: user.clone(), amount: 123_4567890_i128 },
+            Recipient { address: Address::generate(env), amount: 76_5432110_i128 },
+        ];
+        f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &recipients);
+
+        let shares = f.vault.balance(&user);
+        let gross = f.vault.get_asset_amounts_per_shares(&shares).get(0).unwrap();
+        let quoted = f.distributor.quote_redemption(&f.vault.address, &shares);
+        assert_eq!(quoted, gross);
+
+        let out = f.vault.withdraw(&shares, &vec![env, 0_i128], &user).get(0).unwrap();
+        assert_eq!(out, quoted);
+        assert_eq!(f.usdc.balance(&user), quoted);
+        assert_eq!(f.distributor.quote_redemption(&f.vault.address, &0_i128), 0);
+    }
+
+    /// `distribute_tranched`: the idle tranche lands in the vault's idle
+    /// balance while the invested tranche is pushed into the strategy.
+    #[test]
+    fn test_distribute_tranched_idle_and_invested() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let invested_total = 600_0000000_i128;
+        let idle_total = 400_0000000_i128;
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &(invested_total + idle_total));
+
+        let invested_recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 200_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 400_0000000_i128 },
+        ];
+        let idle_recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: idle_total },
+        ];
+
+        let before = f.vault.fetch_total_managed_funds().get(0).unwrap();
+        let results = f.distributor.distribute_tranched(
+            &caller, &f.usdc.address, &f.vault.address, &invested_recipients, &idle_recipients,
+        );
+        let after = f.vault.fetch_total_managed_funds().get(0).unwrap();
+
+        assert_eq!(after.idle_amount - before.idle_amount, idle_total);
+        let invested = after.invested_amount - before.invested_amount;
+        assert!(
+            (invested - invested_total).abs() <= 1,
+            "invested {} vs {}", invested, invested_total
+        );
+
+        assert_eq!(results.len(), 3);
+        for (address, df) in results.iter() {
+            assert!(df > 0);
+            assert_eq!(f.vault.balance(&address), df);
+        }
+        assert_eq!(f.vault.balance(&f.distributor.address), 0);
+    }
+
+    /// `asset_metadata` resolves the vault's underlying and reports the USDC
+    /// token's symbol and its 7 decimals.
+    #[test]
+    fn test_asset_metadata_for_usdc_vault() {
+        let f = DistributorTestFixture::create();
+
+        let (symbol, decimals) = f.distributor.asset_metadata(&f.vault.address);
+        assert_eq!(symbol, f.usdc.symbol());
+        assert_eq!(decimals, 7);
+    }
+
+    /// A healthy DeFindex vault's total matches its idle plus strategy
+    /// balances exactly, so the accounting check passes at zero tolerance.
+    #[test]
+    fn test_real_vault_accounting_consistent() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+        let admin = f.distributor.get_admin();
+
+        f.distributor.set_accounting_tolerance(&admin, &f.vault.address, &0_i128);
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &1000_0000000_i128);
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: Address::generate(env), amount: 600_0000000_i128 },
+            Recipient { address: Address::generate(env), amount: 400_0000000_i128 },
+        ];
+        let results = f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &recipients);
+        assert!(results.get(0).unwrap().1 > 0);
+    }
+
+    /// After expiry, the batch caller reclaims the unclaimed allocation as
+    /// underlying redeemed through the vault, sparing a separate redeem step.
+    #[test]
+    fn test_reclaim_expired_escrow_as_underlying() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+        let admin = f.distributor.get_admin();
+
+        f.distributor.set_escrow_expiry(&admin, &ONE_DAY_LEDGERS);
+
+        let caller     = Address::generate(env);
+        let recipient1 = Address::generate(env);
+        let recipient2 = Address::generate(env);
+
+        let amount1 = 600_0000000_i128;
+        let amount2 = 400_0000000_i128;
+        f.usdc_admin.mint(&caller, &(amount1 + amount2));
+
+        let recipients: Vec<Recipient> = vec![
+            env,
+            Recipient { address: recipient1.clone(), amount: amount1 },
+            Recipient { address: recipient2.clone(), amount: amount2 },
+        ];
+        let batch_id = f.distributor.distribute_escrow(
+            &caller, &f.usdc.address, &f.vault.address, &recipients,
+        );
+        f.distributor.claim(&recipient1, &batch_id);
+
+        env.jump(ONE_DAY_LEDGERS + 1);
+
+        // Recipient 2 never claimed; value their allocation before reclaiming
+        let unclaimed_df = f.vault.balance(&f.distributor.address);
+        assert!(unclaimed_df > 0);
+        let expected = f.vault.get_asset_amounts_per_shares(&unclaimed_df).get(0).unwrap();
+
+        let out = f.distributor.reclaim(&caller, &batch_id, &true, &(expected - 1));
+
+        assert!(out >= expected - 1 && out <= expected, "out {} vs expected {}", out, expected);
+        assert!(amount2 - out <= 1, "reclaimed {} for a {} allocation", out, amount2);
+        assert_eq!(f.usdc.balance(&caller), out);
+        assert_eq!(f.vault.balance(&f.distributor.address), 0);
+        assert_eq!(f.usdc.balance(&f.distributor.address), 0);
+        assert_eq!(
+            f.distributor.escrow_balances(&f.vault.address, &vec![env, recipient2.clone()]),
+            vec![env, 0_i128]
+        );
+    }
+
+    /// Top-up to target: recipients below target receive exactly their
+    /// deficit, recipients at or above target are skipped, and the caller
+    /// deposits only what the deficits require.
+    #[test]
+    fn test_distribute_to_target_mixed_balances() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        let caller = Address::generate(env);
+        let below  = Address::generate(env);
+        let above  = Address::generate(env);
+        let fresh  = Address::generate(env);
+
+        // Give `below` and `above` existing df balances
+        let seed = 300_0000000_i128;
+        f.usdc_admin.mint(&caller, &seed);
+        f.distributor.distribute(&caller, &f.usdc.address, &f.vault.address, &vec![
+            env,
+            Recipient { address: below.clone(), amount: 100_0000000_i128 },
+            Recipient { address: above.clone(), amount: 200_0000000_i128 },
+        ]);
+        let below_before = f.vault.balance(&below);
+        let above_before = f.vault.balance(&above);
+
+        let target = 150_0000000_i128;
+        f.usdc_admin.mint(&caller, &1000_0000000_i128);
+        let caller_usdc_before = f.usdc.balance(&caller);
+
+        let results = f.distributor.distribute_to_target(
+            &caller, &f.usdc.address, &f.vault.address,
+            &vec![env, (below.clone(), target), (above.clone(), target), (fresh.clone(), target)],
+        );
+
+        let deficit_below = target - below_before;
+        assert_eq!(
+            results,
+            vec![env, (below.clone(), deficit_below), (above.clone(), 0_i128), (fresh.clone(), target)]
+        );
+        assert_eq!(f.vault.balance(&below), target);
+        assert_eq!(f.vault.balance(&above), above_before);
+        assert_eq!(f.vault.balance(&fresh), target);
+        assert_eq!(f.vault.balance(&f.distributor.address), 0);
+
+        // The deposit covered the deficits (up to share-price rounding)
+        let spent = caller_usdc_before - f.usdc.balance(&caller);
+        let deficit_value = f
+            .vault
+            .get_asset_amounts_per_shares(&(deficit_below + target))
+            .get(0)
+            .unwrap();
+        assert!(spent >= deficit_value && spent - deficit_value <= 2, "spent {} vs {}", spent, deficit_value);
+    }
+
+    /// Stray df tokens held by the distributor are redeemed through the vault
+    /// and the underlying arrives at `to`.
+    #[test]
+    fn test_emergency_drain_redeems_dust() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+        let admin = f.distributor.get_admin();
+
+        // Strand some df tokens on the distributor
+        let user = Address::generate(env);
+        f.usdc_admin.mint(&user, &10_0000000_i128);
+        let (_, df_minted, _) = f.vault.deposit(
+            &vec![env, 10_0000000_i128], &vec![env, 0_i128], &user, &false,
+        );
+        f.vault.transfer(&user, &f.distributor.address, &df_minted);
+
+        let expected = f.vault.get_asset_amounts_per_shares(&df_minted).get(0).unwrap();
+        let to = Address::generate(env);
+        let out = f.distributor.emergency_drain(&admin, &f.vault.address, &to, &false);
+
+        assert_eq!(out, expected);
+        assert_eq!(f.usdc.balance(&to), expected);
+        assert_eq!(f.vault.balance(&f.distributor.address), 0);
+        assert_eq!(f.usdc.balance(&f.distributor.address), 0);
+    }
+
+    /// The `RoundingLoss` event reports the recipients' combined redeemable
+    /// underlying.  After yield moves the share price off 1:1, the loss is
+    /// non-negative and at most one strop per recipient (the dust invariant).
+    #[test]
+    fn test_rounding_loss_event_small_and_non_negative() {
+        let f = DistributorTestFixture::create();
+        let env = &f.env;
+
+        // Accrue some yield so the share price is not 1:1
+        let borrower = Address::generate(env);
+        f.usdc_admin.mint(&borrower, &500_0000000_i128);
+        f.blend_pool.submit(
+            &borrower,
+            &borrower,
+            &borrower,
+            &vec![
+                env,
+                Request {
+                    request_type: 2, // borrow
+                    address: f.usdc.address.clone(),
+                    amount: 500_0000000_i128,
+                },
+            ],
+        );
+        env.jump(ONE_DAY_LEDGERS * 7);
+
+        let amounts = [333_3333333_i128, 7_3456789_i128, 99_9999999_i128];
+        let deposited: i128 = amounts.iter().sum();
+
+        let caller = Address::generate(env);
+        f.usdc_admin.mint(&caller, &deposited);
+
+        let mut recipients: Vec<Recipient> = vec![env];
+        for amount in amounts {
+            recipients.push_back(Recipient { address: Address::generate(env), amount });
+        }
+
+        let results = f.distributor.distribute(
+            &caller, &f.usdc.address, &f.vault.address, &recipients,
+        );
+        let events = env.events().all().filter_by_contract(&f.distributor.address);
+
+        let mut expected = vec![env];
+        let mut recoverable: i128 = 0;
+        let mut minted: i128 = 0;
+        for (seq, (r, (_, df))) in recipients.iter().zip(results.iter()).enumerate() {
+            let ev = Distributed {
+                asset: f.usdc.address.clone(), vault: f.vault.address.clone(), user: r.address,
+                underlying_amount: r.amount, df_tokens: df, seq: seq as u64,
+            };
+            expected.push_back((f.distributor.address.clone(), ev.topics(env), ev.data(env)));
+            recoverable += f.vault.get_asset_amounts_per_shares(&df).get(0).unwrap();
+            minted += df;
+        }
+        let ev = RoundingLoss {
+            vault: f.vault.address.clone(), asset: f.usdc.address.clone(),
+            deposited, recoverable,
+        };
+        expected.push_back((f.distributor.address.clone(), ev.topics(env), ev.data(env)));
+        let ev = DistributionBatch {
+            asset: f.usdc.address.clone(), vault: f.vault.address.clone(),
+            recipients: recipients.len(), total_underlying: deposited, df_tokens: minted,
+            batch_id: distribution_batch_id(env, &caller, &f.usdc.address, &f.vault.address, &recipients, 0),
+        };
+        expected.push_back((f.distributor.address.clone(), ev.topics(env), ev.data(env)));
+        let ev = DistributionCost {
+            vault: f.vault.address.clone(), recipients: recipients.len(),
+            unit_cost: INSTRUCTIONS_PER_RECIPIENT,
+            estimated_instructions: recipients.len() as u64 * INSTRUCTIONS_PER_RECIPIENT,
+        };
+        expected.push_back((f.distributor.address.clone(), ev.topics(env), ev.data(env)));
+        assert_eq!(events, expected);
+
+        let loss = deposited - recoverable;
+        assert!(loss >= 0, "recoverable {} exceeds deposited {}", recoverable, deposited);
+        assert!(
+            loss <= amounts.len() as i128,
+            "rounding loss {} exceeds one strop per recipient", loss
+        );
+    }
+}
+
+// ── Mock vault ────────────────────────────────────────────────────────────────
+//
+// Stands in for the real defindex vault on the SAME address for both roles:
+//
+//   • vault::Client::deposit()  — the distributor calls this to deposit and
+//                                 receive df_tokens_minted back.
+//   • TokenClient::transfer()   — the distributor calls this to move df tokens
+//                                 from caller to each recipient.
+//
+// By default deposit mints df tokens 1:1 with the input.
+// Call preset_df_mint() before distribute() to override the minted amount and
+// test non-trivial exchange rates / floor rounding, or preset_drift() for a
+// share price that grows with ledger time.
+
+mod mock_vault {
+    use soroban_sdk::{
+        contract, contractimpl, contracttype, symbol_short, vec, Address, Env, Error, IntoVal,
+        Map, String, Symbol, Vec,
+    };
+
+    /// Mirrors the real vault's `StrategyAllocation`.
+    #[contracttype]
+    pub struct StrategyAllocation {
+        pub amount: i128,
+        pub paused: bool,
+        pub strategy_address: Address,
+    }
+
+    /// Mirrors the real vault's `Strategy`.
+    #[contracttype]
+    pub struct Strategy {
+        pub address: Address,
+        pub name: String,
+        pub paused: bool,
+    }
+
+    /// Mirrors the real vault's `AssetStrategySet`.
+    #[contracttype]
+    pub struct AssetStrategySet {
+        pub address: Address,
+        pub strategies: Vec<Strategy>,
+    }
+
+    /// Mirrors the real vault's `CurrentAssetInvestmentAllocation`.
+    #[contracttype]
+    pub struct CurrentAssetInvestmentAllocation {
+        pub asset: Address,
+        pub idle_amount: i128,
+        pub invested_amount: i128,
+        pub strategy_allocations: Vec<StrategyAllocation>,
+        pub total_amount: i128,
+    }
+
+    fn balances(e: &Env) -> Map<Address, i128> {
+        e.storage()
+            .instance()
+            .get(&symbol_short!("bals"))
+            .unwrap_or(Map::new(e))
+    }
+
+    fn save_bals(e: &Env, b: &Map<Address, i128>) {
+        e.storage().instance().set(&symbol_short!("bals"), b);
+    }
+
+    /// Managed underlying including drift accrued since the last accrual:
+    /// `und * (1 + bps_per_ledger * elapsed / 10_000)`.
+    fn managed(e: &Env) -> i128 {
+        let und: i128 = e.storage().instance().get(&symbol_short!("und")).unwrap_or(0);
+        let bps: i128 = e.storage().instance().get(&symbol_short!("drift")).unwrap_or(0);
+        if bps == 0 {
+            return und;
+        }
+        let since: u32 = e.storage().instance().get(&symbol_short!("drift_at")).unwrap();
+        let elapsed = (e.ledger().sequence() - since) as i128;
+        und + und * bps * elapsed / 10_000
+    }
+
+    /// Folds accrued drift into the stored underlying, so later deposits and
+    /// withdrawals do not earn drift retroactively.
+    fn accrue(e: &Env) {
+        if e.storage().instance().has(&symbol_short!("drift")) {
+            e.storage().instance().set(&symbol_short!("und"), &managed(e));
+            e.storage().instance().set(&symbol_short!("drift_at"), &e.ledger().sequence());
+        }
+    }
+
+    #[contract]
+    pub struct MockVault;
+
+    #[contractimpl]
+    impl MockVault {
+        /// Override how many df tokens deposit() will mint (ignores actual amounts).
+        /// Call this before distribute() to simulate non-1:1 exchange rates.
+        pub fn preset_df_mint(e: Env, amount: i128) {
+            e.storage().instance().set(&symbol_short!("preset"), &amount);
+        }
+
+        /// Makes deposit() lose `amount` of the deposited underlying, so the
+        /// minted shares redeem for less than was deposited.
+        pub fn preset_loss(e: Env, amount: i128) {
+            e.storage().instance().set(&symbol_short!("loss"), &amount);
+        }
+
+        /// Makes deposit() credit `amount` extra df tokens to the depositor
+        /// without reporting them, simulating a malicious vault that injects
+        /// tokens mid-distribution.
+        pub fn preset_inject(e: Env, amount: i128) {
+            e.storage().instance().set(&symbol_short!("inject"), &amount);
+        }
+
+        /// Makes deposit() mint `amount` more df tokens than it reports, to an
+        /// account other than the depositor.
+        pub fn preset_mint_elsewhere(e: Env, amount: i128) {
+            e.storage().instance().set(&symbol_short!("elsewhr"), &amount);
+        }
+
+        /// Makes get_asset_amounts_per_shares() revert, like a vault with an
+        /// internal cap on valuation queries.
+        pub fn preset_valuation_revert(e: Env) {
+            e.storage().instance().set(&symbol_short!("noval"), &true);
+        }
+
+        /// Makes fetch_total_managed_funds() report a `total_amount` that is
+        /// `amount` more than the idle and strategy balances add up to.
+        pub fn preset_skew(e: Env, amount: i128) {
+            e.storage().instance().set(&symbol_short!("skew"), &amount);
+        }
+
+        /// Makes the managed underlying, and so the share price, grow by
+        /// `bps_per_ledger` basis points per ledger from now on, compounding
+        /// at each deposit or withdrawal.  Without a preset mint, deposits then
+        /// mint at the drifted price like the real vault.
+        pub fn preset_drift(e: Env, bps_per_ledger: i128) {
+            accrue(&e);
+            e.storage().instance().set(&symbol_short!("drift"), &bps_per_ledger);
+            e.storage().instance().set(&symbol_short!("drift_at"), &e.ledger().sequence());
+        }
+
+        /// Makes transfer() to `to` revert, like a recipient the df token
+        /// refuses to credit.
+        pub fn preset_reject(e: Env, to: Address) {
+            e.storage().instance().set(&symbol_short!("reject"), &to);
+        }
+
+        /// Makes transfer() revert when it would leave the receiver holding
+        /// more than `cap` df tokens, like a share token with a per-account
+        /// balance limit.
+        pub fn preset_balance_cap(e: Env, cap: i128) {
+            e.storage().instance().set(&symbol_short!("cap"), &cap);
+        }
+
+        /// Makes authorized() report `id` as not authorised, like a frozen
+        /// SAC trustline.
+        pub fn preset_frozen(e: Env, id: Address) {
+            e.storage().instance().set(&symbol_short!("frozen"), &id);
+        }
+
+        /// Makes deposit() call `set_min_amount(admin, 1)` on `target`, like a
+        /// malicious vault trying to change the distributor's config
+        /// mid-distribution.  The outcome is kept for callback_ok().
+        pub fn preset_callback(e: Env, target: Address, admin: Address) {
+            e.storage().instance().set(&symbol_short!("callback"), &(target, admin));
+        }
+
+        /// Whether the preset_callback() call succeeded, if it was made.
+        pub fn callback_ok(e: Env) -> Option<bool> {
+            e.storage().instance().get(&symbol_short!("cb_ok"))
+        }
+
+        /// Sets the underlying reported by get_assets().
+        pub fn preset_asset(e: Env, asset: Address) {
+            e.storage().instance().set(&symbol_short!("asset"), &asset);
+        }
+
+        // ── vault interface ───────────────────────────────────────────────────
+
+        /// Reports the preset underlying with no strategies.
+        pub fn get_assets(e: Env) -> Vec<AssetStrategySet> {
+            let asset: Address = e.storage().instance().get(&symbol_short!("asset")).unwrap();
+            vec![&e, AssetStrategySet { address: asset, strategies: vec![&e] }]
+        }
+
+        /// Burns `withdraw_shares` from `from` and reports their underlying
+        /// value at the tracked exchange rate.  deposit() never pulls the
+        /// underlying, so the depositor already holds what is "withdrawn".
+        pub fn withdraw(
+            e: Env,
+            withdraw_shares: i128,
+            _min_amounts_out: Vec<i128>,
+            from: Address,
+        ) -> Vec<i128> {
+            accrue(&e);
+            let amount = Self::get_asset_amounts_per_shares(e.clone(), withdraw_shares)
+                .get(0)
+                .unwrap();
+            let und: i128 = e.storage().instance().get(&symbol_short!("und")).unwrap_or(0);
+            let sup: i128 = e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0);
+            e.storage().instance().set(&symbol_short!("und"), &(und - amount));
+            e.storage().instance().set(&symbol_short!("sup"), &(sup - withdraw_shares));
+
+            let mut bals = balances(&e);
+            let cur = bals.get(from.clone()).unwrap_or(0);
+            bals.set(from, cur - withdraw_shares);
+            save_bals(&e, &bals);
+            vec![&e, amount]
+        }
+
+        /// Mints df tokens to `from`.  Uses preset if set, otherwise 1:1.
+        /// Third element is `()` which decodes as `Option::None` on the caller
+        /// side — matching the real vault's return type.
+        /// Also accumulates the exchange-rate state used by
+        /// `get_asset_amounts_per_shares`.
+        pub fn deposit(
+            e: Env,
+            amounts_desired: Vec<i128>,
+            _amounts_min: Vec<i128>,
+            from: Address,
+            _invest: bool,
+        ) -> (Vec<i128>, i128, ()) {
+            // The real vault pulls from and mints to `from`, so it needs its auth.
+            from.require_auth();
+            let mut total: i128 = 0;
+            for a in amounts_desired.iter() {
+                total += a;
+            }
+            accrue(&e);
+
+            // Track cumulative underlying and supply for get_asset_amounts_per_shares.
+            let prev_und: i128 = e.storage().instance().get(&symbol_short!("und")).unwrap_or(0);
+            let prev_sup: i128 = e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0);
+            let drifting = e.storage().instance().has(&symbol_short!("drift"));
+            let priced = if drifting && prev_sup > 0 { total * prev_sup / prev_und } else { total };
+            let df_minted: i128 = e
+                .storage()
+                .instance()
+                .get(&symbol_short!("preset"))
+                .unwrap_or(priced); // default: 1:1, or the drifted price
+            let loss: i128 = e.storage().instance().get(&symbol_short!("loss")).unwrap_or(0);
+            let elsewhere: i128 =
+                e.storage().instance().get(&symbol_short!("elsewhr")).unwrap_or(0);
+            e.storage().instance().set(&symbol_short!("und"), &(prev_und + total - loss));
+            e.storage().instance().set(&symbol_short!("sup"), &(prev_sup + df_minted + elsewhere));
+
+            let inject: i128 = e.storage().instance().get(&symbol_short!("inject")).unwrap_or(0);
+
+            let mut bals = balances(&e);
+            let cur = bals.get(from.clone()).unwrap_or(0);
+            bals.set(from, cur + df_minted + inject);
+            save_bals(&e, &bals);
+
+            let callback: Option<(Address, Address)> =
+                e.storage().instance().get(&symbol_short!("callback"));
+            if let Some((target, admin)) = callback {
+                let ok = e
+                    .try_invoke_contract::<(), Error>(
+                        &target,
+                        &Symbol::new(&e, "set_min_amount"),
+                        vec![&e, admin.into_val(&e), 1_i128.into_val(&e)],
+                    )
+                    .is_ok();
+                e.storage().instance().set(&symbol_short!("cb_ok"), &ok);
+            }
+
+            (amounts_desired, df_minted, ())
+        }
+
+        /// Returns the underlying value of `vault_shares` shares.
+        /// Mirrors the real vault's `get_asset_amounts_per_shares` interface
+        /// (returns a single-element Vec for the one underlying asset).
+        pub fn get_asset_amounts_per_shares(e: Env, vault_shares: i128) -> Vec<i128> {
+            if e.storage().instance().has(&symbol_short!("noval")) {
+                panic!("valuation unavailable");
+            }
+            let mut valued: Vec<i128> =
+                e.storage().instance().get(&symbol_short!("valued")).unwrap_or(vec![&e]);
+            valued.push_back(vault_shares);
+            e.storage().instance().set(&symbol_short!("valued"), &valued);
+            let total_und = managed(&e);
+            let total_sup: i128 =
+                e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0);
+            let amount = if total_sup == 0 {
+                0
+            } else {
+                vault_shares * total_und / total_sup
+            };
+            vec![&e, amount]
+        }
+
+        /// Total df tokens minted so far.
+        pub fn total_supply(e: Env) -> i128 {
+            e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0)
+        }
+
+        /// Reports the managed underlying as half idle, half in one strategy,
+        /// with `total_amount` off by the preset skew.
+        pub fn fetch_total_managed_funds(e: Env) -> Vec<CurrentAssetInvestmentAllocation> {
+            let und = managed(&e);
+            let skew: i128 = e.storage().instance().get(&symbol_short!("skew")).unwrap_or(0);
+            let invested = und / 2;
+            vec![
+                &e,
+                CurrentAssetInvestmentAllocation {
+                    asset: e.current_contract_address(),
+                    idle_amount: und - invested,
+                    invested_amount: invested,
+                    strategy_allocations: vec![
+                        &e,
+                        StrategyAllocation {
+                            amount: invested,
+                            paused: false,
+                            strategy_address: e.current_contract_address(),
+                        },
+                    ],
+                    total_amount: und + skew,
+                },
+            ]
+        }
+
+        // ── SEP-41 token interface (df token = vault address) ─────────────────
+
+        pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+            if e.storage().instance().get::<_, Address>(&symbol_short!("reject")) == Some(to.clone()) {
+                panic!("transfer rejected");
+            }
+            let mut bals = balances(&e);
+            let f = bals.get(from.clone()).unwrap_or(0);
+            let t = bals.get(to.clone()).unwrap_or(0);
+            if let Some(cap) = e.storage().instance().get::<_, i128>(&symbol_short!("cap")) {
+                if t + amount > cap {
+                    panic!("balance cap exceeded");
+                }
+            }
+            bals.set(from, f - amount);
+            bals.set(to, t + amount);
+            save_bals(&e, &bals);
+        }
+
+        /// SAC-style eligibility flag: false only for the preset frozen id.
+        pub fn authorized(e: Env, id: Address) -> bool {
+            e.storage().instance().get::<_, Address>(&symbol_short!("frozen")) != Some(id)
+        }
+
+        // ── test helper ───────────────────────────────────────────────────────
+
+        /// Every share amount get_asset_amounts_per_shares() was asked to
+        /// value, in call order.
+        pub fn valued_shares(e: Env) -> Vec<i128> {
+            e.storage().instance().get(&symbol_short!("valued")).unwrap_or(vec![&e])
+        }
+
+        pub fn balance(e: Env, address: Address) -> i128 {
+            balances(&e).get(address).unwrap_or(0)
+        }
+    }
+}
+
+use mock_vault::MockVaultClient;
+
+// ── Mock caller ───────────────────────────────────────────────────────────────
+//
+// A wrapper contract that funds distributions from its own balance.  Being a
+// contract, it cannot sign; it calls `distribute` directly (implicit auth for
+// the top-level call) and pre-authorises the nested asset transfer the
+// distributor makes on its behalf.
+
+mod mock_caller {
+    use crate::{DistributorClient, Recipient};
+    use soroban_sdk::auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation};
+    use soroban_sdk::{contract, contractimpl, vec, Address, Env, IntoVal, Symbol, Vec};
+
+    #[contract]
+    pub struct MockCaller;
+
+    #[contractimpl]
+    impl MockCaller {
+        pub fn distribute_via(
+            e: Env,
+            distributor: Address,
+            asset: Address,
+            vault: Address,
+            recipients: Vec<Recipient>,
+        ) -> Vec<(Address, i128)> {
+            let mut total: i128 = 0;
+            for r in recipients.iter() {
+                total += r.amount;
+            }
+
+            e.authorize_as_current_contract(vec![
+                &e,
+                InvokerContractAuthEntry::Contract(SubContractInvocation {
+                    context: ContractContext {
+                        contract: asset.clone(),
+                        fn_name: Symbol::new(&e, "transfer"),
+                        args: (e.current_contract_address(), distributor.clone(), total)
+                            .into_val(&e),
+                    },
+                    sub_invocations: vec![&e],
+                }),
+            ]);
+
+            DistributorClient::new(&e, &distributor).distribute(
+                &e.current_contract_address(),
+                &asset,
+                &vault,
+                &recipients,
+            )
+        }
+    }
+}
+
+// ── Mock stake ────────────────────────────────────────────────────────────────
+//
+// A staking contract that records the df tokens credited to each user.
+
+mod mock_stake {
+    use soroban_sdk::{contract, contractimpl, Address, Env, Map, Symbol};
+
+    fn staked_map(e: &Env) -> Map<Address, i128> {
+        e.storage()
+            .instance()
+            .get(&Symbol::new(e, "staked"))
+            .unwrap_or(Map::new(e))
+    }
+
+    #[contract]
+    pub struct MockStake;
+
+    #[contractimpl]
+    impl MockStake {
+        pub fn stake(e: Env, user: Address, amount: i128) {
+            let mut staked = staked_map(&e);
+            let prev = staked.get(user.clone()).unwrap_or(0);
+            staked.set(user, prev + amount);
+            e.storage().instance().set(&Symbol::new(&e, "staked"), &staked);
+        }
+
+        pub fn staked(e: Env, user: Address) -> i128 {
+            staked_map(&e).get(user).unwrap_or(0)
+        }
+    }
+}
+
+use mock_stake::MockStakeClient;
+
+// ── Mock hook ─────────────────────────────────────────────────────────────────
+//
+// A post-distribution hook that records its last callback, or fails on demand.
+
+mod mock_hook {
+    use soroban_sdk::{contract, contractimpl, Address, Env, Symbol};
+
+    #[contract]
+    pub struct MockHook;
+
+    #[contractimpl]
+    impl MockHook {
+        pub fn set_fail(e: Env, fail: bool) {
+            e.storage().instance().set(&Symbol::new(&e, "fail"), &fail);
+        }
+
+        pub fn on_distributed(
+            e: Env,
+            vault: Address,
+            total: i128,
+            df_minted: i128,
+            recipient_count: u32,
+        ) {
+            if e.storage().instance().get(&Symbol::new(&e, "fail")).unwrap_or(false) {
+                panic!("hook failed");
+            }
+            e.storage()
+                .instance()
+                .set(&Symbol::new(&e, "last"), &(vault, total, df_minted, recipient_count));
+        }
+
+        pub fn last(e: Env) -> Option<(Address, i128, i128, u32)> {
+            e.storage().instance().get(&Symbol::new(&e, "last"))
+        }
+    }
+}
+
+use mock_hook::MockHookClient;
+
+// ── Mock split vault ──────────────────────────────────────────────────────────
+//
+// A vault whose shares live in a separate token contract: deposit() mints
+// 1:1 shares of the `share` SAC (administered by the vault) to the depositor.
+
+mod mock_split_vault {
+    use soroban_sdk::{
+        contract, contractimpl, symbol_short, token::StellarAssetClient, vec, Address, Env, Vec,
+    };
+
+    #[contract]
+    pub struct MockSplitVault;
+
+    #[contractimpl]
+    impl MockSplitVault {
+        pub fn set_share_token(e: Env, share: Address) {
+            e.storage().instance().set(&symbol_short!("share"), &share);
+        }
+
+        pub fn deposit(
+            e: Env,
+            amounts_desired: Vec<i128>,
+            _amounts_min: Vec<i128>,
+            from: Address,
+            _invest: bool,
+        ) -> (Vec<i128>, i128, ()) {
+            let total: i128 = amounts_desired.iter().sum();
+            let share: Address = e.storage().instance().get(&symbol_short!("share")).unwrap();
+            StellarAssetClient::new(&e, &share).mint(&from, &total);
+            let sup: i128 = e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0);
+            e.storage().instance().set(&symbol_short!("sup"), &(sup + total));
+            (amounts_desired, total, ())
+        }
+
+        pub fn get_asset_amounts_per_shares(e: Env, vault_shares: i128) -> Vec<i128> {
+            vec![&e, vault_shares]
+        }
+
+        pub fn total_supply(e: Env) -> i128 {
+            e.storage().instance().get(&symbol_short!("sup")).unwrap_or(0)
+        }
+    }
+}
+
+// ── setup helper ──────────────────────────────────────────────────────────────
+
+fn setup(e: &Env) -> (Address, Address, DistributorClient<'_>) {
+    let admin = Address::generate(e);
+    let asset_id = e.register_stellar_asset_contract_v2(admin.clone()).address();
+    let vault_id = e.register(mock_vault::MockVault, ());
+    let distributor_id = e.register(Distributor, (admin,));
+    (asset_id, vault_id, DistributorClient::new(e, &distributor_id))
+}
+
+/// `DistributionBatch::batch_id` of `caller`'s distribution of `recipients`
+/// made at the contract's `nonce`-th id.
+fn distribution_batch_id(
+    env: &Env,
+    caller: &Address,
+    asset: &Address,
+    vault: &Address,
+    recipients: &Vec<Recipient>,
+    nonce: u64,
+) -> BytesN<32> {
+    storage::distribution_id(env, caller, asset, vault, recipients, nonce)
+}
+
+// ── tests ─────────────────────────────────────────────────────────────────────
+
+/// Basic two-recipient, 1:1 mock rate — exact split, no rounding.
+#[test]
+fn test_two_recipients_exact_split() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    // total=1000, df_minted=1000 (1:1 default)
+    // user1: floor(300*1000/1000) = 300
+    // user2 (last): 1000 - 300 = 700
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 300_i128 },
+        Recipient { address: recipient2.clone(), amount: 700_i128 },
+    ];
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 300_i128));
+    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 700_i128));
+    assert_eq!(vault.balance(&recipient1), 300_i128);
+    assert_eq!(vault.balance(&recipient2), 700_i128);
+    assert_eq!(vault.balance(&caller), 0_i128);
+}
+
+/// Vault rate is non-1:1 (3 input → 10 df tokens).
+/// Verifies floor division: floor(1 * 10 / 3) = 3, remainder goes to last.
+#[test]
+fn test_uneven_split_floors_correctly() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    // Simulate vault issuing 10 df tokens for a 3-unit deposit (non-1:1)
+    vault.preset_df_mint(&10_i128);
+
+    // total=3, df_minted=10
+    // user1: floor(1 * 10 / 3) = floor(3.33) = 3
+    // user2 (last): 10 - 3 = 7
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &3_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 1_i128 },
+        Recipient { address: recipient2.clone(), amount: 2_i128 },
+    ];
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 3_i128));
+    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 7_i128));
+    assert_eq!(vault.balance(&recipient1), 3_i128);
+    assert_eq!(vault.balance(&recipient2), 7_i128);
+}
 
 /// Remainder from rounding goes to the last recipient, not lost as dust.
 #[test]
-fn test_rounding_remainder_goes_to_last() {
+fn test_rounding_remainder_goes_to_last() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let recipient3 = Address::generate(&env);
+
+    // Vault issues 10 df tokens for 9 units in (non-1:1)
+    vault.preset_df_mint(&10_i128);
+
+    // total=9, df_minted=10
+    // user1: floor(3 * 10 / 9) = floor(3.33) = 3
+    // user2: floor(3 * 10 / 9) = floor(3.33) = 3
+    // user3 (last): 10 - 3 - 3 = 4  (gets remainder, not floor(3.33)=3)
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 3_i128 },
+        Recipient { address: recipient2.clone(), amount: 3_i128 },
+        Recipient { address: recipient3.clone(), amount: 3_i128 },
+    ];
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 3_i128));
+    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 3_i128));
+    assert_eq!(results.get(2).unwrap(), (recipient3.clone(), 4_i128));
+    assert_eq!(vault.balance(&recipient3), 4_i128);
+}
+
+/// Single recipient always hits the "last" path and receives all df tokens.
+#[test]
+fn test_single_recipient_gets_all_df_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    // Vault issues 999 df tokens for 500 units in
+    vault.preset_df_mint(&999_i128);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient.clone(), amount: 500_i128 },
+    ];
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(results.get(0).unwrap(), (recipient.clone(), 999_i128));
+    assert_eq!(vault.balance(&recipient), 999_i128);
+    assert_eq!(vault.balance(&caller), 0_i128);
+}
+
+/// Sum of all distributed df tokens must equal df_tokens_minted — no dust lost.
+#[test]
+fn test_no_df_tokens_lost_to_rounding() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+
+    let vault = MockVaultClient::new(&env, &vault_id);
+    // 7 inputs → 13 df tokens: guarantees non-trivial rounding
+    vault.preset_df_mint(&13_i128);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &7_i128);
+
+    let users: [Address; 5] = core::array::from_fn(|_| Address::generate(&env));
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: users[0].clone(), amount: 1_i128 },
+        Recipient { address: users[1].clone(), amount: 1_i128 },
+        Recipient { address: users[2].clone(), amount: 2_i128 },
+        Recipient { address: users[3].clone(), amount: 1_i128 },
+        Recipient { address: users[4].clone(), amount: 2_i128 },
+    ];
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    let total_distributed: i128 = (0..5_u32).map(|i| results.get(i).unwrap().1).sum();
+    assert_eq!(total_distributed, 13_i128);
+    assert_eq!(vault.balance(&caller), 0_i128);
+}
+
+/// The result Vec preserves the exact input order of `recipients`, so
+/// integrators can zip it against their input list by index.
+#[test]
+fn test_results_preserve_input_order() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    // 50 recipients exceed the default test budget and the mainnet
+    // per-invocation event-size limit; this test is about ordering only.
+    env.cost_estimate().budget().reset_unlimited();
+    env.cost_estimate().disable_resource_limits();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    let n: i128 = 50;
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &(n * (n + 1) / 2));
+
+    // Generated addresses are in arbitrary (not sorted) order
+    let mut recipients: Vec<Recipient> = vec![&env];
+    for i in 1..=n {
+        recipients.push_back(Recipient { address: Address::generate(&env), amount: i });
+    }
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(results.len(), recipients.len());
+    for (r, (user, df)) in recipients.iter().zip(results.iter()) {
+        assert_eq!(user, r.address);
+        assert_eq!(df, r.amount); // 1:1 mock rate
+    }
+}
+
+/// `preview_remainder` predicts the last recipient's remainder under a
+/// non-1:1 price: `df_tokens_minted - sum(floors)`.
+#[test]
+fn test_preview_remainder_non_1to1() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    // Seed the price at 9 underlying : 10 df tokens
+    vault.preset_df_mint(&10_i128);
+    let seeder = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&seeder, &9_i128);
+    client.distribute(&seeder, &asset_id, &vault_id, &vec![
+        &env, Recipient { address: seeder.clone(), amount: 9_i128 },
+    ]);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 3_i128 },
+        Recipient { address: Address::generate(&env), amount: 3_i128 },
+        Recipient { address: Address::generate(&env), amount: 3_i128 },
+    ];
+
+    // minted = floor(9 * 10 / 9) = 10; floors = 3 + 3; remainder = 10 - 6 = 4
+    let preview = client.preview_remainder(&vault_id, &recipients);
+    assert_eq!(preview, 4_i128);
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+    let floors = results.get(0).unwrap().1 + results.get(1).unwrap().1;
+    assert_eq!(preview, 10_i128 - floors);
+    assert_eq!(preview, results.get(2).unwrap().1);
+}
+
+/// Named recipients take their fixed amounts; `change_to` receives the rest
+/// of the deposit plus rounding dust, not the last named recipient.
+#[test]
+fn test_distribute_with_change() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    // Vault mints 1100 df tokens for the 1000 deposit
+    vault.preset_df_mint(&1100_i128);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let change     = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 301_i128 },
+        Recipient { address: recipient2.clone(), amount: 199_i128 },
+    ];
+    let results = client.distribute_with_change(
+        &caller, &asset_id, &vault_id, &1000_i128, &recipients, &change,
+    );
+
+    // floor(301*1100/1000) = 331, floor(199*1100/1000) = 218, change = 1100 - 549
+    assert_eq!(
+        results,
+        vec![&env, (recipient1.clone(), 331_i128), (recipient2.clone(), 218_i128), (change.clone(), 551_i128)]
+    );
+    assert_eq!(vault.balance(&recipient1), 331_i128);
+    assert_eq!(vault.balance(&recipient2), 218_i128);
+    assert_eq!(vault.balance(&change), 551_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// `change_to` is validated like a named recipient: the distributor itself
+/// is rejected.
+#[test]
+#[should_panic(expected = "change_to address is invalid")]
+fn test_distribute_with_change_invalid_change_to_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 500_i128 }];
+    client.distribute_with_change(
+        &caller, &asset_id, &vault_id, &1000_i128, &recipients, &client.address,
+    );
+}
+
+/// A `change_to` that is also a named recipient is rejected as a duplicate.
+#[test]
+#[should_panic(expected = "change_to is already a recipient")]
+fn test_distribute_with_change_duplicate_change_to_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 500_i128 }];
+    client.distribute_with_change(
+        &caller, &asset_id, &vault_id, &1000_i128, &recipients, &recipient,
+    );
+}
+
+/// `distribute_tuples` gives the same results as the struct-based call.
+#[test]
+fn test_distribute_tuples_matches_distribute() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+
+    let structs: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 600_i128 },
+        Recipient { address: recipient2.clone(), amount: 400_i128 },
+    ];
+    let tuples: Vec<(Address, i128)> = vec![
+        &env,
+        (recipient1.clone(), 600_i128),
+        (recipient2.clone(), 400_i128),
+    ];
+
+    let from_structs = client.distribute(&caller, &asset_id, &vault_id, &structs);
+    let from_tuples = client.distribute_tuples(&caller, &asset_id, &vault_id, &tuples);
+
+    assert_eq!(from_tuples, from_structs);
+    assert_eq!(MockVaultClient::new(&env, &vault_id).balance(&recipient1), 1200_i128);
+}
+
+/// `distribute_from_keys` pays the account address of each ed25519 key,
+/// exactly as `distribute` would pay those addresses.
+#[test]
+fn test_distribute_from_keys_pays_key_accounts() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let key1 = BytesN::from_array(&env, &[1_u8; 32]);
+    let key2 = BytesN::from_array(&env, &[2_u8; 32]);
+    let keys: Vec<(BytesN<32>, i128)> = vec![&env, (key1.clone(), 300_i128), (key2.clone(), 700_i128)];
+    let results = client.distribute_from_keys(&caller, &asset_id, &vault_id, &keys);
+
+    let account1 = Address::from_payload(&env, AddressPayload::AccountIdPublicKeyEd25519(key1.clone()));
+    let account2 = Address::from_payload(&env, AddressPayload::AccountIdPublicKeyEd25519(key2));
+    assert_eq!(results, vec![&env, (account1.clone(), 300_i128), (account2.clone(), 700_i128)]);
+    assert_eq!(vault.balance(&account1), 300_i128);
+    assert_eq!(vault.balance(&account2), 700_i128);
+    assert_eq!(account1.to_payload(), Some(AddressPayload::AccountIdPublicKeyEd25519(key1)));
+}
+
+/// The single-recipient fast path in `distribute` ends with the same balances
+/// as the general path and costs fewer CPU instructions.
+#[test]
+fn test_single_recipient_fast_path() {
+    // Runs one single-recipient distribution in a fresh env and returns
+    // (cpu instructions, recipient df balance, caller asset balance).  Any
+    // non-default option, here a matching `expected_total`, takes the
+    // general path.
+    fn run(fast: bool) -> (u64, i128, i128) {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let (asset_id, vault_id, client) = setup(&env);
+        let caller    = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+        let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 700_i128 }];
+
+        env.cost_estimate().budget().reset_default();
+        if fast {
+            client.distribute(&caller, &asset_id, &vault_id, &recipients);
+        } else {
+            let options = DistributeOptions { expected_total: Some(700_i128), ..Default::default() };
+            client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+        }
+        let cpu = env.cost_estimate().budget().cpu_instruction_cost();
+
+        (
+            cpu,
+            MockVaultClient::new(&env, &vault_id).balance(&recipient),
+            TokenClient::new(&env, &asset_id).balance(&caller),
+        )
+    }
+
+    let (fast_cpu, fast_df, fast_left) = run(true);
+    let (general_cpu, general_df, general_left) = run(false);
+
+    assert_eq!(fast_df, 700_i128);
+    assert_eq!((fast_df, fast_left), (general_df, general_left));
+    assert!(
+        fast_cpu < general_cpu,
+        "fast path cpu {} not below general path cpu {}", fast_cpu, general_cpu
+    );
+}
+
+/// The auth tree for `distribute` is exactly the caller's asset transfer.
+#[test]
+fn test_required_auth_tree_lists_asset_transfer() {
+    let env = Env::default();
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let recipients = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 600_i128 },
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+    ];
+    let tree = client.required_auth_tree(&Address::generate(&env), &asset_id, &vault_id, &recipients);
+
+    assert_eq!(tree, vec![&env, (asset_id, Symbol::new(&env, "transfer"))]);
+}
+
+// ── Invalid recipient tests ───────────────────────────────────────────────────
+
+/// The all-zero placeholder account is rejected, naming its index.
+#[test]
+#[should_panic(expected = "recipient 1 address is invalid")]
+fn test_zero_address_recipient_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 600_i128 },
+        Recipient { address: Address::from_str(&env, ZERO_ACCOUNT), amount: 400_i128 },
+    ]);
+}
+
+/// The distributor itself is rejected, including as the only recipient,
+/// since shares sent to it would be stranded.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_distributor_as_recipient_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: client.address.clone(), amount: 1000_i128 },
+    ]);
+}
+
+/// A list whose only recipient is the distributor itself.
+fn self_as_recipient(env: &Env, client: &DistributorClient) -> Vec<Recipient> {
+    vec![env, Recipient { address: client.address.clone(), amount: 1000_i128 }]
+}
+
+/// `distribute_best_effort` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_best_effort_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    client.distribute_best_effort(&caller, &asset_id, &vault_id, &self_as_recipient(&env, &client), &None);
+}
+
+/// `distribute_escrow` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_escrow_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &self_as_recipient(&env, &client));
+}
+
+/// `offer_distribution` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_offer_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    client.set_escrow_expiry(&client.get_admin(), &100_u32);
+    client.offer_distribution(&caller, &asset_id, &vault_id, &self_as_recipient(&env, &client));
+}
+
+/// `distribute_direct` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_direct_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    client.distribute_direct(&caller, &asset_id, &vault_id, &self_as_recipient(&env, &client));
+}
+
+/// `distribute_at_rate` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_at_rate_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let recipients = self_as_recipient(&env, &client);
+    client.distribute_at_rate(&client.get_admin(), &caller, &asset_id, &vault_id, &recipients, &1_i128);
+}
+
+/// `distribute_from_holder` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_from_holder_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    MockVaultClient::new(&env, &vault_id).preset_asset(&asset_id);
+    let holder = Address::generate(&env);
+    client.distribute_from_holder(&caller, &holder, &vault_id, &self_as_recipient(&env, &client));
+}
+
+/// `distribute_tranched` rejects the distributor in either tranche.
+#[test]
+#[should_panic(expected = "recipient 1 address is invalid")]
+fn test_tranched_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let invested = sorted_recipients(&env, 1, 100_i128);
+    client.distribute_tranched(&caller, &asset_id, &vault_id, &invested, &self_as_recipient(&env, &client));
+}
+
+/// `distribute_from_budget` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_from_budget_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let spender = Address::generate(&env);
+    client.set_budget(&caller, &spender, &asset_id, &1000_i128);
+    client.distribute_from_budget(&spender, &caller, &asset_id, &vault_id, &self_as_recipient(&env, &client));
+}
+
+/// `distribute_with_reserve` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_with_reserve_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let reserve_to = Address::generate(&env);
+    let recipients = self_as_recipient(&env, &client);
+    client.distribute_with_reserve(&caller, &asset_id, &vault_id, &1000_i128, &1000_u32, &reserve_to, &recipients);
+}
+
+/// `distribute_with_reserve` rejects the distributor as `reserve_to`, which
+/// would strand the reserve on the contract.
+#[test]
+#[should_panic(expected = "reserve_to address is invalid")]
+fn test_with_reserve_rejects_self_as_reserve_to() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_with_reserve(&caller, &asset_id, &vault_id, &1000_i128, &1000_u32, &client.address, &recipients);
+}
+
+/// `distribute_with_reserve` rejects a burn address as `reserve_to`.
+#[test]
+#[should_panic(expected = "reserve_to address is invalid")]
+fn test_with_reserve_rejects_zero_reserve_to() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let reserve_to = Address::from_str(&env, ZERO_ACCOUNT);
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_with_reserve(&caller, &asset_id, &vault_id, &1000_i128, &1000_u32, &reserve_to, &recipients);
+}
+
+/// `distribute_capped_underlying` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_capped_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let overflow_to = Address::generate(&env);
+    let recipients = self_as_recipient(&env, &client);
+    client.distribute_capped_underlying(&caller, &asset_id, &vault_id, &recipients, &500_i128, &overflow_to);
+}
+
+/// `distribute_capped_underlying` rejects the distributor as `overflow_to`,
+/// which would strand the clamped shares on the contract.
+#[test]
+#[should_panic(expected = "overflow_to address is invalid")]
+fn test_capped_rejects_self_as_overflow_to() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_capped_underlying(&caller, &asset_id, &vault_id, &recipients, &100_i128, &client.address);
+}
+
+/// `distribute_capped_underlying` rejects a burn address as `overflow_to`.
+#[test]
+#[should_panic(expected = "overflow_to address is invalid")]
+fn test_capped_rejects_zero_overflow_to() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let overflow_to = Address::from_str(&env, ZERO_CONTRACT);
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_capped_underlying(&caller, &asset_id, &vault_id, &recipients, &100_i128, &overflow_to);
+}
+
+/// `distribute_with_change` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_with_change_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let change_to = Address::generate(&env);
+    let recipients = self_as_recipient(&env, &client);
+    client.distribute_with_change(&caller, &asset_id, &vault_id, &1000_i128, &recipients, &change_to);
+}
+
+/// `distribute_with_swap` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_with_swap_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let router = Address::generate(&env);
+    let path = vec![&env, asset_id.clone()];
+    let recipients = self_as_recipient(&env, &client);
+    client.distribute_with_swap(&caller, &asset_id, &router, &path, &vault_id, &1_i128, &recipients);
+}
+
+/// `distribute_with_bonus` rejects the distributor as a recipient.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_with_bonus_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+    let recipients = self_as_recipient(&env, &client);
+    client.distribute_with_bonus(&caller, &asset_id, &vault_id, &recipients, &asset_id, &1_i128);
+}
+
+/// A referral that would strand the rebate is rejected.
+#[test]
+#[should_panic(expected = "referral address is invalid")]
+fn test_referral_rejects_invalid_address() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let options = DistributeOptions { referral: Some(Address::from_str(&env, ZERO_ACCOUNT)), ..Default::default() };
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// Staking into the distributor itself is rejected.
+#[test]
+#[should_panic(expected = "stake_contract address is invalid")]
+fn test_stake_contract_rejects_invalid_address() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let options = DistributeOptions { stake_contract: Some(client.address.clone()), ..Default::default() };
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// The underlying fee cannot be routed to the distributor.
+#[test]
+#[should_panic(expected = "fee recipient address is invalid")]
+fn test_underlying_fee_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let _ = (asset_id, vault_id, caller);
+    client.set_underlying_fee(&client.get_admin(), &client.address, &100_u32);
+}
+
+/// `emergency_drain` cannot send the redeemed underlying to a burn address.
+#[test]
+#[should_panic(expected = "drain target address is invalid")]
+fn test_emergency_drain_rejects_invalid_target() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let _ = (asset_id, caller);
+    let to = Address::from_str(&env, ZERO_ACCOUNT);
+    client.emergency_drain(&client.get_admin(), &vault_id, &to, &false);
+}
+
+// ── Nested distribution tests ─────────────────────────────────────────────────
+
+/// Two groups, each subdivided by bps: every member receives its slice of
+/// its group, with each group's last member absorbing that group's remainder.
+#[test]
+fn test_distribute_nested_two_groups() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1_600_i128);
+
+    let team: Vec<Address> = vec![
+        &env, Address::generate(&env), Address::generate(&env), Address::generate(&env),
+    ];
+    let advisors: Vec<Address> = vec![&env, Address::generate(&env), Address::generate(&env)];
+    let team_bps: Vec<u32> = vec![&env, 5_000, 3_333, 1_667];
+    let advisor_bps: Vec<u32> = vec![&env, 2_500, 7_500];
+
+    let mut groups: Vec<(i128, Vec<RecipientBps>)> = vec![&env];
+    for (total, members, bps) in [(1_000_i128, &team, &team_bps), (600_i128, &advisors, &advisor_bps)] {
+        let mut group: Vec<RecipientBps> = vec![&env];
+        for (address, bps) in members.iter().zip(bps.iter()) {
+            group.push_back(RecipientBps { address, bps });
+        }
+        groups.push_back((total, group));
+    }
+
+    let results = client.distribute_nested(&caller, &asset_id, &vault_id, &groups);
+
+    // team: 500, floor(1000 * 3333 / 10000) = 333, 1000 - 833 = 167
+    // advisors: 150, 450
+    let expected = [500_i128, 333, 167, 150, 450];
+    for (i, (address, df)) in results.iter().enumerate() {
+        assert_eq!(df, expected[i]);
+        assert_eq!(vault.balance(&address), expected[i]);
+    }
+
+    // Each group's split obeys the floor/remainder rule over its bps
+    let team_shares: Vec<i128> = vec![&env, 500, 333, 167];
+    let team_weights: Vec<i128> = vec![&env, 5_000, 3_333, 1_667];
+    assert_floor_remainder(&team_weights, 1_000, &team_shares);
+    let advisor_shares: Vec<i128> = vec![&env, 150, 450];
+    let advisor_weights: Vec<i128> = vec![&env, 2_500, 7_500];
+    assert_floor_remainder(&advisor_weights, 600, &advisor_shares);
+}
+
+/// A group whose bps do not add up to 100% is rejected.
+#[test]
+#[should_panic(expected = "group 1 bps must sum to 10000")]
+fn test_distribute_nested_rejects_bad_bps() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &200_i128);
+
+    let groups: Vec<(i128, Vec<RecipientBps>)> = vec![
+        &env,
+        (100_i128, vec![&env, RecipientBps { address: Address::generate(&env), bps: 10_000 }]),
+        (100_i128, vec![&env, RecipientBps { address: Address::generate(&env), bps: 9_999 }]),
+    ];
+    client.distribute_nested(&caller, &asset_id, &vault_id, &groups);
+}
+
+// ── Balance-share tests ───────────────────────────────────────────────────────
+
+/// Distributing 50% of the caller's balance pays half of it out by the
+/// recipients' bps and leaves the other half with the caller.
+#[test]
+fn test_distribute_half_of_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let token = TokenClient::new(&env, &asset_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1_001_i128);
+
+    let r1 = Address::generate(&env);
+    let r2 = Address::generate(&env);
+    let results = client.distribute_balance_bps(&caller, &asset_id, &vault_id, &5_000_u32, &vec![
+        &env,
+        RecipientBps { address: r1.clone(), bps: 7_000 },
+        RecipientBps { address: r2.clone(), bps: 3_000 },
+    ]);
+
+    // total = floor(1001 / 2) = 500 → 350 / 150
+    assert_eq!(results, vec![&env, (r1.clone(), 350_i128), (r2.clone(), 150_i128)]);
+    assert_eq!(vault.balance(&r1), 350_i128);
+    assert_eq!(token.balance(&caller), 501_i128);
+}
+
+/// A balance too small for a non-zero share is rejected.
+#[test]
+#[should_panic(expected = "computed total is zero")]
+fn test_distribute_balance_bps_zero_total_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1_i128);
+
+    client.distribute_balance_bps(&caller, &asset_id, &vault_id, &5_000_u32, &vec![
+        &env,
+        RecipientBps { address: Address::generate(&env), bps: 10_000 },
+    ]);
+}
+
+// ── Recipient set tests ───────────────────────────────────────────────────────
+
+/// A registered set can be distributed against repeatedly, picks up updates,
+/// and is gone once removed.
+#[test]
+fn test_recipient_set_distribute_update_and_remove() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let admin = client.get_admin();
+    let name = Symbol::new(&env, "team");
+
+    let caller = Address::generate(&env);
+    let alice  = Address::generate(&env);
+    let bob    = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+
+    client.register_set(&admin, &name, &vec![
+        &env,
+        Recipient { address: alice.clone(), amount: 300_i128 },
+        Recipient { address: bob.clone(),   amount: 200_i128 },
+    ]);
+
+    client.distribute_set(&caller, &asset_id, &vault_id, &name);
+    client.distribute_set(&caller, &asset_id, &vault_id, &name);
+    assert_eq!(vault.balance(&alice), 600_i128);
+    assert_eq!(vault.balance(&bob), 400_i128);
+
+    // Re-registering replaces the set
+    client.register_set(&admin, &name, &vec![
+        &env,
+        Recipient { address: bob.clone(), amount: 1000_i128 },
+    ]);
+    assert_eq!(client.get_set(&name).unwrap().len(), 1);
+    client.distribute_set(&caller, &asset_id, &vault_id, &name);
+    assert_eq!(vault.balance(&alice), 600_i128);
+    assert_eq!(vault.balance(&bob), 1400_i128);
+
+    client.remove_set(&admin, &name);
+    assert!(client.get_set(&name).is_none());
+}
+
+/// Distributing against an unknown set name panics.
+#[test]
+#[should_panic(expected = "recipient set not found")]
+fn test_distribute_unknown_set_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.distribute_set(&Address::generate(&env), &asset_id, &vault_id, &Symbol::new(&env, "nobody"));
+}
+
+// ── Sorted fast-path tests ────────────────────────────────────────────────────
+
+/// Builds `n` recipients with distinct generated addresses sorted ascending.
+fn sorted_recipients(env: &Env, n: u32, amount: i128) -> Vec<Recipient> {
+    let mut addrs: std::vec::Vec<Address> = (0..n).map(|_| Address::generate(env)).collect();
+    addrs.sort();
+    let mut recipients: Vec<Recipient> = vec![env];
+    for a in addrs {
+        recipients.push_back(Recipient { address: a, amount });
+    }
+    recipients
+}
+
+/// A correctly sorted list passes the adjacent-comparison dedup check and
+/// distributes exactly like the Map-based path.
+#[test]
+fn test_assume_sorted_fast_path() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+
+    let recipients = sorted_recipients(&env, 10, 100_i128);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let options = DistributeOptions { assume_sorted: true, ..Default::default() };
+    let results = client.distribute_with_options(
+        &caller, &asset_id, &vault_id, &recipients, &options,
+    );
+
+    assert_eq!(results.len(), 10);
+    for (i, (user, df)) in results.iter().enumerate() {
+        assert_eq!(user, recipients.get(i as u32).unwrap().address);
+        assert_eq!(df, 100_i128);
+        assert_eq!(vault.balance(&user), 100_i128);
+    }
+}
+
+/// An unsorted list passed with `assume_sorted` is rejected, so the fast path
+/// can never let a duplicate slip through.
+#[test]
+#[should_panic(expected = "recipients are not sorted by address")]
+fn test_assume_sorted_rejects_unsorted_list() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+
+    let sorted = sorted_recipients(&env, 3, 100_i128);
+    // Swap the first two entries to break the ordering
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        sorted.get(1).unwrap(),
+        sorted.get(0).unwrap(),
+        sorted.get(2).unwrap(),
+    ];
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &300_i128);
+
+    let options = DistributeOptions { assume_sorted: true, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// df tokens a vault slips to the distributor beyond the reported mint are
+/// never distributed: the split uses `df_tokens_minted`, not the live
+/// balance, so the injected tokens stay behind as dust.
+#[test]
+fn test_injected_df_tokens_are_not_distributed() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    let user1  = Address::generate(&env);
+    let user2  = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    vault.preset_inject(&5000_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user1.clone(), amount: 600_i128 },
+        Recipient { address: user2.clone(), amount: 400_i128 },
+    ]);
+
+    assert_eq!(vault.balance(&user1), 600_i128);
+    assert_eq!(vault.balance(&user2), 400_i128);
+    assert_eq!(vault.balance(&client.address), 5000_i128);
+}
+
+/// A vault whose total supply grows by more than the mint it reports (it
+/// minted part of the deposit to another account) is caught before any
+/// df tokens are distributed.
+#[test]
+#[should_panic(expected = "vault supply change does not match minted df tokens")]
+fn test_supply_delta_mismatch_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    MockVaultClient::new(&env, &vault_id).preset_mint_elsewhere(&7_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+}
+
+// ── Tie-break tests ───────────────────────────────────────────────────────────
+
+/// With `remainder_to_largest_address`, equal amounts listed in two different
+/// orders give the remainder to the same (largest) address.
+#[test]
+fn test_remainder_to_largest_address_is_order_independent() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &18_i128);
+
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let c = Address::generate(&env);
+    let largest = [a.clone(), b.clone(), c.clone()].into_iter().max().unwrap();
+
+    let options = DistributeOptions { remainder_to_largest_address: true, ..Default::default() };
+    for order in [[a.clone(), b.clone(), c.clone()], [c.clone(), a.clone(), b.clone()]] {
+        // total=9, df_minted=10: two recipients get floor(3*10/9) = 3, one gets 4
+        vault.preset_df_mint(&10_i128);
+        let mut recipients: Vec<Recipient> = vec![&env];
+        for address in order {
+            recipients.push_back(Recipient { address, amount: 3_i128 });
+        }
+        let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+        for (address, df) in results.iter() {
+            assert_eq!(df, if address == largest { 4_i128 } else { 3_i128 });
+        }
+    }
+    assert_eq!(vault.balance(&largest), 8_i128);
+}
+
+// ── Repeat tests ──────────────────────────────────────────────────────────────
+
+/// `repeat_last` reapplies the 3 : 7 split of the previous distribution to a
+/// new total without re-specifying the recipients.
+#[test]
+fn test_repeat_last_reuses_proportions() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &3000_i128);
+
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: a.clone(), amount: 300_i128 },
+        Recipient { address: b.clone(), amount: 700_i128 },
+    ]);
+
+    let results = client.repeat_last(&caller, &asset_id, &vault_id, &2000_i128);
+    assert_eq!(results, vec![&env, (a.clone(), 600_i128), (b.clone(), 1400_i128)]);
+}
+
+/// Without a previous distribution into the vault there is nothing to repeat.
+#[test]
+#[should_panic(expected = "no previous distribution to repeat")]
+fn test_repeat_last_without_history_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.repeat_last(&Address::generate(&env), &asset_id, &vault_id, &100_i128);
+}
+
+/// A total too small for the 3 : 7 split's first floor is rejected rather
+/// than dropping that recipient: floor(1 * 300 / 1000) = 0.
+#[test]
+#[should_panic(expected = "total too small to give every recipient a share")]
+fn test_repeat_last_rejects_zero_floor() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1001_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 300_i128 },
+        Recipient { address: Address::generate(&env), amount: 700_i128 },
+    ]);
+
+    client.repeat_last(&caller, &asset_id, &vault_id, &1_i128);
+}
+
+// ── Max dust tests ────────────────────────────────────────────────────────────
+
+/// At an uneven rate the last recipient's remainder stays below one df token
+/// per other recipient, so a `max_dust` of `n - 1` always holds.
+#[test]
+fn test_max_dust_bounded_under_normal_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_df_mint(&9_999_991_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &7_000_i128);
+
+    let recipients = sorted_recipients(&env, 7, 1_000_i128);
+    let options = DistributeOptions { max_dust: Some(6), ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    // floor(1000 * 9_999_991 / 7000) = 1_428_570; the last takes 1_428_571
+    let floor = 1_428_570_i128;
+    let dust = results.get(6).unwrap().1 - floor;
+    assert!((0..=6).contains(&dust));
+    assert_eq!(dust, 1);
+}
+
+/// A remainder above `max_dust` fails the post-condition: three equal
+/// recipients sharing 200 df tokens floor to 66 each, leaving the last 2
+/// over its floor against a bound of 1.
+#[test]
+#[should_panic(expected = "rounding remainder exceeds max_dust")]
+fn test_max_dust_exceeded_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_df_mint(&200_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &300_i128);
+
+    let recipients = sorted_recipients(&env, 3, 100_i128);
+    let options = DistributeOptions { max_dust: Some(1), ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// The per-recipient bound holds without `max_dust`: a split leaving the
+/// last of three recipients 3 df tokens over its floor is rejected, and a
+/// looser `max_dust` cannot lift the bound.
+#[test]
+#[should_panic(expected = "rounding remainder exceeds one token per recipient")]
+fn test_dust_bounded_without_max_dust() {
+    let env = Env::default();
+    let recipients = sorted_recipients(&env, 3, 100_i128);
+    // floor(100 * 200 / 300) = 66 each, so the last may hold at most 68
+    let shares = vec![&env, 66_i128, 66_i128, 69_i128];
+    check_dust(&env, &recipients, 300, 200, &shares, 2, Some(100));
+}
+
+// ── Max balance tests ─────────────────────────────────────────────────────────
+
+/// A recipient whose balance would pass `max_balance` is rejected before any
+/// transfer, rather than inside a capped share token.
+#[test]
+#[should_panic(expected = "recipient balance would exceed max_balance")]
+fn test_max_balance_rejects_before_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let holder = Address::generate(&env);
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: holder.clone(), amount: 400_i128 },
+    ]);
+    vault.preset_balance_cap(&500_i128);
+
+    let recipients = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 300_i128 },
+        Recipient { address: holder.clone(), amount: 300_i128 },
+    ];
+    let options = DistributeOptions { max_balance: Some(500_i128), ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// Allocations that stay within `max_balance` go through unchanged.
+#[test]
+fn test_max_balance_within_cap_distributes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_balance_cap(&500_i128);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &800_i128);
+
+    let recipients = sorted_recipients(&env, 2, 400_i128);
+    let options = DistributeOptions { max_balance: Some(500_i128), ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+    assert_eq!(results.get(1).unwrap().1, 400_i128);
+}
+
+// ── Spread remainder tests ────────────────────────────────────────────────────
+
+/// Five equal recipients sharing 8 df tokens floor to 1 each; the remainder
+/// of 3 goes one token each to the first three, and the mint is fully paid.
+#[test]
+fn test_spread_remainder_round_robin() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_df_mint(&8_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
+
+    let recipients = sorted_recipients(&env, 5, 100_i128);
+    let options = DistributeOptions { spread_remainder: true, ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    let mut paid = 0_i128;
+    for (i, (address, df)) in (0..results.len()).zip(results.iter()) {
+        let expected = if i < 3 { 2_i128 } else { 1_i128 };
+        assert_eq!(df, expected);
+        assert_eq!(vault.balance(&address), expected);
+        paid += df;
+    }
+    assert_eq!(paid, 8_i128);
+}
+
+#[test]
+#[should_panic(expected = "spread_remainder conflicts with the remainder options")]
+fn test_spread_remainder_with_share_increment_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
+
+    let recipients = sorted_recipients(&env, 5, 100_i128);
+    let options =
+        DistributeOptions { spread_remainder: true, share_increment: 10, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+// ── Concentration tests ───────────────────────────────────────────────────────
+
+/// One recipient holding 95% of the total trips a `max_single_bps` of 9000
+/// before anything is deposited.
+#[test]
+#[should_panic(expected = "recipient exceeds max_single_bps")]
+fn test_max_single_bps_rejects_concentrated_batch() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 50_i128 },
+        Recipient { address: Address::generate(&env), amount: 950_i128 },
+    ];
+    let options = DistributeOptions { max_single_bps: Some(9000), ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// A recipient at exactly the limit is accepted.
+#[test]
+fn test_max_single_bps_allows_exact_limit() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 100_i128 },
+        Recipient { address: Address::generate(&env), amount: 900_i128 },
+    ];
+    let options = DistributeOptions { max_single_bps: Some(9000), ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+    assert_eq!(results.get(1).unwrap().1, 900_i128);
+}
+
+// ── Share increment tests ─────────────────────────────────────────────────────
+
+/// With `share_increment` 1000 every non-last share is a round lot and the
+/// last recipient absorbs what the rounding freed, so the mint is fully paid.
+#[test]
+fn test_share_increment_rounds_non_last_shares() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_df_mint(&12_345_678_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &10_000_i128);
+
+    let mut recipients: Vec<Recipient> = vec![&env];
+    for amount in [3000_i128, 3000_i128, 4000_i128] {
+        recipients.push_back(Recipient { address: Address::generate(&env), amount });
+    }
+    let options = DistributeOptions { share_increment: 1000, ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    // floor(3000 * 12_345_678 / 10_000) = 3_703_703 -> 3_703_000 twice; the
+    // last takes 4_938_272 + 2 * 703
+    let mut shares: Vec<i128> = vec![&env];
+    for (_, df) in results.iter() {
+        shares.push_back(df);
+    }
+    assert_eq!(shares, vec![&env, 3_703_000_i128, 3_703_000_i128, 4_939_678_i128]);
+    assert_eq!(shares.get(0).unwrap() % 1000, 0);
+    assert_eq!(shares.get(1).unwrap() % 1000, 0);
+    assert_eq!(shares.iter().sum::<i128>(), 12_345_678_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// A non-positive increment is rejected before any funds move.
+#[test]
+#[should_panic(expected = "share_increment must be positive")]
+fn test_share_increment_zero_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let options = DistributeOptions { share_increment: 0, ..Default::default() };
+    client.distribute_with_options(
+        &caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 50_i128), &options,
+    );
+}
+
+// ── Expected total tests ──────────────────────────────────────────────────────
+
+/// A declared `expected_total` equal to the recipients' sum distributes normally.
+#[test]
+fn test_expected_total_matching() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 600_i128 },
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+    ];
+    let options = DistributeOptions { expected_total: Some(1000_i128), ..Default::default() };
+    let results = client.distribute_with_options(
+        &caller, &asset_id, &vault_id, &recipients, &options,
+    );
+    assert_eq!(results.get(0).unwrap().1 + results.get(1).unwrap().1, 1000_i128);
+}
+
+/// A declared `expected_total` that differs from the sum panics before deposit.
+#[test]
+#[should_panic(expected = "total does not match expected_total")]
+fn test_expected_total_mismatch_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 600_i128 },
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+    ];
+    let options = DistributeOptions { expected_total: Some(999_i128), ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+// ── Accounting check tests ────────────────────────────────────────────────────
+
+/// A vault whose reported total drifts from its idle + strategy balances by
+/// more than the tolerance is rejected.
+#[test]
+#[should_panic(expected = "vault accounting is inconsistent")]
+fn test_inconsistent_vault_accounting_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_accounting_tolerance(&client.get_admin(), &vault_id, &5_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    vault.preset_skew(&6_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+}
+
+/// A drift within the tolerance is accepted.
+#[test]
+fn test_vault_accounting_within_tolerance_accepted() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_accounting_tolerance(&client.get_admin(), &vault_id, &5_i128);
+    assert_eq!(client.get_accounting_tolerance(&vault_id), Some(5_i128));
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    vault.preset_skew(&-5_i128);
+    let results = client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+    assert_eq!(results.len(), 2);
+}
+
+// ── Referral rebate tests ─────────────────────────────────────────────────────
+
+/// With no rebate configured (0 bps), the caller receives nothing and the
+/// recipients split the whole mint.
+#[test]
+fn test_referral_rebate_zero_bps() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    assert_eq!(client.get_referral_rebate(), 0);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = sorted_recipients(&env, 3, 333_i128);
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(vault.balance(&caller), 0);
+    let paid: i128 = results.iter().map(|(_, df)| df).sum();
+    assert_eq!(paid, 999_i128);
+}
+
+/// At 100 bps the caller receives 1% of the mint and the recipients split
+/// the remaining 99% pro-rata; together they reconcile to the full mint.
+#[test]
+fn test_referral_rebate_100_bps() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_referral_rebate(&client.get_admin(), &100_u32);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &10_000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 6_000_i128 },
+        Recipient { address: Address::generate(&env), amount: 4_000_i128 },
+    ];
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(vault.balance(&caller), 100_i128);
+    assert_eq!(results.get(0).unwrap().1, 5_940_i128);
+    assert_eq!(results.get(1).unwrap().1, 3_960_i128);
+    let paid: i128 = results.iter().map(|(_, df)| df).sum();
+    assert_eq!(paid + vault.balance(&caller), 10_000_i128);
+    assert_eq!(vault.balance(&client.address), 0);
+}
+
+/// A `referral` address in the options receives the rebate instead of the
+/// caller, including for a single-recipient list.
+#[test]
+fn test_referral_rebate_to_referral_address() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_referral_rebate(&client.get_admin(), &100_u32);
+
+    let caller = Address::generate(&env);
+    let referral = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipient = Address::generate(&env);
+    let options = DistributeOptions { referral: Some(referral.clone()), ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ], &options);
+
+    assert_eq!(vault.balance(&referral), 10_i128);
+    assert_eq!(vault.balance(&caller), 0);
+    assert_eq!(results, vec![&env, (recipient, 990_i128)]);
+}
+
+#[test]
+#[should_panic(expected = "referral rebate exceeds maximum")]
+fn test_referral_rebate_above_maximum_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_, _, client) = setup(&env);
+    client.set_referral_rebate(&client.get_admin(), &(MAX_REFERRAL_REBATE_BPS + 1));
+}
+
+// ── Underlying fee tests ──────────────────────────────────────────────────────
+
+/// A 1% underlying fee on 1000 sends 10 underlying to the fee recipient and
+/// deposits only 990, which the recipients split pro-rata.
+#[test]
+fn test_underlying_fee_withheld_before_deposit() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let token = TokenClient::new(&env, &asset_id);
+    let fee_to = Address::generate(&env);
+    client.set_underlying_fee(&client.get_admin(), &fee_to, &100_u32);
+
+    let caller = Address::generate(&env);
+    let user1  = Address::generate(&env);
+    let user2  = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user1.clone(), amount: 300_i128 },
+        Recipient { address: user2.clone(), amount: 700_i128 },
+    ]);
+
+    // floor(300 * 990 / 1000) = 297; the last takes 990 - 297
+    assert_eq!(results, vec![&env, (user1.clone(), 297_i128), (user2.clone(), 693_i128)]);
+    assert_eq!(token.balance(&fee_to), 10_i128);
+    assert_eq!(token.balance(&caller), 0_i128);
+    assert_eq!(vault.total_supply(), 990_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// The fee only applies to `distribute` and the modes built on it: best
+/// effort and escrow deposit their full total and send nothing to `to`.
+#[test]
+fn test_underlying_fee_not_charged_by_other_modes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let token = TokenClient::new(&env, &asset_id);
+    let fee_to = Address::generate(&env);
+    client.set_underlying_fee(&client.get_admin(), &fee_to, &100_u32);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    let results = client.distribute_best_effort(&caller, &asset_id, &vault_id, &recipients, &None);
+    assert_eq!(results.get(0).unwrap().1, 500_i128);
+    assert_eq!(results.get(1).unwrap().1, 500_i128);
+
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+
+    assert_eq!(token.balance(&fee_to), 0_i128);
+    assert_eq!(vault.total_supply(), 2000_i128);
+}
+
+/// With a 1% underlying fee and a 5% referral rebate configured, the
+/// fee-aware preview matches what `distribute` then pays out.
+#[test]
+fn test_preview_distribute_with_fee_matches_distribution() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1500_i128);
+    // Seed the vault so it has a share price to preview against
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 500_i128 },
+    ]);
+
+    client.set_underlying_fee(&admin, &Address::generate(&env), &100_u32);
+    client.set_referral_rebate(&admin, &500_u32);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 333_i128 },
+        Recipient { address: Address::generate(&env), amount: 667_i128 },
+    ];
+    let preview = client.preview_distribute_with_fee(&vault_id, &recipients);
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    // 990 deposited and minted, 49 rebated: floor(333 * 941 / 1000) = 313,
+    // the last takes 941 - 313
+    assert_eq!(preview, results);
+    assert_eq!(results.get(0).unwrap().1, 313_i128);
+    assert_eq!(results.get(1).unwrap().1, 628_i128);
+}
+
+/// The single-recipient fast path is skipped while a fee is set, and
+/// clearing the fee (0 bps) restores fee-free distributions.
+#[test]
+fn test_underlying_fee_single_recipient_and_clear() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let token = TokenClient::new(&env, &asset_id);
+    let admin = client.get_admin();
+    let fee_to = Address::generate(&env);
+    client.set_underlying_fee(&admin, &fee_to, &250_u32);
+    assert_eq!(
+        client.get_underlying_fee(),
+        Some(UnderlyingFeeConfig { to: fee_to.clone(), bps: 250 })
+    );
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 1, 1000_i128));
+    assert_eq!(results.get(0).unwrap().1, 975_i128);
+    assert_eq!(token.balance(&fee_to), 25_i128);
+
+    client.set_underlying_fee(&admin, &fee_to, &0_u32);
+    assert_eq!(client.get_underlying_fee(), None);
+    let results = client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 1, 1000_i128));
+    assert_eq!(results.get(0).unwrap().1, 1000_i128);
+    assert_eq!(token.balance(&fee_to), 25_i128);
+}
+
+// ── Loss protection tests ─────────────────────────────────────────────────────
+
+/// With `allow_loss` off, a vault whose minted shares redeem for less than the
+/// deposit is rejected.
+#[test]
+#[should_panic(expected = "minted shares are worth less than the deposit")]
+fn test_disallow_loss_rejects_loss_making_vault() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    // 1000 shares minted for 1000 deposited, but redeemable for only 900
+    vault.preset_loss(&100_i128);
+    let options = DistributeOptions { allow_loss: false, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 600_i128 },
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+    ], &options);
+}
+
+/// With `allow_loss` off, a vault that prices shares fairly distributes as
+/// usual.
+#[test]
+fn test_disallow_loss_accepts_fair_vault() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let options = DistributeOptions { allow_loss: false, ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 600_i128 },
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+    ], &options);
+
+    assert_eq!(results.get(0).unwrap().1 + results.get(1).unwrap().1, 1000_i128);
+}
+
+/// The loss check values exactly the shares this deposit minted (plus the
+/// one-share rounding allowance), never the vault's whole supply.
+#[test]
+fn test_disallow_loss_values_only_minted_shares() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &6000_i128);
+    // A prior distribution so the supply (5000) differs from the next mint
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 2500_i128));
+
+    let before = vault.valued_shares().len();
+    let options = DistributeOptions { allow_loss: false, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128), &options);
+
+    // The loss probe comes first; the later supply-wide read prices `RoundingLoss`
+    let valued = vault.valued_shares();
+    assert_eq!(valued.get(before), Some(1001_i128));
+    assert_eq!(vault.total_supply(), 6000_i128);
+}
+
+// ── Fallback valuation tests ──────────────────────────────────────────────────
+
+/// A vault whose valuation query reverts fails the distribution by default.
+#[test]
+#[should_panic]
+fn test_reverting_valuation_fails_without_fallback() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    MockVaultClient::new(&env, &vault_id).preset_valuation_revert();
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 3, 3_i128));
+}
+
+/// With `fallback_valuation`, the same vault distributes: the split is the
+/// usual floor/remainder one and `RoundingLoss` is priced at the deposit's
+/// own rate.
+#[test]
+fn test_fallback_valuation_splits_correctly() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_valuation_revert();
+    vault.preset_df_mint(&10_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+
+    let recipients = sorted_recipients(&env, 3, 3_i128);
+    let options = DistributeOptions {
+        fallback_valuation: true, emit_per_recipient: false, ..Default::default()
+    };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    let mut shares: Vec<i128> = vec![&env];
+    for (_, df) in results.iter() {
+        shares.push_back(df);
+    }
+    assert_eq!(shares, vec![&env, 3_i128, 3_i128, 4_i128]);
+    assert_floor_remainder(&vec![&env, 3_i128, 3_i128, 3_i128], 10_i128, &shares);
+
+    // Priced at 9 underlying per 10 shares: floor(2.7) + floor(2.7) + floor(3.6) = 7
+    let ev0 = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(), deposited: 9_i128, recoverable: 7_i128,
+    };
+    let ev1 = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 3, total_underlying: 9_i128, df_tokens: 10_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let ev2 = DistributionCost {
+        vault: vault_id.clone(), recipients: 3,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 3 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![
+            &env,
+            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+        ]
+    );
+}
+
+// ── Override rate tests ───────────────────────────────────────────────────────
+
+/// `distribute_at_rate` splits at the agreed rate, not the vault's: with 2000
+/// shares minted for 1000 underlying but priced at 1600, recipients get
+/// `amount * 2000 / 1600` and the unassigned shares go back to the caller.
+#[test]
+fn test_distribute_at_rate_uses_override() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_df_mint(&2000_i128);
+    let admin = client.get_admin();
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 300_i128 },
+        Recipient { address: recipient2.clone(), amount: 700_i128 },
+    ];
+    let results = client.distribute_at_rate(
+        &admin, &caller, &asset_id, &vault_id, &recipients, &1600_i128,
+    );
+
+    // The live rate would have given 600 / 1400.
+    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 375_i128));
+    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 875_i128));
+    assert_eq!(vault.balance(&recipient1), 375_i128);
+    assert_eq!(vault.balance(&recipient2), 875_i128);
+    assert_eq!(vault.balance(&caller), 750_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// An override below the deposited total would promise more shares than
+/// were minted.
+#[test]
+#[should_panic(expected = "override rate undervalues minted shares")]
+fn test_distribute_at_rate_rejects_low_override() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+
+    client.distribute_at_rate(
+        &admin, &caller, &asset_id, &vault_id, &sorted_recipients(&env, 3, 3_i128), &8_i128,
+    );
+}
+
+// ── Direct deposit tests ──────────────────────────────────────────────────────
+
+/// `distribute_direct` makes one vault deposit per recipient with the
+/// recipient as depositor: each recipient's shares are minted straight to
+/// them and neither the caller nor the distributor ever holds df tokens.
+#[test]
+fn test_distribute_direct_mints_to_each_recipient() {
+    let env = Env::default();
+    // Recipients authorize their deposit below the root call.
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 300_i128 },
+        Recipient { address: recipient2.clone(), amount: 700_i128 },
+    ];
+    let results = client.distribute_direct(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(
+        results,
+        vec![&env, (recipient1.clone(), 300_i128), (recipient2.clone(), 700_i128)]
+    );
+    assert_eq!(vault.balance(&recipient1), 300_i128);
+    assert_eq!(vault.balance(&recipient2), 700_i128);
+    assert_eq!(vault.balance(&caller), 0_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+    assert_eq!(vault.total_supply(), 1000_i128);
+    assert_eq!(TokenClient::new(&env, &asset_id).balance(&caller), 0_i128);
+    assert_eq!(client.df_distributed(&vault_id), 1000_i128);
+}
+
+/// `distribute_from_holder` moves df tokens a separate holder already owns
+/// to the recipients, with no deposit and no df tokens passing through the
+/// caller or the distributor.
+#[test]
+fn test_distribute_from_holder_splits_holder_shares() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_asset(&asset_id);
+
+    let funder = Address::generate(&env);
+    let holder = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&funder, &1000_i128);
+    client.distribute(&funder, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: holder.clone(), amount: 1000_i128 },
+    ]);
+    let supply = vault.total_supply();
+
+    let caller = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let results = client.distribute_from_holder(&caller, &holder, &vault_id, &vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 400_i128 },
+        Recipient { address: recipient2.clone(), amount: 500_i128 },
+    ]);
+
+    assert_eq!(
+        results,
+        vec![&env, (recipient1.clone(), 400_i128), (recipient2.clone(), 500_i128)]
+    );
+    assert_eq!(vault.balance(&holder), 100_i128);
+    assert_eq!(vault.balance(&recipient1), 400_i128);
+    assert_eq!(vault.balance(&recipient2), 500_i128);
+    assert_eq!(vault.balance(&caller), 0_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+    assert_eq!(vault.total_supply(), supply, "no deposit made");
+}
+
+// ── Price drift tests ─────────────────────────────────────────────────────────
+
+/// With a drift of 10 bps per ledger the mock's share price rises 10% over
+/// 100 ledgers, and a later distribution mints at the drifted price.
+#[test]
+fn test_mock_vault_price_drifts_with_ledgers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_sequence_number(1_000);
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_drift(&10_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2100_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+    assert_eq!(vault.get_asset_amounts_per_shares(&1000_i128), vec![&env, 1000_i128]);
+
+    env.ledger().set_sequence_number(1_050);
+    let halfway = vault.get_asset_amounts_per_shares(&1000_i128).get(0).unwrap();
+    env.ledger().set_sequence_number(1_100);
+    let after = vault.get_asset_amounts_per_shares(&1000_i128).get(0).unwrap();
+    assert!(1000 < halfway && halfway < after);
+    assert_eq!(after, 1100_i128);
+
+    // 1100 underlying now buys 1000 shares
+    let results = client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 550_i128));
+    assert_eq!(results.get(0).unwrap().1 + results.get(1).unwrap().1, 1000_i128);
+}
+
+// ── Reconcile tests ───────────────────────────────────────────────────────────
+
+/// Top-up targets are validated like a recipient list: the distributor
+/// itself is rejected.
+#[test]
+#[should_panic(expected = "recipient 1 address is invalid")]
+fn test_distribute_to_target_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+
+    client.distribute_to_target(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        (Address::generate(&env), 100_i128),
+        (client.address.clone(), 100_i128),
+    ]);
+}
+
+/// `min_amount` applies to the underlying each deficit costs.
+#[test]
+#[should_panic(expected = "recipient 0 amount below min_amount")]
+fn test_distribute_to_target_enforces_min_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+    client.set_min_amount(&client.get_admin(), &50_i128);
+
+    client.distribute_to_target(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        (Address::generate(&env), 10_i128),
+    ]);
+}
+
+/// At a share price below 1 `distribute_to_target` can mint more than the
+/// deficit; the surplus is recorded for the caller and `reconcile` redeems
+/// it back to underlying.
+#[test]
+fn test_reconcile_redeems_target_surplus() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_asset(&asset_id);
+
+    // Seed the vault at 500 underlying per 1000 shares
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+    vault.preset_loss(&500_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128));
+    vault.preset_loss(&0_i128);
+
+    // Deposits ceil(101 * 500 / 1000) = 51 and the vault mints 200 shares
+    vault.preset_df_mint(&200_i128);
+    let fresh = Address::generate(&env);
+    client.distribute_to_target(&caller, &asset_id, &vault_id, &vec![&env, (fresh.clone(), 101_i128)]);
+    assert_eq!(vault.balance(&fresh), 101_i128);
+    assert_eq!(vault.balance(&caller), 99_i128);
+    assert_eq!(client.get_residual(&caller, &vault_id), 99_i128);
+
+    // 99 shares at 551 underlying per 1200 shares
+    let usdc_before = TokenClient::new(&env, &asset_id).balance(&caller);
+    let out = client.reconcile(&caller, &vault_id);
+    assert_eq!(out, 99 * 551 / 1200);
+    assert_eq!(TokenClient::new(&env, &asset_id).balance(&caller), usdc_before + out);
+    assert_eq!(vault.balance(&caller), 0_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+    assert_eq!(client.get_residual(&caller, &vault_id), 0_i128);
+}
+
+/// Nothing to redeem without a recorded residual.
+#[test]
+#[should_panic(expected = "nothing to reconcile")]
+fn test_reconcile_without_residual_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_, vault_id, client) = setup(&env);
+    client.reconcile(&Address::generate(&env), &vault_id);
+}
+
+// ── Zero-amount tests ─────────────────────────────────────────────────────────
+
+/// With `skip_zero`, interleaved zero rows are dropped: they receive nothing
+/// and the remainder goes to the last non-zero recipient.
+#[test]
+fn test_skip_zero_rows() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    // total = 9, vault mints 10 → floor(3*10/9) = 3, remainder 10 - 3 = 7
+    vault.preset_df_mint(&10_i128);
+
+    let caller = Address::generate(&env);
+    let zero1  = Address::generate(&env);
+    let user1  = Address::generate(&env);
+    let zero2  = Address::generate(&env);
+    let user2  = Address::generate(&env);
+    let zero3  = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: zero1.clone(), amount: 0_i128 },
+        Recipient { address: user1.clone(), amount: 3_i128 },
+        Recipient { address: zero2.clone(), amount: 0_i128 },
+        Recipient { address: user2.clone(), amount: 6_i128 },
+        Recipient { address: zero3.clone(), amount: 0_i128 },
+    ];
+    let options = DistributeOptions { skip_zero: true, ..Default::default() };
+    let results = client.distribute_with_options(
+        &caller, &asset_id, &vault_id, &recipients, &options,
+    );
+
+    assert_eq!(
+        results,
+        vec![
+            &env,
+            (zero1.clone(), 0_i128),
+            (user1.clone(), 3_i128),
+            (zero2.clone(), 0_i128),
+            (user2.clone(), 7_i128),
+            (zero3.clone(), 0_i128),
+        ]
+    );
+    assert_eq!(vault.balance(&user1), 3_i128);
+    assert_eq!(vault.balance(&user2), 7_i128);
+    assert_eq!(vault.balance(&zero1), 0_i128);
+    assert_eq!(vault.balance(&zero3), 0_i128);
+}
+
+/// Each zero row dropped under `skip_zero` emits a `RecipientRejected`
+/// event with its input index and reason, ahead of the batch events.
+#[test]
+fn test_skip_zero_emits_recipient_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let zero1  = Address::generate(&env);
+    let zero2  = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: zero1.clone(), amount: 0_i128 },
+        Recipient { address: Address::generate(&env), amount: 200_i128 },
+        Recipient { address: zero2.clone(), amount: 0_i128 },
+        Recipient { address: Address::generate(&env), amount: 300_i128 },
+    ];
+    let options = DistributeOptions { skip_zero: true, emit_per_recipient: false, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    let ev0 = RecipientRejected { index: 0, user: zero1, reason: RejectReason::ZeroAmount };
+    let ev1 = RecipientRejected { index: 2, user: zero2, reason: RejectReason::ZeroAmount };
+    let ev2 = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(), deposited: 500_i128, recoverable: 500_i128,
+    };
+    let ev3 = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 2, total_underlying: 500_i128, df_tokens: 500_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let ev4 = DistributionCost {
+        vault: vault_id.clone(), recipients: 2,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 2 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![
+            &env,
+            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+            (client.address.clone(), ev3.topics(&env), ev3.data(&env)),
+            (client.address.clone(), ev4.topics(&env), ev4.data(&env)),
+        ]
+    );
+}
+
+/// `skip_zero` still rejects negative amounts.
+#[test]
+#[should_panic(expected = "each recipient amount must be positive")]
+fn test_skip_zero_rejects_negative() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 0_i128 },
+        Recipient { address: Address::generate(&env), amount: -1_i128 },
+        Recipient { address: Address::generate(&env), amount: 100_i128 },
+    ];
+    let options = DistributeOptions { skip_zero: true, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+}
+
+/// With `skip_zero`, a list of only zero rows is rejected before any deposit
+/// instead of reaching the vault with a zero total.
+#[test]
+#[should_panic(expected = "no non-zero recipients")]
+fn test_skip_zero_all_zero_rows_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+
+    let options = DistributeOptions { skip_zero: true, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 0_i128 },
+        Recipient { address: Address::generate(&env), amount: 0_i128 },
+    ], &options);
+}
+
+// ── Tenure tests ──────────────────────────────────────────────────────────────
+
+/// Shares follow tenure: 10, 30 and 60 ledgers in the system split 1000
+/// df tokens 100 / 300 / 600.
+#[test]
+fn test_distribute_by_tenure_weights_by_ledgers_held() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.sequence_number = 100);
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller  = Address::generate(&env);
+    let newbie  = Address::generate(&env);
+    let regular = Address::generate(&env);
+    let veteran = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let results = client.distribute_by_tenure(&caller, &asset_id, &vault_id, &1000_i128, &vec![
+        &env,
+        (newbie.clone(), 90_u64),
+        (regular.clone(), 70_u64),
+        (veteran.clone(), 40_u64),
+    ]);
+
+    assert_eq!(results, vec![
+        &env,
+        (newbie, 100_i128),
+        (regular, 300_i128),
+        (veteran, 600_i128),
+    ]);
+}
+
+/// A join ledger after the current one is rejected.
+#[test]
+#[should_panic(expected = "join ledger is in the future")]
+fn test_distribute_by_tenure_future_join_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.sequence_number = 100);
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute_by_tenure(&caller, &asset_id, &vault_id, &1000_i128, &vec![
+        &env,
+        (Address::generate(&env), 50_u64),
+        (Address::generate(&env), 101_u64),
+    ]);
+}
+
+/// An entry that joined this ledger has no tenure: it gets nothing and does
+/// not absorb the remainder, which goes to the last entry with tenure.
+#[test]
+fn test_distribute_by_tenure_zero_tenure_last_gets_nothing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.sequence_number = 100);
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller   = Address::generate(&env);
+    let member1  = Address::generate(&env);
+    let member2  = Address::generate(&env);
+    let newcomer = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let results = client.distribute_by_tenure(&caller, &asset_id, &vault_id, &1000_i128, &vec![
+        &env,
+        (member1.clone(), 90_u64),
+        (member2.clone(), 80_u64),
+        (newcomer.clone(), 100_u64),
+    ]);
+
+    // floor(1000 * 10 / 30) = 333; member2 absorbs 1000 - 333
+    assert_eq!(results, vec![
+        &env,
+        (member1, 333_i128),
+        (member2, 667_i128),
+        (newcomer.clone(), 0_i128),
+    ]);
+    assert_eq!(MockVaultClient::new(&env, &vault_id).balance(&newcomer), 0_i128);
+}
+
+/// Tenure entries are validated like a recipient list: the distributor
+/// itself is rejected.
+#[test]
+#[should_panic(expected = "recipient 1 address is invalid")]
+fn test_distribute_by_tenure_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.sequence_number = 100);
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute_by_tenure(&caller, &asset_id, &vault_id, &1000_i128, &vec![
+        &env,
+        (Address::generate(&env), 50_u64),
+        (client.address.clone(), 60_u64),
+    ]);
+}
+
+/// `min_amount` applies to each entry's pro-rata part of `total`.
+#[test]
+#[should_panic(expected = "recipient 0 amount below min_amount")]
+fn test_distribute_by_tenure_enforces_min_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|l| l.sequence_number = 100);
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.set_min_amount(&client.get_admin(), &200_i128);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    // 10 of 100 ledgers: 100 underlying, below the 200 minimum
+    client.distribute_by_tenure(&caller, &asset_id, &vault_id, &1000_i128, &vec![
+        &env,
+        (Address::generate(&env), 90_u64),
+        (Address::generate(&env), 10_u64),
+    ]);
+}
+
+// ── Quadratic tests ───────────────────────────────────────────────────────────
+
+/// Contributions 1, 4, 100 and 2500 weigh 1, 2, 10 and 50 (63 in all), so a
+/// 630 deposit splits 10 / 20 / 100 / 500.
+#[test]
+fn test_distribute_quadratic_perfect_squares() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &630_i128);
+
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let c = Address::generate(&env);
+    let d = Address::generate(&env);
+    let results = client.distribute_quadratic(&caller, &asset_id, &vault_id, &630_i128, &vec![
+        &env,
+        (a.clone(), 1_i128),
+        (b.clone(), 4_i128),
+        (c.clone(), 100_i128),
+        (d.clone(), 2500_i128),
+    ]);
+
+    assert_eq!(results, vec![&env, (a, 10_i128), (b, 20_i128), (c, 100_i128), (d, 500_i128)]);
+}
+
+/// Non-square contributions are floored: 8, 99 and 120 weigh 2, 9 and 10,
+/// and a 100 deposit splits floor(200/21) = 9, floor(900/21) = 42, and the
+/// last takes the remaining 49.
+#[test]
+fn test_distribute_quadratic_floors_roots() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let c = Address::generate(&env);
+    let results = client.distribute_quadratic(&caller, &asset_id, &vault_id, &100_i128, &vec![
+        &env,
+        (a.clone(), 8_i128),
+        (b.clone(), 99_i128),
+        (c.clone(), 120_i128),
+    ]);
+
+    assert_eq!(results, vec![&env, (a, 9_i128), (b, 42_i128), (c, 49_i128)]);
+}
+
+/// A contribution of 2 weighs 1, the same as a contribution of 1, so a 100
+/// deposit splits evenly between them.
+#[test]
+fn test_distribute_quadratic_contribution_of_two() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let results = client.distribute_quadratic(&caller, &asset_id, &vault_id, &100_i128, &vec![
+        &env,
+        (a.clone(), 1_i128),
+        (b.clone(), 2_i128),
+    ]);
+
+    assert_eq!(results, vec![&env, (a, 50_i128), (b, 50_i128)]);
+}
+
+/// Contributions that all round to a zero weight are rejected.
+#[test]
+#[should_panic(expected = "total weight is zero")]
+fn test_distribute_quadratic_zero_weight_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    client.distribute_quadratic(&caller, &asset_id, &vault_id, &100_i128, &vec![
+        &env,
+        (Address::generate(&env), 0_i128),
+        (Address::generate(&env), 0_i128),
+    ]);
+}
+
+/// A zero contribution gets nothing and does not absorb the remainder,
+/// which goes to the last non-zero contributor.
+#[test]
+fn test_distribute_quadratic_zero_contribution_last_gets_nothing() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let c = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let results = client.distribute_quadratic(&caller, &asset_id, &vault_id, &100_i128, &vec![
+        &env,
+        (a.clone(), 1_i128),
+        (b.clone(), 4_i128),
+        (c.clone(), 0_i128),
+    ]);
+
+    // Weights 1 and 2: floor(100 / 3) = 33, b absorbs 100 - 33
+    assert_eq!(results, vec![&env, (a, 33_i128), (b, 67_i128), (c, 0_i128)]);
+}
+
+/// Contributions are validated as a recipient list before weighting: the
+/// distributor itself is rejected.
+#[test]
+#[should_panic(expected = "recipient 0 address is invalid")]
+fn test_distribute_quadratic_rejects_invalid_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    client.distribute_quadratic(&caller, &asset_id, &vault_id, &100_i128, &vec![
+        &env,
+        (client.address.clone(), 4_i128),
+        (Address::generate(&env), 9_i128),
+    ]);
+}
+
+/// `isqrt` is the exact floor root at square boundaries and at `i128::MAX`.
+#[test]
+fn test_isqrt_boundaries() {
+    for (n, root) in [(0, 0), (1, 1), (2, 1), (3, 1), (4, 2), (15, 3), (16, 4), (99, 9)] {
+        assert_eq!(isqrt(n), root);
+    }
+    let r = isqrt(i128::MAX);
+    assert_eq!(r, 13_043_817_825_332_782_212_i128);
+    assert!(r.checked_mul(r).is_some());
+    assert!((r + 1).checked_mul(r + 1).is_none());
+}
+
+// ── Best-effort tests ─────────────────────────────────────────────────────────
+
+/// A recipient whose transfer fails is reported with `succeeded = false` and
+/// their shares are refunded to the caller, while the others are paid; the
+/// zero row is skipped.
+#[test]
+fn test_distribute_best_effort_reports_failed_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let ok1    = Address::generate(&env);
+    let failed = Address::generate(&env);
+    let zero   = Address::generate(&env);
+    let ok2    = Address::generate(&env);
+    vault.preset_reject(&failed);
+
+    let results = client.distribute_best_effort(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: ok1.clone(), amount: 200_i128 },
+        Recipient { address: failed.clone(), amount: 300_i128 },
+        Recipient { address: zero.clone(), amount: 0_i128 },
+        Recipient { address: ok2.clone(), amount: 500_i128 },
+    ], &None);
+
+    // The refunded 300 are still worth 300, so nothing is lost to rounding
+    let loss = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(), deposited: 1000_i128, recoverable: 1000_i128,
+    };
+    assert!(env
+        .events()
+        .all()
+        .filter_by_contract(&client.address)
+        .events()
+        .contains(&loss.to_xdr(&env, &client.address)));
+    assert_eq!(
+        results,
+        vec![
+            &env,
+            (ok1.clone(), 200_i128, true),
+            (failed.clone(), 300_i128, false),
+            (zero.clone(), 0_i128, false),
+            (ok2.clone(), 500_i128, true),
+        ]
+    );
+    assert_eq!(vault.balance(&ok1), 200_i128);
+    assert_eq!(vault.balance(&ok2), 500_i128);
+    assert_eq!(vault.balance(&failed), 0_i128);
+    assert_eq!(vault.balance(&caller), 300_i128, "failed share refunded");
+    assert_eq!(vault.balance(&client.address), 0_i128);
+    assert_eq!(client.df_distributed(&vault_id), 700_i128);
+}
+
+/// A recipient the df token reports as not authorised is refunded up front
+/// without a transfer attempt and flagged `Ineligible`.
+#[test]
+fn test_distribute_best_effort_skips_ineligible_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let frozen = Address::generate(&env);
+    let ok     = Address::generate(&env);
+    vault.preset_frozen(&frozen);
+
+    let results = client.distribute_best_effort(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: frozen.clone(), amount: 400_i128 },
+        Recipient { address: ok.clone(), amount: 600_i128 },
+    ], &None);
+
+    assert_eq!(
+        results,
+        vec![&env, (frozen.clone(), 400_i128, false), (ok.clone(), 600_i128, true)]
+    );
+    let rejected = RecipientRejected { index: 0, user: frozen.clone(), reason: RejectReason::Ineligible };
+    assert!(env
+        .events()
+        .all()
+        .filter_by_contract(&client.address)
+        .events()
+        .contains(&rejected.to_xdr(&env, &client.address)));
+    assert_eq!(vault.balance(&frozen), 0_i128);
+    assert_eq!(vault.balance(&caller), 400_i128);
+    assert_eq!(vault.balance(&ok), 600_i128);
+}
+
+/// Under a 500 df-token cap, a recipient already holding 300 is clamped to
+/// 200 with the excess refunded, and one already at the cap is skipped.
+#[test]
+fn test_distribute_best_effort_clamps_to_balance_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &2000_i128);
+
+    let partial = Address::generate(&env);
+    let full    = Address::generate(&env);
+    let ok      = Address::generate(&env);
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: partial.clone(), amount: 300_i128 },
+        Recipient { address: full.clone(), amount: 500_i128 },
+    ]);
+    vault.preset_balance_cap(&500_i128);
+
+    let results = client.distribute_best_effort(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: partial.clone(), amount: 400_i128 },
+        Recipient { address: full.clone(), amount: 100_i128 },
+        Recipient { address: ok.clone(), amount: 200_i128 },
+    ], &Some(500_i128));
+
+    assert_eq!(
+        results,
+        vec![
+            &env,
+            (partial.clone(), 200_i128, true),
+            (full.clone(), 100_i128, false),
+            (ok.clone(), 200_i128, true),
+        ]
+    );
+    let rejected = RecipientRejected { index: 1, user: full.clone(), reason: RejectReason::BalanceCap };
+    assert!(env
+        .events()
+        .all()
+        .filter_by_contract(&client.address)
+        .events()
+        .contains(&rejected.to_xdr(&env, &client.address)));
+    assert_eq!(vault.balance(&partial), 500_i128);
+    assert_eq!(vault.balance(&full), 500_i128);
+    assert_eq!(vault.balance(&caller), 300_i128, "clamped and skipped shares refunded");
+}
+
+// ── Reserve tests ─────────────────────────────────────────────────────────────
+
+/// A 10% reserve takes 100 of the 1000 minted df tokens; the remaining 900
+/// split 1 : 2 between the recipients, and everything sums to the mint.
+#[test]
+fn test_distribute_with_reserve_ten_percent() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let treasury = Address::generate(&env);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let results = client.distribute_with_reserve(
+        &caller,
+        &asset_id,
+        &vault_id,
+        &1000_i128,
+        &1_000_u32,
+        &treasury,
+        &vec![
+            &env,
+            Recipient { address: a.clone(), amount: 1_i128 },
+            Recipient { address: b.clone(), amount: 2_i128 },
+        ],
+    );
+
+    assert_eq!(results, vec![&env, (a.clone(), 300_i128), (b.clone(), 600_i128)]);
+    assert_eq!(vault.balance(&treasury), 100_i128);
+    assert_eq!(vault.balance(&a) + vault.balance(&b) + vault.balance(&treasury), vault.total_supply());
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+// ── Invariant tests ───────────────────────────────────────────────────────────
+
+/// 20 seeded random cases: `distribute` splits every mint by the floor
+/// formula with the remainder on the last recipient.
+#[test]
+fn test_distribute_floor_remainder_invariant() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    let mut rng = Lcg::new(42);
+
+    for _ in 0..20 {
+        let n = rng.next_in(1, 10) as u32;
+        let mut recipients: Vec<Recipient> = vec![&env];
+        let mut amounts: Vec<i128> = vec![&env];
+        for _ in 0..n {
+            let amount = rng.next_in(1, 1_000_000_000);
+            recipients.push_back(Recipient { address: Address::generate(&env), amount });
+            amounts.push_back(amount);
+        }
+        let total: i128 = amounts.iter().sum();
+        let df_minted = rng.next_in(1, 1_000_000_000_000);
+        vault.preset_df_mint(&df_minted);
+        StellarAssetClient::new(&env, &asset_id).mint(&caller, &total);
+
+        let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+        let mut shares: Vec<i128> = vec![&env];
+        for (_, df) in results.iter() {
+            shares.push_back(df);
+        }
+        assert_floor_remainder(&amounts, df_minted, &shares);
+    }
+}
+
+/// The same invariant holds for the tenure-weighted split.
+#[test]
+fn test_distribute_by_tenure_floor_remainder_invariant() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+    env.ledger().with_mut(|l| l.sequence_number = 1_000_000);
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let caller = Address::generate(&env);
+    let mut rng = Lcg::new(7);
+
+    for _ in 0..20 {
+        let n = rng.next_in(1, 10) as u32;
+        let mut entries: Vec<(Address, u64)> = vec![&env];
+        let mut tenures: Vec<i128> = vec![&env];
+        for _ in 0..n {
+            let tenure = rng.next_in(1, 500_000);
+            entries.push_back((Address::generate(&env), 1_000_000 - tenure as u64));
+            tenures.push_back(tenure);
+        }
+        let total = rng.next_in(1, 1_000_000_000);
+        let df_minted = rng.next_in(1, 1_000_000_000_000);
+        vault.preset_df_mint(&df_minted);
+        StellarAssetClient::new(&env, &asset_id).mint(&caller, &total);
+
+        let results = client.distribute_by_tenure(&caller, &asset_id, &vault_id, &total, &entries);
+
+        let mut shares: Vec<i128> = vec![&env];
+        for (_, df) in results.iter() {
+            shares.push_back(df);
+        }
+        assert_floor_remainder(&tenures, df_minted, &shares);
+    }
+}
+
+// ── Staking tests ─────────────────────────────────────────────────────────────
+
+/// With a stake contract set, each recipient's allocation is staked on their
+/// behalf, except for recipients who opted out and receive shares directly.
+#[test]
+fn test_distribute_with_stake_contract_respects_opt_out() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let stake_id = env.register(mock_stake::MockStake, ());
+    let stake = MockStakeClient::new(&env, &stake_id);
+
+    let caller    = Address::generate(&env);
+    let user1     = Address::generate(&env);
+    let user2     = Address::generate(&env);
+    let opted_out = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.set_stake_opt_out(&opted_out, &true);
+    assert!(client.get_stake_opt_out(&opted_out));
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: user1.clone(),     amount: 500_i128 },
+        Recipient { address: opted_out.clone(), amount: 300_i128 },
+        Recipient { address: user2.clone(),     amount: 200_i128 },
+    ];
+    let options = DistributeOptions { stake_contract: Some(stake_id.clone()), ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    assert_eq!(stake.staked(&user1), results.get(0).unwrap().1);
+    assert_eq!(stake.staked(&user2), results.get(2).unwrap().1);
+    assert_eq!(stake.staked(&opted_out), 0_i128);
+    assert_eq!(vault.balance(&opted_out), results.get(1).unwrap().1);
+
+    // Staked shares sit with the stake contract, not the recipients
+    assert_eq!(vault.balance(&stake_id), 700_i128);
+    assert_eq!(vault.balance(&user1), 0_i128);
+    assert_eq!(vault.balance(&user2), 0_i128);
+}
+
+// ── Hook tests ────────────────────────────────────────────────────────────────
+
+/// The hook is called once, after the transfers, with the batch totals.
+#[test]
+fn test_hook_called_after_distribution() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let hook_id = env.register(mock_hook::MockHook, ());
+    let hook = MockHookClient::new(&env, &hook_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+    vault.preset_df_mint(&10_i128);
+
+    let recipients = sorted_recipients(&env, 3, 3_i128);
+    let options = DistributeOptions { hook: Some(hook_id.clone()), ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    assert_eq!(hook.last(), Some((vault_id.clone(), 9_i128, 10_i128, 3_u32)));
+}
+
+/// A failing hook reverts the distribution by default.
+#[test]
+#[should_panic]
+fn test_failing_hook_reverts_by_default() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let hook_id = env.register(mock_hook::MockHook, ());
+    MockHookClient::new(&env, &hook_id).set_fail(&true);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let options = DistributeOptions { hook: Some(hook_id), ..Default::default() };
+    client.distribute_with_options(
+        &caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 500_i128), &options,
+    );
+}
+
+/// With `hook_failure_ok`, a failing hook emits `HookFailed` and the
+/// distribution stands.
+#[test]
+fn test_failing_hook_tolerated() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let hook_id = env.register(mock_hook::MockHook, ());
+    MockHookClient::new(&env, &hook_id).set_fail(&true);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    let options = DistributeOptions {
+        hook: Some(hook_id.clone()),
+        hook_failure_ok: true,
+        emit_per_recipient: false,
+        ..Default::default()
+    };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    let ev0 = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(), deposited: 1000_i128, recoverable: 1000_i128,
+    };
+    let ev1 = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 2, total_underlying: 1000_i128, df_tokens: 1000_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let ev2 = DistributionCost {
+        vault: vault_id.clone(), recipients: 2,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 2 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+    let ev3 = HookFailed { hook: hook_id, vault: vault_id.clone() };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![
+            &env,
+            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+            (client.address.clone(), ev3.topics(&env), ev3.data(&env)),
+        ]
+    );
+    for r in recipients.iter() {
+        assert_eq!(vault.balance(&r.address), 500_i128);
+    }
+}
+
+// ── Share token tests ─────────────────────────────────────────────────────────
+
+/// With `share_token` set, the vault is only used for the deposit and shares
+/// are transferred in the separate share-token contract.
+#[test]
+fn test_distribute_with_separate_share_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, _, client) = setup(&env);
+    let vault_id = env.register(mock_split_vault::MockSplitVault, ());
+    let share_id = env.register_stellar_asset_contract_v2(vault_id.clone()).address();
+    mock_split_vault::MockSplitVaultClient::new(&env, &vault_id).set_share_token(&share_id);
+    let share = TokenClient::new(&env, &share_id);
+
+    let caller = Address::generate(&env);
+    let user1  = Address::generate(&env);
+    let user2  = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let options = DistributeOptions { share_token: Some(share_id.clone()), ..Default::default() };
+    let results = client.distribute_with_options(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user1.clone(), amount: 700_i128 },
+        Recipient { address: user2.clone(), amount: 300_i128 },
+    ], &options);
+
+    assert_eq!(results, vec![&env, (user1.clone(), 700_i128), (user2.clone(), 300_i128)]);
+    assert_eq!(share.balance(&user1), 700_i128);
+    assert_eq!(share.balance(&user2), 300_i128);
+    assert_eq!(share.balance(&client.address), 0_i128);
+    assert_eq!(client.df_distributed(&vault_id), 1000_i128);
+}
+
+// ── Event tests ───────────────────────────────────────────────────────────────
+
+/// One `Distributed` event is emitted per recipient.
+/// Verifies the topic name, contract address, and all data fields
+/// (asset, vault, user, underlying_amount, df_tokens) at a 1:1 mock rate.
+#[test]
+fn test_events_emitted_per_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    let amount1 = 600_i128;
+    let amount2 = 400_i128;
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &(amount1 + amount2));
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: amount1 },
+        Recipient { address: recipient2.clone(), amount: amount2 },
+    ];
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+    let df1 = results.get(0).unwrap().1;
+    let df2 = results.get(1).unwrap().1;
+
+    let ev0 = Distributed {
+        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient1.clone(),
+        underlying_amount: amount1, df_tokens: df1, seq: 0,
+    };
+    let ev1 = Distributed {
+        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient2.clone(),
+        underlying_amount: amount2, df_tokens: df2, seq: 1,
+    };
+    let ev2 = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(),
+        deposited: amount1 + amount2, recoverable: amount1 + amount2, // 1:1, no loss
+    };
+    let ev3 = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 2, total_underlying: amount1 + amount2, df_tokens: df1 + df2,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let ev4 = DistributionCost {
+        vault: vault_id.clone(), recipients: 2,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 2 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![
+            &env,
+            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+            (client.address.clone(), ev3.topics(&env), ev3.data(&env)),
+            (client.address.clone(), ev4.topics(&env), ev4.data(&env)),
+        ]
+    );
+}
+
+/// With a non-1:1 exchange rate and rounding, the event captures the original
+/// underlying input amount and the actual (floored or remainder) df_tokens.
+#[test]
+fn test_events_non_1to1_exchange_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    // Vault mints 10 df tokens for 9 units in → non-trivial rounding
+    vault.preset_df_mint(&10_i128);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    let recipient3 = Address::generate(&env);
+
+    // total=9, df_minted=10
+    // user1: floor(3*10/9) = 3, user2: floor(3*10/9) = 3, user3 (last): 10-3-3 = 4
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 3_i128 },
+        Recipient { address: recipient2.clone(), amount: 3_i128 },
+        Recipient { address: recipient3.clone(), amount: 3_i128 },
+    ];
+
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    let ev0 = Distributed {
+        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient1.clone(),
+        underlying_amount: 3_i128, df_tokens: 3_i128, seq: 0, // floor(3*10/9)
+    };
+    let ev1 = Distributed {
+        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient2.clone(),
+        underlying_amount: 3_i128, df_tokens: 3_i128, seq: 1, // floor(3*10/9)
+    };
+    let ev2 = Distributed {
+        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient3.clone(),
+        underlying_amount: 3_i128, df_tokens: 4_i128, seq: 2, // remainder: 10 - 3 - 3
+    };
+    // Valued at 9/10: floor(3*9/10) + floor(3*9/10) + floor(4*9/10) = 2 + 2 + 3
+    let ev3 = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(),
+        deposited: 9_i128, recoverable: 7_i128,
+    };
+    let ev4 = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 3, total_underlying: 9_i128, df_tokens: 10_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let ev5 = DistributionCost {
+        vault: vault_id.clone(), recipients: 3,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 3 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![
+            &env,
+            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+            (client.address.clone(), ev3.topics(&env), ev3.data(&env)),
+            (client.address.clone(), ev4.topics(&env), ev4.data(&env)),
+            (client.address.clone(), ev5.topics(&env), ev5.data(&env)),
+        ]
+    );
+}
+
+/// With `emit_per_recipient: false`, no `Distributed` events are published;
+/// only the per-batch summary events remain.
+#[test]
+fn test_suppress_per_recipient_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = sorted_recipients(&env, 5, 200_i128);
+    let options = DistributeOptions { emit_per_recipient: false, ..Default::default() };
+    client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+    let ev0 = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(), deposited: 1000_i128, recoverable: 1000_i128,
+    };
+    let ev1 = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 5, total_underlying: 1000_i128, df_tokens: 1000_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let ev2 = DistributionCost {
+        vault: vault_id.clone(), recipients: 5,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 5 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![
+            &env,
+            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+        ]
+    );
+
+    // Shares still reach every recipient
+    for r in recipients.iter() {
+        assert_eq!(vault.balance(&r.address), 200_i128);
+    }
+}
+
+/// Repeating a distribution with identical inputs yields a new
+/// `DistributionBatch::batch_id`, since each id consumes a fresh nonce.
+#[test]
+fn test_distribution_batch_id_unique_per_call() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &200_i128);
+
+    let recipients = sorted_recipients(&env, 2, 50_i128);
+    let options = DistributeOptions { emit_per_recipient: false, ..Default::default() };
+    let mut ids: std::vec::Vec<BytesN<32>> = std::vec::Vec::new();
+    for nonce in 0..2_u64 {
+        client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+        let id = distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, nonce);
+        let ev = DistributionBatch {
+            asset: asset_id.clone(), vault: vault_id.clone(),
+            recipients: 2, total_underlying: 100_i128, df_tokens: 100_i128,
+            batch_id: id.clone(),
+        };
+        assert!(env
+            .events()
+            .all()
+            .filter_by_contract(&client.address)
+            .events()
+            .contains(&ev.to_xdr(&env, &client.address)));
+        ids.push(id);
+    }
+    assert_ne!(ids[0], ids[1]);
+}
+
+/// `distribute_with_change` closes with a `DistributionBatch` and its
+/// `DistributionCost` receipt like `distribute`, counting `change_to` among
+/// the paid rows.
+#[test]
+fn test_distribute_with_change_emits_batch() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = sorted_recipients(&env, 2, 200_i128);
+    client.distribute_with_change(
+        &caller, &asset_id, &vault_id, &1000_i128, &recipients, &Address::generate(&env),
+    );
+
+    let ev = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 3, total_underlying: 1000_i128, df_tokens: 1000_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let cost = DistributionCost {
+        vault: vault_id.clone(),
+        recipients: 3,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT,
+        estimated_instructions: 3 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+    let all = env.events().all();
+    let events = all.filter_by_contract(&client.address);
+    assert!(events.events().contains(&ev.to_xdr(&env, &client.address)));
+    assert!(events.events().contains(&cost.to_xdr(&env, &client.address)));
+}
+
+/// With the named amounts covering the whole total at 1:1 there is no
+/// change: `change_to` is reported with 0 but gets no `Distributed` event
+/// and is left out of the batch count.
+#[test]
+fn test_distribute_with_change_skips_zero_change() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let change = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    let results = client.distribute_with_change(
+        &caller, &asset_id, &vault_id, &1000_i128, &recipients, &change,
+    );
+
+    let mut expected = vec![&env];
+    for (seq, r) in (0_u64..).zip(recipients.iter()) {
+        let ev = Distributed {
+            asset: asset_id.clone(), vault: vault_id.clone(), user: r.address,
+            underlying_amount: 500_i128, df_tokens: 500_i128, seq,
+        };
+        expected.push_back((client.address.clone(), ev.topics(&env), ev.data(&env)));
+    }
+    let loss = RoundingLoss {
+        vault: vault_id.clone(), asset: asset_id.clone(), deposited: 1000_i128, recoverable: 1000_i128,
+    };
+    let batch = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 2, total_underlying: 1000_i128, df_tokens: 1000_i128,
+        batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0),
+    };
+    let cost = DistributionCost {
+        vault: vault_id.clone(), recipients: 2,
+        unit_cost: INSTRUCTIONS_PER_RECIPIENT, estimated_instructions: 2 * INSTRUCTIONS_PER_RECIPIENT,
+    };
+    expected.push_back((client.address.clone(), loss.topics(&env), loss.data(&env)));
+    expected.push_back((client.address.clone(), batch.topics(&env), batch.data(&env)));
+    expected.push_back((client.address.clone(), cost.topics(&env), cost.data(&env)));
+    assert_eq!(env.events().all().filter_by_contract(&client.address), expected);
+    assert_eq!(results.get(2), Some((change, 0_i128)));
+}
+
+/// An escrow distribution's `DistributionBatch` carries the escrow batch id
+/// that recipients claim against.
+#[test]
+fn test_distribute_escrow_emits_batch_with_escrow_id() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &400_i128);
+
+    let recipients = sorted_recipients(&env, 2, 200_i128);
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
+
+    let ev = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 2, total_underlying: 400_i128, df_tokens: 400_i128,
+        batch_id,
+    };
+    assert!(env
+        .events()
+        .all()
+        .filter_by_contract(&client.address)
+        .events()
+        .contains(&ev.to_xdr(&env, &client.address)));
+}
+
+/// `distribute_with_id` returns the id its `DistributionBatch` event carries,
+/// alongside the same results `distribute` would give.
+#[test]
+fn test_distribute_with_id_returns_event_id() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    let (batch_id, results) = client.distribute_with_id(&caller, &asset_id, &vault_id, &recipients);
+    assert_eq!(results.get(1).unwrap().1, 500_i128);
+    assert_eq!(
+        batch_id,
+        distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0)
+    );
+
+    let ev = DistributionBatch {
+        asset: asset_id.clone(), vault: vault_id.clone(),
+        recipients: 2, total_underlying: 1000_i128, df_tokens: 1000_i128,
+        batch_id,
+    };
+    assert!(env
+        .events()
+        .all()
+        .filter_by_contract(&client.address)
+        .events()
+        .contains(&ev.to_xdr(&env, &client.address)));
+}
+
+/// The `DistributionCost` receipt scales linearly with the recipient count.
+#[test]
+fn test_distribution_cost_scales_with_recipients() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    for (nonce, n) in [2_u32, 6].into_iter().enumerate() {
+        let nonce = nonce as u64;
+        let recipients = sorted_recipients(&env, n, 10_i128);
+        let options = DistributeOptions { emit_per_recipient: false, ..Default::default() };
+        client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+        let total = 10_i128 * n as i128;
+        let ev0 = RoundingLoss {
+            vault: vault_id.clone(), asset: asset_id.clone(), deposited: total, recoverable: total,
+        };
+        let ev1 = DistributionBatch {
+            asset: asset_id.clone(), vault: vault_id.clone(),
+            recipients: n, total_underlying: total, df_tokens: total,
+            batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, nonce),
+        };
+        let ev2 = DistributionCost {
+            vault: vault_id.clone(), recipients: n,
+            unit_cost: INSTRUCTIONS_PER_RECIPIENT,
+            estimated_instructions: n as u64 * INSTRUCTIONS_PER_RECIPIENT,
+        };
+        assert_eq!(
+            env.events().all().filter_by_contract(&client.address),
+            vec![
+                &env,
+                (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
+                (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
+                (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
+            ]
+        );
+    }
+}
+
+/// `max_recipients_for_budget` is non-decreasing in the budget, matches the
+/// per-recipient estimate and never exceeds the per-transaction bound.
+#[test]
+fn test_max_recipients_for_budget_monotonic() {
+    let env = Env::default();
+    let (_, _, client) = setup(&env);
+
+    assert_eq!(client.max_recipients_for_budget(&0), 0);
+    assert_eq!(client.max_recipients_for_budget(&(INSTRUCTIONS_PER_RECIPIENT - 1)), 0);
+    assert_eq!(client.max_recipients_for_budget(&(10 * INSTRUCTIONS_PER_RECIPIENT)), 10);
+    assert_eq!(client.max_recipients_for_budget(&100_000_000), MAX_RECIPIENTS_PER_TX);
+    assert_eq!(client.max_recipients_for_budget(&u64::MAX), MAX_RECIPIENTS_PER_TX);
+
+    let mut prev = 0;
+    for budget in (0..=10_000_000_u64).step_by(250_000) {
+        let n = client.max_recipients_for_budget(&budget);
+        assert!(n >= prev);
+        assert!(n as u64 * INSTRUCTIONS_PER_RECIPIENT <= budget);
+        prev = n;
+    }
+}
+
+/// `seq` on `Distributed` is global: it continues contiguously from one
+/// distribution to the next.
+#[test]
+fn test_distributed_seq_contiguous_across_batches() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let mut next_seq: u64 = 0;
+    for (nonce, batch_size) in [2_u32, 3_u32].into_iter().enumerate() {
+        let nonce = nonce as u64;
+        let mut recipients: Vec<Recipient> = vec![&env];
+        for _ in 0..batch_size {
+            recipients.push_back(Recipient { address: Address::generate(&env), amount: 100_i128 });
+        }
+        client.distribute(&caller, &asset_id, &vault_id, &recipients);
+        let events = env.events().all().filter_by_contract(&client.address);
+
+        // Per-recipient `Distributed` events, then the batch summary events
+        let mut expected = vec![&env];
+        for r in recipients.iter() {
+            let ev = Distributed {
+                asset: asset_id.clone(), vault: vault_id.clone(), user: r.address,
+                underlying_amount: 100_i128, df_tokens: 100_i128, seq: next_seq,
+            };
+            expected.push_back((client.address.clone(), ev.topics(&env), ev.data(&env)));
+            next_seq += 1;
+        }
+        let total = 100_i128 * batch_size as i128;
+        let ev = RoundingLoss {
+            vault: vault_id.clone(), asset: asset_id.clone(), deposited: total, recoverable: total,
+        };
+        expected.push_back((client.address.clone(), ev.topics(&env), ev.data(&env)));
+        let ev = DistributionBatch {
+            asset: asset_id.clone(), vault: vault_id.clone(),
+            recipients: batch_size, total_underlying: total, df_tokens: total,
+            batch_id: distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, nonce),
+        };
+        expected.push_back((client.address.clone(), ev.topics(&env), ev.data(&env)));
+        let ev = DistributionCost {
+            vault: vault_id.clone(), recipients: batch_size,
+            unit_cost: INSTRUCTIONS_PER_RECIPIENT,
+            estimated_instructions: batch_size as u64 * INSTRUCTIONS_PER_RECIPIENT,
+        };
+        expected.push_back((client.address.clone(), ev.topics(&env), ev.data(&env)));
+
+        assert_eq!(events, expected);
+    }
+    assert_eq!(next_seq, 5);
+}
+
+// ── Version tests ─────────────────────────────────────────────────────────────
+
+/// `version` reports the ABI constant and `supported_modes` names the
+/// distribution entry points.
+#[test]
+fn test_version_and_supported_modes() {
+    let env = Env::default();
+    let (_, _, client) = setup(&env);
+
+    assert_eq!(client.version(), CONTRACT_VERSION);
+    assert_eq!(CONTRACT_VERSION, 50);
+
+    let modes = client.supported_modes();
+    assert!(modes.contains(Symbol::new(&env, "distribute")));
+    assert!(modes.contains(Symbol::new(&env, "distribute_escrow")));
+    assert!(!modes.contains(Symbol::new(&env, "claim")));
+}
+
+// ── Admin config tests ────────────────────────────────────────────────────────
+
+/// `get_config` reports the defaults on a fresh contract and reflects admin
+/// setters afterwards.
+#[test]
+fn test_get_config_defaults_and_updates() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_, _, client) = setup(&env);
+    let admin = client.get_admin();
+
+    let mut expected = DistributorConfig {
+        admin: admin.clone(),
+        paused: false,
+        ttl_params: TtlParams {
+            threshold: storage::DEFAULT_TTL_THRESHOLD,
+            extend_to: storage::DEFAULT_TTL_EXTEND_TO,
+        },
+        min_amount: 0,
+        min_recipients: 1,
+        max_recipients_per_tx: MAX_RECIPIENTS_PER_TX,
+        max_vaults_per_call: DEFAULT_MAX_VAULTS_PER_CALL,
+        escrow_expiry: 0,
+        referral_rebate_bps: 0,
+        underlying_fee_to: None,
+        underlying_fee_bps: 0,
+    };
+    assert_eq!(client.get_config(), expected);
+
+    client.set_min_recipients(&admin, &3_u32);
+    client.pause(&admin);
+    expected.min_recipients = 3;
+    expected.paused = true;
+    assert_eq!(client.get_config(), expected);
+}
+
+/// Custom TTL params are stored and `distribute` extends the instance TTL to
+/// the configured `extend_to` (the fresh instance TTL is below `threshold`).
+#[test]
+fn test_custom_ttl_params_applied_on_distribute() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+
+    client.set_ttl_params(&admin, &50_000_u32, &100_000_u32);
+    assert_eq!(
+        client.get_ttl_params(),
+        TtlParams { threshold: 50_000, extend_to: 100_000 }
+    );
+
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 100_i128 }];
+    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+    assert_eq!(results.get(0).unwrap(), (recipient, 100_i128));
+
+    let ttl = env.as_contract(&client.address, || {
+        use soroban_sdk::testutils::storage::Instance as _;
+        env.storage().instance().get_ttl()
+    });
+    assert_eq!(ttl, 100_000);
+}
+
+/// `threshold` above `extend_to` is rejected.
+#[test]
+#[should_panic(expected = "ttl threshold must be in 1..=extend_to")]
+fn test_ttl_params_threshold_above_extend_to_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_asset_id, _vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+
+    client.set_ttl_params(&admin, &200_u32, &100_u32);
+}
+
+/// Only the stored admin may change the TTL params.
+#[test]
+#[should_panic(expected = "caller is not the admin")]
+fn test_ttl_params_non_admin_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_asset_id, _vault_id, client) = setup(&env);
+    let impostor = Address::generate(&env);
+
+    client.set_ttl_params(&impostor, &100_u32, &200_u32);
+}
+
+/// A recipient below the admin-set `min_amount` is rejected by index before
+/// anything is deposited.
+#[test]
+#[should_panic(expected = "recipient 1 amount below min_amount")]
+fn test_recipient_below_min_amount_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+
+    client.set_min_amount(&admin, &100_i128);
+    assert_eq!(client.get_min_amount(), 100_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 100_i128 },
+        Recipient { address: Address::generate(&env), amount: 99_i128 },
+        Recipient { address: Address::generate(&env), amount: 801_i128 },
+    ];
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+}
+
+/// With `min_recipients` at 3, a 2-recipient list is rejected.
+#[test]
+#[should_panic(expected = "fewer recipients than min_recipients")]
+fn test_below_min_recipients_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    assert_eq!(client.get_min_recipients(), 1);
+    client.set_min_recipients(&client.get_admin(), &3_u32);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 100_i128));
+}
+
+/// With `min_recipients` at 3, a 3-recipient list distributes.
+#[test]
+fn test_at_min_recipients_succeeds() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.set_min_recipients(&client.get_admin(), &3_u32);
+    assert_eq!(client.get_min_recipients(), 3);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let results = client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 3, 100_i128));
+    assert_eq!(results.len(), 3);
+}
+
+/// With `min_recipients` at 2, a single recipient is rejected.
+#[test]
+#[should_panic(expected = "fewer recipients than min_recipients")]
+fn test_single_recipient_below_min_recipients_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.set_min_recipients(&client.get_admin(), &2_u32);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 1, 100_i128));
+}
+
+/// `distribute_mixed` entries over `n` distinct mock vaults of one asset,
+/// funding `caller` for all of them.
+fn mixed_entries(
+    env: &Env,
+    asset_id: &Address,
+    caller: &Address,
+    n: u32,
+) -> Vec<(Address, Address, Vec<Recipient>)> {
+    let mut entries = vec![env];
+    for _ in 0..n {
+        let vault_id = env.register(mock_vault::MockVault, ());
+        entries.push_back((asset_id.clone(), vault_id, sorted_recipients(env, 2, 50_i128)));
+    }
+    StellarAssetClient::new(env, asset_id).mint(caller, &(100 * n as i128));
+    entries
+}
+
+/// With the vault cap at 2, a `distribute_mixed` over exactly 2 vaults goes
+/// through.
+#[test]
+fn test_mixed_at_vault_cap_succeeds() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, _, client) = setup(&env);
+    assert_eq!(client.get_max_vaults_per_call(), DEFAULT_MAX_VAULTS_PER_CALL);
+    client.set_max_vaults_per_call(&client.get_admin(), &2_u32);
+
+    let caller = Address::generate(&env);
+    let results = client.distribute_mixed(&caller, &mixed_entries(&env, &asset_id, &caller, 2));
+    assert_eq!(results.len(), 2);
+}
+
+/// One vault over the cap is rejected.
+#[test]
+#[should_panic(expected = "too many vaults in one call")]
+fn test_mixed_over_vault_cap_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, _, client) = setup(&env);
+    client.set_max_vaults_per_call(&client.get_admin(), &2_u32);
+
+    let caller = Address::generate(&env);
+    client.distribute_mixed(&caller, &mixed_entries(&env, &asset_id, &caller, 3));
+}
+
+/// Pausing one asset blocks its distributions while another asset keeps
+/// distributing; unpausing restores it.
+#[test]
+fn test_pause_asset_blocks_only_that_asset() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_a, vault_a, client) = setup(&env);
+    let admin = client.get_admin();
+    let asset_b = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    let vault_b = env.register(mock_vault::MockVault, ());
+
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_a).mint(&caller, &200_i128);
+    StellarAssetClient::new(&env, &asset_b).mint(&caller, &100_i128);
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 100_i128 }];
+
+    client.pause_asset(&admin, &asset_a);
+    assert!(client.is_asset_paused(&asset_a));
+    assert!(!client.is_asset_paused(&asset_b));
+
+    assert!(client.try_distribute(&caller, &asset_a, &vault_a, &recipients).is_err());
+    client.distribute(&caller, &asset_b, &vault_b, &recipients);
+    assert_eq!(MockVaultClient::new(&env, &vault_b).balance(&recipient), 100_i128);
+
+    client.unpause_asset(&admin, &asset_a);
+    client.distribute(&caller, &asset_a, &vault_a, &recipients);
+    assert_eq!(MockVaultClient::new(&env, &vault_a).balance(&recipient), 100_i128);
+}
+
+/// Distributing a paused asset panics with the per-asset message.
+#[test]
+#[should_panic(expected = "asset is paused")]
+fn test_paused_asset_distribution_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.pause_asset(&client.get_admin(), &asset_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 100_i128 }];
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+}
+
+/// The global pause overrides per-asset state: an unpaused asset is blocked too.
+#[test]
+#[should_panic(expected = "distributions are paused")]
+fn test_global_pause_overrides_asset_state() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.pause(&client.get_admin());
+    assert!(client.is_paused());
+    assert!(!client.is_asset_paused(&asset_id));
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 100_i128 }];
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+}
+
+/// A deposit that leaves the vault quoting a share price inside the band
+/// goes through.
+#[test]
+fn test_price_within_bounds_distributes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.set_price_bounds(&client.get_admin(), &vault_id, &5_000_000_i128, &20_000_000_i128);
+    assert_eq!(client.get_price_bounds(&vault_id), Some((5_000_000_i128, 20_000_000_i128)));
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    // 1:1 mint prices a whole share at exactly PRICE_PROBE_SHARES underlying
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 1000_i128 }];
+    assert_eq!(client.distribute(&caller, &asset_id, &vault_id, &recipients).get(0).unwrap().1, 1000_i128);
+}
+
+/// A vault that mints far too few shares, pricing each one absurdly high, is
+/// rejected by the post-deposit probe.
+#[test]
+#[should_panic(expected = "vault share price out of bounds")]
+fn test_price_out_of_bounds_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_price_bounds(&client.get_admin(), &vault_id, &5_000_000_i128, &20_000_000_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    // 1000 underlying for 1 share: 1000 × PRICE_PROBE_SHARES per share
+    vault.preset_df_mint(&1_i128);
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 1000_i128 }];
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+}
+
+/// A vault calling back into an admin setter from inside `deposit` is
+/// turned away, and the distribution itself completes untouched.
+#[test]
+fn test_vault_callback_cannot_change_config() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    vault.preset_callback(&client.address, &client.get_admin());
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 1000_i128 }];
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(vault.callback_ok(), Some(false));
+    assert_eq!(client.get_min_amount(), 0_i128);
+}
+
+/// Admin setters check the distribution lock themselves, independently of
+/// the host's re-entry rule.
+#[test]
+#[should_panic(expected = "distribution in progress")]
+fn test_admin_setter_blocked_while_distributing() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_asset_id, _vault_id, client) = setup(&env);
+    env.as_contract(&client.address, || {
+        storage::set_distributing(&env, true);
+    });
+    client.pause(&client.get_admin());
+}
+
+/// The lock is released once a distribution finishes, so setters work again.
+#[test]
+fn test_admin_setter_allowed_after_distribution() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 1000_i128 }];
+    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    client.set_min_amount(&client.get_admin(), &5_i128);
+    assert_eq!(client.get_min_amount(), 5_i128);
+}
+
+/// Every mode that calls into a vault takes the same lock, not just
+/// `distribute`.
+#[test]
+#[should_panic(expected = "distribution in progress")]
+fn test_best_effort_blocked_while_distributing() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    env.as_contract(&client.address, || {
+        storage::set_distributing(&env, true);
+    });
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_best_effort(&caller, &asset_id, &vault_id, &recipients, &None);
+}
+
+/// The harvest runs under the lock too, so it cannot start while another
+/// distribution holds it.
+#[test]
+#[should_panic(expected = "distribution in progress")]
+fn test_harvest_blocked_while_distributing() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    env.as_contract(&client.address, || {
+        storage::set_distributing(&env, true);
+    });
+    let recipients = sorted_recipients(&env, 2, 500_i128);
+    client.distribute_with_harvest(
+        &caller,
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &1_i128,
+        &asset_id,
+        &vault_id,
+        &recipients,
+    );
+}
+
+/// The escrow entry points release the lock on the way out, so setters work
+/// after a batch is escrowed and claimed.
+#[test]
+fn test_admin_setter_allowed_after_escrow_claim() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipient = Address::generate(&env);
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 1000_i128 }];
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
+    client.claim(&recipient, &batch_id);
+
+    client.set_min_amount(&client.get_admin(), &5_i128);
+    assert_eq!(client.get_min_amount(), 5_i128);
+}
+
+// ── Accounting tests ──────────────────────────────────────────────────────────
+
+/// `distribution_rate` returns the underlying deposited and df tokens minted
+/// for each recorded distribution, matching the mock vault's preset.
+#[test]
+fn test_distribution_rate_matches_preset() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1009_i128);
+    let batch = |amount: i128| vec![&env, Recipient { address: Address::generate(&env), amount }];
+
+    client.distribute(&caller, &asset_id, &vault_id, &batch(1000_i128));
+    vault.preset_df_mint(&10_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &batch(9_i128));
+
+    assert_eq!(client.distribution_count(), 2);
+    assert_eq!(client.distribution_rate(&0), (1000_i128, 1000_i128));
+    assert_eq!(client.distribution_rate(&1), (9_i128, 10_i128));
+}
+
+/// `audit_log` returns the requested window of distribution summaries,
+/// clamped to what has been recorded.
+#[test]
+fn test_audit_log_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1500_i128);
+
+    for i in 1..=5_i128 {
+        env.ledger().set_sequence_number(100 + i as u32);
+        client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 2, 50 * i));
+    }
+
+    let log = client.audit_log(&1, &3);
+    assert_eq!(log.len(), 3);
+    for (summary, id) in log.iter().zip(1..4_u64) {
+        assert_eq!(
+            summary,
+            DistributionSummary {
+                id,
+                caller: caller.clone(),
+                asset: asset_id.clone(),
+                vault: vault_id.clone(),
+                total: 100 * (id as i128 + 1),
+                df_minted: 100 * (id as i128 + 1),
+                ledger: 101 + id as u32,
+            }
+        );
+    }
+    assert_eq!(client.audit_log(&3, &10).len(), 2);
+    assert_eq!(client.audit_log(&5, &10).len(), 0);
+}
+
+/// Past [`AUDIT_LOG_CAPACITY`] distributions the oldest summaries are
+/// overwritten and drop out of the log.
+#[test]
+fn test_audit_log_overwrites_oldest() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    let runs = AUDIT_LOG_CAPACITY + 2;
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &(runs as i128));
+    for _ in 0..runs {
+        client.distribute(&caller, &asset_id, &vault_id, &sorted_recipients(&env, 1, 1_i128));
+    }
+
+    let log = client.audit_log(&0, &5);
+    assert_eq!(log.len(), 3);
+    assert_eq!(log.get(0).unwrap().id, 2);
+    assert_eq!(log.get(2).unwrap().id, 4);
+}
+
+/// `volume_by_asset` accumulates underlying per asset across distributions.
+#[test]
+fn test_volume_by_asset_tracks_each_asset() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_a, vault_a, client) = setup(&env);
+    let asset_b = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    let vault_b = env.register(mock_vault::MockVault, ());
+
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    StellarAssetClient::new(&env, &asset_a).mint(&caller, &1000_i128);
+    StellarAssetClient::new(&env, &asset_b).mint(&caller, &500_i128);
+
+    let batch = |amount: i128| vec![&env, Recipient { address: recipient.clone(), amount }];
+    client.distribute(&caller, &asset_a, &vault_a, &batch(600_i128));
+    client.distribute(&caller, &asset_a, &vault_a, &batch(400_i128));
+    client.distribute(&caller, &asset_b, &vault_b, &batch(500_i128));
+
+    let volumes = client.volume_by_asset();
+    assert_eq!(volumes.len(), 2);
+    assert_eq!(volumes.get(asset_a), Some(1000_i128));
+    assert_eq!(volumes.get(asset_b), Some(500_i128));
+}
+
+/// `df_distributed` accumulates the df tokens paid out across distributions
+/// to the same vault.
+#[test]
+fn test_df_distributed_accumulates_per_vault() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let first = client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+        Recipient { address: Address::generate(&env), amount: 200_i128 },
+    ]);
+    let second = client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 400_i128 },
+    ]);
+
+    let mut paid = 0_i128;
+    for (_, df) in first.iter().chain(second.iter()) {
+        paid += df;
+    }
+    assert_eq!(paid, 1000_i128);
+    assert_eq!(client.df_distributed(&vault_id), paid);
+    assert_eq!(client.df_distributed(&Address::generate(&env)), 0_i128);
+}
+
+/// A lifetime counter about to overflow makes the next distribution panic
+/// with a clear message instead of wrapping.
+#[test]
+#[should_panic(expected = "df distributed overflow")]
+fn test_df_distributed_overflow_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    env.as_contract(&client.address, || {
+        storage::add_df_distributed(&env, &vault_id, i128::MAX - 500);
+    });
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    client.distribute(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 1000_i128 },
+    ]);
+}
+
+// ── Budget tests ──────────────────────────────────────────────────────────────
+
+/// Sets up an owner with `funds` approved to the distributor and a budget of
+/// `budget` granted to a fresh spender.  Returns (owner, spender).
+fn setup_budget(
+    env: &Env,
+    asset_id: &Address,
+    client: &DistributorClient,
+    funds: i128,
+    budget: i128,
+) -> (Address, Address) {
+    let owner   = Address::generate(env);
+    let spender = Address::generate(env);
+    StellarAssetClient::new(env, asset_id).mint(&owner, &funds);
+    TokenClient::new(env, asset_id).approve(&owner, &client.address, &funds, &1000_u32);
+    client.set_budget(&owner, &spender, asset_id, &budget);
+    (owner, spender)
+}
+
+/// A spender distributes within budget from the owner's allowance; the
+/// budget is decremented by the total.
+#[test]
+fn test_distribute_from_budget_within_budget() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let (owner, spender) = setup_budget(&env, &asset_id, &client, 1000_i128, 500_i128);
+    let recipient = Address::generate(&env);
+
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 300_i128 }];
+    client.distribute_from_budget(&spender, &owner, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(MockVaultClient::new(&env, &vault_id).balance(&recipient), 300_i128);
+    assert_eq!(TokenClient::new(&env, &asset_id).balance(&owner), 700_i128);
+    assert_eq!(client.get_budget(&owner, &spender, &asset_id), 200_i128);
+}
+
+/// A single distribution larger than the remaining budget is rejected.
+#[test]
+#[should_panic(expected = "distribution exceeds budget")]
+fn test_distribute_from_budget_over_budget_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let (owner, spender) = setup_budget(&env, &asset_id, &client, 1000_i128, 500_i128);
+
+    let recipients = vec![&env, Recipient { address: Address::generate(&env), amount: 501_i128 }];
+    client.distribute_from_budget(&spender, &owner, &asset_id, &vault_id, &recipients);
+}
+
+/// Once the budget is spent exactly, any further distribution is rejected.
+#[test]
+#[should_panic(expected = "distribution exceeds budget")]
+fn test_distribute_from_budget_exhausted_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let (owner, spender) = setup_budget(&env, &asset_id, &client, 1000_i128, 500_i128);
+    let batch = |amount: i128| vec![&env, Recipient { address: Address::generate(&env), amount }];
+
+    client.distribute_from_budget(&spender, &owner, &asset_id, &vault_id, &batch(200_i128));
+    client.distribute_from_budget(&spender, &owner, &asset_id, &vault_id, &batch(300_i128));
+    assert_eq!(client.get_budget(&owner, &spender, &asset_id), 0_i128);
+
+    client.distribute_from_budget(&spender, &owner, &asset_id, &vault_id, &batch(1_i128));
+}
+
+// ── Commit-reveal tests ───────────────────────────────────────────────────────
+
+/// Recipients revealed with the committed hash are distributed normally.
+#[test]
+fn test_reveal_matching_commitment_distributes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 600_i128 },
+        Recipient { address: recipient2.clone(), amount: 400_i128 },
+    ];
+    let commitment = env.crypto().sha256(&recipients.clone().to_xdr(&env)).into();
+
+    client.commit_distribution(&caller, &commitment);
+    client.reveal_and_distribute(&caller, &asset_id, &vault_id, &recipients);
+
+    assert_eq!(vault.balance(&recipient1), 600_i128);
+    assert_eq!(vault.balance(&recipient2), 400_i128);
+}
+
+/// Revealing a set that differs from the commitment (here, one amount) panics.
+#[test]
+#[should_panic(expected = "revealed recipients do not match commitment")]
+fn test_reveal_mismatched_commitment_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let committed = vec![&env, Recipient { address: recipient.clone(), amount: 500_i128 }];
+    let commitment = env.crypto().sha256(&committed.to_xdr(&env)).into();
+    client.commit_distribution(&caller, &commitment);
+
+    let revealed = vec![&env, Recipient { address: recipient.clone(), amount: 1000_i128 }];
+    client.reveal_and_distribute(&caller, &asset_id, &vault_id, &revealed);
+}
+
+// ── Chunking tests ────────────────────────────────────────────────────────────
+
+/// Builds `n` recipients of 10 underlying each.
+fn ten_each(env: &Env, n: u32) -> Vec<Recipient> {
+    let mut recipients: Vec<Recipient> = vec![env];
+    for _ in 0..n {
+        recipients.push_back(Recipient { address: Address::generate(env), amount: 10_i128 });
+    }
+    recipients
+}
+
+/// Asserts the last invocation stayed within the network's per-transaction
+/// CPU and memory limits.  The test host's own bookkeeping for large
+/// argument lists is metered separately, so the budget is lifted while
+/// calling and checked here instead.
+fn assert_within_tx_budget(env: &Env) {
+    assert!(env.cost_estimate().budget().cpu_instruction_cost() < 100_000_000);
+    assert!(env.cost_estimate().budget().memory_bytes_cost() < 41_943_040);
+}
+
+/// 150 recipients: `distribute_auto` deposits once and hands back four
+/// chunks of at most 40, each paid by its own `distribute_chunk` call within
+/// the per-transaction limits.
+#[test]
+fn test_distribute_auto_deposit_then_chunks() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1500_i128);
+    let recipients = ten_each(&env, 150);
+
+    env.cost_estimate().budget().reset_unlimited();
+    let auto = client.distribute_auto(&caller, &asset_id, &vault_id, &recipients);
+    assert_within_tx_budget(&env);
+    assert!(auto.results.is_empty());
+    assert_eq!(auto.pending_chunks, vec![&env, 0_u32, 1_u32, 2_u32, 3_u32]);
+    let batch_id = auto.batch_id.unwrap();
+    // The chunk key is the id the `DistributionBatch` event carries
+    assert_eq!(
+        batch_id,
+        distribution_batch_id(&env, &caller, &asset_id, &vault_id, &recipients, 0)
+    );
+
+    // Everything stays with the distributor until the chunks are paid
+    assert_eq!(vault.balance(&client.address), 1500_i128);
+    assert_eq!(vault.balance(&recipients.get(0).unwrap().address), 0_i128);
+
+    // Chunks can be paid in any order
+    let mut paid = 0;
+    for chunk in [3_u32, 0, 2, 1] {
+        env.cost_estimate().budget().reset_unlimited();
+        let results = client.distribute_chunk(&caller, &batch_id, &chunk);
+        assert_within_tx_budget(&env);
+        assert_eq!(results.len(), if chunk == 3 { 30 } else { MAX_RECIPIENTS_PER_TX });
+        paid += results.len();
+    }
+    assert_eq!(paid, 150);
+
+    for r in recipients.iter() {
+        assert_eq!(vault.balance(&r.address), 10_i128);
+    }
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// At exactly [`MAX_RECIPIENTS_PER_TX`] recipients the remainder still lands
+/// on the last one: 40 equal amounts sharing 79 df tokens give 1 each to the
+/// first 39 and 40 to index 39.
+#[test]
+fn test_remainder_at_max_recipients_boundary() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    MockVaultClient::new(&env, &vault_id).preset_df_mint(&79_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &400_i128);
+    let recipients = ten_each(&env, MAX_RECIPIENTS_PER_TX);
+
+    let auto = client.distribute_auto(&caller, &asset_id, &vault_id, &recipients);
+    assert_eq!(auto.batch_id, None);
+    assert_eq!(auto.results.len(), MAX_RECIPIENTS_PER_TX);
+    for (i, (_, df)) in (0..MAX_RECIPIENTS_PER_TX).zip(auto.results.iter()) {
+        assert_eq!(df, if i == MAX_RECIPIENTS_PER_TX - 1 { 40_i128 } else { 1_i128 });
+    }
+}
+
+/// One recipient past the cap: the remainder absorber is alone in chunk 1.
+#[test]
+fn test_remainder_one_past_max_recipients_in_last_chunk() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    MockVaultClient::new(&env, &vault_id).preset_df_mint(&81_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &410_i128);
+    let recipients = ten_each(&env, MAX_RECIPIENTS_PER_TX + 1);
+
+    let auto = client.distribute_auto(&caller, &asset_id, &vault_id, &recipients);
+    assert_eq!(auto.pending_chunks, vec![&env, 0_u32, 1_u32]);
+    let batch_id = auto.batch_id.unwrap();
+
+    let last = client.distribute_chunk(&caller, &batch_id, &1_u32);
+    assert_eq!(last, vec![&env, (recipients.get(MAX_RECIPIENTS_PER_TX).unwrap().address, 41_i128)]);
+    for (_, df) in client.distribute_chunk(&caller, &batch_id, &0_u32).iter() {
+        assert_eq!(df, 1_i128);
+    }
+}
+
+/// Lists within the per-transaction limit are paid immediately.
+#[test]
+fn test_distribute_auto_small_list_pays_directly() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &100_i128);
+
+    let auto = client.distribute_auto(&caller, &asset_id, &vault_id, &ten_each(&env, 10));
+    assert_eq!(auto.results.len(), 10);
+    assert_eq!(auto.batch_id, None);
+    assert!(auto.pending_chunks.is_empty());
+}
+
+/// The shares a chunked distribution reserves count against the escrow cap.
+#[test]
+#[should_panic(expected = "escrow cap exceeded")]
+fn test_distribute_auto_chunked_respects_escrow_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    client.set_max_escrow(&client.get_admin(), &asset_id, &400_i128);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &410_i128);
+    client.distribute_auto(&caller, &asset_id, &vault_id, &ten_each(&env, MAX_RECIPIENTS_PER_TX + 1));
+}
+
+/// Once its last chunk is paid, a chunked distribution is the caller's last
+/// split, so `repeat_last` reuses it like a single-call `distribute`.
+#[test]
+fn test_distribute_auto_chunked_records_last_split() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &(410_i128 + 820_i128));
+    let recipients = ten_each(&env, MAX_RECIPIENTS_PER_TX + 1);
+
+    let batch_id = client.distribute_auto(&caller, &asset_id, &vault_id, &recipients).batch_id.unwrap();
+    client.distribute_chunk(&caller, &batch_id, &0_u32);
+    let unfinished = env.as_contract(&client.address, || storage::get_last_split(&env, &caller, &vault_id));
+    assert!(unfinished.is_none());
+    client.distribute_chunk(&caller, &batch_id, &1_u32);
+
+    let results = client.repeat_last(&caller, &asset_id, &vault_id, &820_i128);
+    assert_eq!(results.len(), MAX_RECIPIENTS_PER_TX + 1);
+    for (r, (address, df)) in recipients.iter().zip(results.iter()) {
+        assert_eq!((address, df), (r.address, 20_i128));
+    }
+}
+
+/// A chunk is paid at most once.
+#[test]
+#[should_panic(expected = "chunk is not pending")]
+fn test_distribute_chunk_twice_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1500_i128);
+
+    let auto = client.distribute_auto(&caller, &asset_id, &vault_id, &ten_each(&env, 150));
+    let batch_id = auto.batch_id.unwrap();
+
+    client.distribute_chunk(&caller, &batch_id, &1_u32);
+    client.distribute_chunk(&caller, &batch_id, &1_u32);
+}
+
+/// Recipients `from..to` of `recipients`.
+fn slice(env: &Env, recipients: &Vec<Recipient>, from: u32, to: u32) -> Vec<Recipient> {
+    let mut out: Vec<Recipient> = vec![env];
+    for i in from..to {
+        out.push_back(recipients.get(i).unwrap());
+    }
+    out
+}
+
+/// A chunk paid, then `resume_distribution` continuing sequentially from the
+/// recorded progress (including mid-chunk) to completion: every recipient
+/// is paid exactly once and the shares add up to the mint.
+#[test]
+fn test_resume_distribution_to_completion() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipients = ten_each(&env, 100);
+    let batch_id = client
+        .distribute_auto(&caller, &asset_id, &vault_id, &recipients)
+        .batch_id
+        .unwrap();
+
+    client.distribute_chunk(&caller, &batch_id, &0_u32);
+    assert_eq!(client.chunk_progress(&batch_id), (40_u32, 400_i128));
+
+    client.resume_distribution(&caller, &batch_id, &slice(&env, &recipients, 40, 65));
+    assert_eq!(client.chunk_progress(&batch_id), (65_u32, 650_i128));
+
+    // Chunk 1 is only part-paid, so it is still pending for its remainder
+    let rest = client.distribute_chunk(&caller, &batch_id, &1_u32);
+    assert_eq!(rest.len(), 15);
+    assert_eq!(client.chunk_progress(&batch_id), (80_u32, 800_i128));
+
+    client.resume_distribution(&caller, &batch_id, &slice(&env, &recipients, 80, 100));
+
+    let mut total = 0_i128;
+    for r in recipients.iter() {
+        assert_eq!(vault.balance(&r.address), 10_i128);
+        total += vault.balance(&r.address);
+    }
+    assert_eq!(total, 1000_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// A slice that does not start at `next_index` is rejected.
+#[test]
+#[should_panic(expected = "slice does not continue from next_index")]
+fn test_resume_distribution_discontinuous_slice_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    let recipients = ten_each(&env, 100);
+    let batch_id = client
+        .distribute_auto(&caller, &asset_id, &vault_id, &recipients)
+        .batch_id
+        .unwrap();
+
+    client.resume_distribution(&caller, &batch_id, &slice(&env, &recipients, 1, 10));
+}
+
+// ── Escrow tests ──────────────────────────────────────────────────────────────
+
+/// An unclaimed escrow allocation reassigned by the admin is claimable by the
+/// new recipient and no longer by the original one.
+#[test]
+fn test_reassign_unclaimed_allocation_then_claim() {
     let env = Env::default();
     env.mock_all_auths();
 
     let (asset_id, vault_id, client) = setup(&env);
     let vault = MockVaultClient::new(&env, &vault_id);
+    let admin = client.get_admin();
+
+    let caller      = Address::generate(&env);
+    let recipient1  = Address::generate(&env);
+    let recipient2  = Address::generate(&env);
+    let replacement = Address::generate(&env);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 600_i128 },
+        Recipient { address: recipient2.clone(), amount: 400_i128 },
+    ];
+
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
+
+    // Shares are held by the distributor until claimed
+    assert_eq!(vault.balance(&client.address), 1000_i128);
+    assert_eq!(vault.balance(&recipient1), 0_i128);
+
+    client.reassign(&admin, &vault_id, &recipient1, &replacement);
+
+    let ev = Reassigned {
+        vault: vault_id.clone(), from: recipient1.clone(), to: replacement.clone(), df_tokens: 600_i128,
+    };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![&env, (client.address.clone(), ev.topics(&env), ev.data(&env))]
+    );
+
+    let receipt = client.receipt(&replacement, &batch_id).unwrap();
+    assert_eq!((receipt.vault, receipt.shares), (vault_id.clone(), 600_i128));
+    assert_eq!(client.receipt(&recipient1, &batch_id).unwrap().shares, 600_i128);
+
+    assert_eq!(client.claim(&replacement, &batch_id), 600_i128);
+    assert_eq!(client.claim(&recipient2, &batch_id), 400_i128);
+
+    assert_eq!(vault.balance(&replacement), 600_i128);
+    assert_eq!(vault.balance(&recipient2), 400_i128);
+    assert_eq!(vault.balance(&recipient1), 0_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+}
+
+/// An allocation cannot be reassigned to the distributor, where it would be
+/// stranded.
+#[test]
+#[should_panic(expected = "reassign target address is invalid")]
+fn test_reassign_to_self_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ]);
+
+    client.reassign(&client.get_admin(), &vault_id, &recipient, &client.address);
+}
+
+/// Migrating to a burn address is rejected like a reassignment.
+#[test]
+#[should_panic(expected = "reassign target address is invalid")]
+fn test_migrate_recipient_to_zero_address_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ]);
+
+    let zero = Address::from_str(&env, ZERO_ACCOUNT);
+    client.migrate_recipient(&client.get_admin(), &vault_id, &recipient, &zero);
+}
+
+/// Each escrow recipient gets a receipt of their allocation that survives
+/// the claim; non-recipients and unknown batches have none.
+#[test]
+fn test_escrow_receipts_per_recipient() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_sequence_number(500);
 
+    let (asset_id, vault_id, client) = setup(&env);
     let caller     = Address::generate(&env);
     let recipient1 = Address::generate(&env);
     let recipient2 = Address::generate(&env);
-    let recipient3 = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
 
-    // Vault issues 10 df tokens for 9 units in (non-1:1)
-    vault.preset_df_mint(&10_i128);
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 250_i128 },
+        Recipient { address: recipient2.clone(), amount: 750_i128 },
+    ]);
+
+    let receipt = |shares: i128| DistributionReceipt {
+        batch_id: batch_id.clone(),
+        vault: vault_id.clone(),
+        shares,
+        ledger: 500,
+    };
+    assert_eq!(client.receipt(&recipient1, &batch_id), Some(receipt(250_i128)));
+    assert_eq!(client.receipt(&recipient2, &batch_id), Some(receipt(750_i128)));
+    assert_eq!(client.receipt(&caller, &batch_id), None);
+    assert_eq!(client.receipt(&recipient1, &BytesN::from_array(&env, &[0; 32])), None);
 
-    // total=9, df_minted=10
-    // user1: floor(3 * 10 / 9) = floor(3.33) = 3
-    // user2: floor(3 * 10 / 9) = floor(3.33) = 3
-    // user3 (last): 10 - 3 - 3 = 4  (gets remainder, not floor(3.33)=3)
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+    client.claim(&recipient1, &batch_id);
+    assert_eq!(client.receipt(&recipient1, &batch_id), Some(receipt(250_i128)));
+}
+
+/// `migrate_recipient` moves an unclaimed allocation to the recipient's new
+/// wallet with both the admin's and the old address's authorisation.
+#[test]
+fn test_migrate_recipient_moves_escrow_allocation() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    let admin = client.get_admin();
+
+    let caller = Address::generate(&env);
+    let old    = Address::generate(&env);
+    let new    = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
+
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: old.clone(), amount: 500_i128 },
+    ]);
+    client.migrate_recipient(&admin, &vault_id, &old, &new);
+
+    assert_eq!(
+        env.auths().into_iter().map(|(a, _)| a).collect::<std::vec::Vec<_>>(),
+        std::vec![admin.clone(), old.clone()]
+    );
+    assert_eq!(
+        client.escrow_balances(&vault_id, &vec![&env, old.clone(), new.clone()]),
+        vec![&env, 0_i128, 500_i128]
+    );
+    assert_eq!(client.claim(&new, &batch_id), 500_i128);
+    assert_eq!(vault.balance(&new), 500_i128);
+}
+
+/// `claim_all` releases a user's allocations from three batches in one call
+/// and leaves nothing behind.
+#[test]
+fn test_claim_all_across_batches() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller = Address::generate(&env);
+    let user   = Address::generate(&env);
+    let other  = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    for amount in [100_i128, 200, 300] {
+        client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+            &env,
+            Recipient { address: user.clone(), amount },
+            Recipient { address: other.clone(), amount: 100_i128 },
+        ]);
+    }
+
+    assert_eq!(client.claim_all(&user, &vault_id), 600_i128);
+    assert_eq!(vault.balance(&user), 600_i128);
+    assert_eq!(client.escrow_balances(&vault_id, &vec![&env, user.clone(), other.clone()]),
+        vec![&env, 0_i128, 300_i128]);
+    assert!(client.try_claim_all(&user, &vault_id).is_err());
+}
+
+/// After reassignment the original recipient has nothing left to claim.
+#[test]
+#[should_panic(expected = "nothing to claim")]
+fn test_claim_after_reassign_by_original_recipient_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+
+    let caller      = Address::generate(&env);
+    let recipient   = Address::generate(&env);
+    let replacement = Address::generate(&env);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
+
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 500_i128 }];
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
+
+    client.reassign(&admin, &vault_id, &recipient, &replacement);
+    client.claim(&recipient, &batch_id);
+}
+
+/// Canceling an untouched batch returns every escrowed df token to the caller
+/// and leaves nothing for recipients to claim.
+#[test]
+fn test_cancel_untouched_escrow_batch() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
 
     let recipients: Vec<Recipient> = vec![
         &env,
-        Recipient { address: recipient1.clone(), amount: 3_i128 },
-        Recipient { address: recipient2.clone(), amount: 3_i128 },
-        Recipient { address: recipient3.clone(), amount: 3_i128 },
+        Recipient { address: recipient1.clone(), amount: 700_i128 },
+        Recipient { address: recipient2.clone(), amount: 300_i128 },
     ];
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
 
-    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+    assert_eq!(client.cancel_distribution(&caller, &batch_id), 1000_i128);
 
-    assert_eq!(results.get(0).unwrap(), (recipient1.clone(), 3_i128));
-    assert_eq!(results.get(1).unwrap(), (recipient2.clone(), 3_i128));
-    assert_eq!(results.get(2).unwrap(), (recipient3.clone(), 4_i128));
-    assert_eq!(vault.balance(&recipient3), 4_i128);
+    let ev = Canceled {
+        batch_id: batch_id.clone(), vault: vault_id.clone(), caller: caller.clone(), df_tokens: 1000_i128,
+    };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        vec![&env, (client.address.clone(), ev.topics(&env), ev.data(&env))]
+    );
+
+    assert_eq!(vault.balance(&caller), 1000_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
+    assert_eq!(
+        client.escrow_balances(&vault_id, &vec![&env, recipient1.clone(), recipient2.clone()]),
+        vec![&env, 0_i128, 0_i128]
+    );
+}
+
+/// Once any recipient has claimed, the batch can no longer be canceled.
+#[test]
+#[should_panic(expected = "escrow batch has claims")]
+fn test_cancel_after_claim_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller     = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let recipients: Vec<Recipient> = vec![
+        &env,
+        Recipient { address: recipient1.clone(), amount: 700_i128 },
+        Recipient { address: recipient2.clone(), amount: 300_i128 },
+    ];
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
+
+    client.claim(&recipient1, &batch_id);
+    client.cancel_distribution(&caller, &batch_id);
+}
+
+/// Escrow up to the per-asset cap is accepted; the distribution that would
+/// push live escrow over the cap is rejected.
+#[test]
+#[should_panic(expected = "escrow cap exceeded")]
+fn test_escrow_over_cap_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let admin = client.get_admin();
+
+    client.set_max_escrow(&admin, &asset_id, &1000_i128);
+    assert_eq!(client.get_max_escrow(&asset_id), Some(1000_i128));
+
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1200_i128);
+
+    let batch = |amount: i128| vec![&env, Recipient { address: Address::generate(&env), amount }];
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &batch(600_i128));
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &batch(350_i128));
+    // 950 held; 200 more would reach 1150
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &batch(200_i128));
+}
+
+/// `caller_batches` lists outstanding batches and drops one once every
+/// recipient in it has claimed.
+#[test]
+fn test_caller_batches_shrinks_when_fully_claimed() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller = Address::generate(&env);
+    let user_a = Address::generate(&env);
+    let user_b = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let batch1 = client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user_a.clone(), amount: 300_i128 },
+        Recipient { address: user_b.clone(), amount: 200_i128 },
+    ]);
+    let batch2 = client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user_a.clone(), amount: 500_i128 },
+    ]);
+    assert_eq!(client.caller_batches(&caller), vec![&env, batch1.clone(), batch2.clone()]);
+
+    // Partially claimed batches stay listed
+    client.claim(&user_a, &batch1);
+    assert_eq!(client.caller_batches(&caller), vec![&env, batch1.clone(), batch2.clone()]);
+
+    client.claim(&user_b, &batch1);
+    assert_eq!(client.caller_batches(&caller), vec![&env, batch2.clone()]);
+}
+
+/// `escrow_balances` sums each user's allocations across batches and returns
+/// them in query order, with zero for addresses that hold nothing.
+#[test]
+fn test_escrow_balances_batch_query() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+
+    let caller   = Address::generate(&env);
+    let user_a   = Address::generate(&env);
+    let user_b   = Address::generate(&env);
+    let user_c   = Address::generate(&env);
+    let stranger = Address::generate(&env);
+
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user_a.clone(), amount: 100_i128 },
+        Recipient { address: user_b.clone(), amount: 200_i128 },
+    ]);
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: user_a.clone(), amount: 300_i128 },
+        Recipient { address: user_c.clone(), amount: 400_i128 },
+    ]);
+
+    let balances = client.escrow_balances(
+        &vault_id,
+        &vec![&env, user_c.clone(), stranger.clone(), user_a.clone(), user_b.clone()],
+    );
+    assert_eq!(balances, vec![&env, 400_i128, 0_i128, 400_i128, 200_i128]);
+}
+
+/// Queries above `MAX_QUERY_LEN` addresses are rejected.
+#[test]
+#[should_panic(expected = "too many users in query")]
+fn test_escrow_balances_rejects_oversized_query() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (_asset_id, vault_id, client) = setup(&env);
+
+    let mut users: Vec<Address> = vec![&env];
+    for _ in 0..=MAX_QUERY_LEN {
+        users.push_back(Address::generate(&env));
+    }
+    client.escrow_balances(&vault_id, &users);
+}
+
+/// An accepted offer releases the recipient's shares; until then they stay
+/// with the distributor.
+#[test]
+fn test_offer_then_accept_releases_allocation() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_escrow_expiry(&client.get_admin(), &100_u32);
+
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    let batch_id = client.offer_distribution(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ]);
+    assert_eq!(vault.balance(&recipient), 0_i128);
+    assert_eq!(vault.balance(&client.address), 1000_i128);
+
+    assert_eq!(client.accept_offer(&recipient, &batch_id), 1000_i128);
+    assert_eq!(vault.balance(&recipient), 1000_i128);
+    assert_eq!(vault.balance(&client.address), 0_i128);
 }
 
-/// Single recipient always hits the "last" path and receives all df tokens.
+/// Offers nobody accepts go back to the caller once the deadline passes.
 #[test]
-fn test_single_recipient_gets_all_df_tokens() {
+fn test_offer_without_acceptance_reclaimed_after_deadline() {
     let env = Env::default();
     env.mock_all_auths();
 
     let (asset_id, vault_id, client) = setup(&env);
     let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_escrow_expiry(&client.get_admin(), &100_u32);
 
     let caller    = Address::generate(&env);
     let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
 
-    // Vault issues 999 df tokens for 500 units in
-    vault.preset_df_mint(&999_i128);
-
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &500_i128);
-
-    let recipients: Vec<Recipient> = vec![
+    let batch_id = client.offer_distribution(&caller, &asset_id, &vault_id, &vec![
         &env,
-        Recipient { address: recipient.clone(), amount: 500_i128 },
-    ];
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ]);
 
-    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
+    env.ledger().with_mut(|l| l.sequence_number += 101);
 
-    assert_eq!(results.get(0).unwrap(), (recipient.clone(), 999_i128));
-    assert_eq!(vault.balance(&recipient), 999_i128);
-    assert_eq!(vault.balance(&caller), 0_i128);
+    assert_eq!(client.reclaim(&caller, &batch_id, &false, &0_i128), 1000_i128);
+    assert_eq!(vault.balance(&caller), 1000_i128);
+    assert_eq!(vault.balance(&recipient), 0_i128);
+    assert_eq!(client.escrow_balances(&vault_id, &vec![&env, recipient]), vec![&env, 0_i128]);
 }
 
-/// Sum of all distributed df tokens must equal df_tokens_minted — no dust lost.
+/// An offer is only released by `accept_offer`: `claim` refuses it.
 #[test]
-fn test_no_df_tokens_lost_to_rounding() {
+#[should_panic(expected = "offers must be accepted")]
+fn test_claim_rejects_offer() {
     let env = Env::default();
     env.mock_all_auths();
 
     let (asset_id, vault_id, client) = setup(&env);
+    client.set_escrow_expiry(&client.get_admin(), &100_u32);
 
-    let caller = Address::generate(&env);
-
-    let vault = MockVaultClient::new(&env, &vault_id);
-    // 7 inputs → 13 df tokens: guarantees non-trivial rounding
-    vault.preset_df_mint(&13_i128);
-
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &7_i128);
-
-    let users: [Address; 5] = core::array::from_fn(|_| Address::generate(&env));
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
 
-    let recipients: Vec<Recipient> = vec![
+    let batch_id = client.offer_distribution(&caller, &asset_id, &vault_id, &vec![
         &env,
-        Recipient { address: users[0].clone(), amount: 1_i128 },
-        Recipient { address: users[1].clone(), amount: 1_i128 },
-        Recipient { address: users[2].clone(), amount: 2_i128 },
-        Recipient { address: users[3].clone(), amount: 1_i128 },
-        Recipient { address: users[4].clone(), amount: 2_i128 },
-    ];
-
-    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
-
-    let total_distributed: i128 = (0..5_u32).map(|i| results.get(i).unwrap().1).sum();
-    assert_eq!(total_distributed, 13_i128);
-    assert_eq!(vault.balance(&caller), 0_i128);
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ]);
+    client.claim(&recipient, &batch_id);
 }
 
-// ── Event tests ───────────────────────────────────────────────────────────────
-
-/// One `Distributed` event is emitted per recipient.
-/// Verifies the topic name, contract address, and all data fields
-/// (asset, vault, user, underlying_amount, df_tokens) at a 1:1 mock rate.
+/// `claim_all` releases ordinary escrow batches and leaves offers pending.
 #[test]
-fn test_events_emitted_per_recipient() {
+fn test_claim_all_skips_offers() {
     let env = Env::default();
     env.mock_all_auths();
 
     let (asset_id, vault_id, client) = setup(&env);
+    let vault = MockVaultClient::new(&env, &vault_id);
+    client.set_escrow_expiry(&client.get_admin(), &100_u32);
 
-    let caller     = Address::generate(&env);
-    let recipient1 = Address::generate(&env);
-    let recipient2 = Address::generate(&env);
-
-    let amount1 = 600_i128;
-    let amount2 = 400_i128;
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &(amount1 + amount2));
-
-    let recipients: Vec<Recipient> = vec![
-        &env,
-        Recipient { address: recipient1.clone(), amount: amount1 },
-        Recipient { address: recipient2.clone(), amount: amount2 },
-    ];
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
 
-    let results = client.distribute(&caller, &asset_id, &vault_id, &recipients);
-    let df1 = results.get(0).unwrap().1;
-    let df2 = results.get(1).unwrap().1;
+    let recipients = vec![&env, Recipient { address: recipient.clone(), amount: 500_i128 }];
+    let offer_id = client.offer_distribution(&caller, &asset_id, &vault_id, &recipients);
+    client.distribute_escrow(&caller, &asset_id, &vault_id, &recipients);
 
-    let ev0 = Distributed {
-        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient1.clone(),
-        underlying_amount: amount1, df_tokens: df1,
-    };
-    let ev1 = Distributed {
-        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient2.clone(),
-        underlying_amount: amount2, df_tokens: df2,
-    };
+    assert_eq!(client.claim_all(&recipient, &vault_id), 500_i128);
+    assert_eq!(vault.balance(&recipient), 500_i128);
+    assert_eq!(client.escrow_balances(&vault_id, &vec![&env, recipient.clone()]), vec![&env, 500_i128]);
 
-    assert_eq!(
-        env.events().all().filter_by_contract(&client.address),
-        vec![
-            &env,
-            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
-            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
-        ]
-    );
+    assert_eq!(client.accept_offer(&recipient, &offer_id), 500_i128);
+    assert_eq!(vault.balance(&recipient), 1000_i128);
 }
 
-/// With a non-1:1 exchange rate and rounding, the event captures the original
-/// underlying input amount and the actual (floored or remainder) df_tokens.
+/// `accept_offer` only takes offers, not ordinary escrow batches.
 #[test]
-fn test_events_non_1to1_exchange_rate() {
+#[should_panic(expected = "batch is not an offer")]
+fn test_accept_offer_rejects_escrow_batch() {
     let env = Env::default();
     env.mock_all_auths();
 
     let (asset_id, vault_id, client) = setup(&env);
-    let vault = MockVaultClient::new(&env, &vault_id);
-
-    // Vault mints 10 df tokens for 9 units in → non-trivial rounding
-    vault.preset_df_mint(&10_i128);
-
-    let caller     = Address::generate(&env);
-    let recipient1 = Address::generate(&env);
-    let recipient2 = Address::generate(&env);
-    let recipient3 = Address::generate(&env);
 
-    // total=9, df_minted=10
-    // user1: floor(3*10/9) = 3, user2: floor(3*10/9) = 3, user3 (last): 10-3-3 = 4
-    StellarAssetClient::new(&env, &asset_id).mint(&caller, &9_i128);
+    let caller    = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
 
-    let recipients: Vec<Recipient> = vec![
+    let batch_id = client.distribute_escrow(&caller, &asset_id, &vault_id, &vec![
         &env,
-        Recipient { address: recipient1.clone(), amount: 3_i128 },
-        Recipient { address: recipient2.clone(), amount: 3_i128 },
-        Recipient { address: recipient3.clone(), amount: 3_i128 },
-    ];
+        Recipient { address: recipient.clone(), amount: 1000_i128 },
+    ]);
+    client.accept_offer(&recipient, &batch_id);
+}
 
-    client.distribute(&caller, &asset_id, &vault_id, &recipients);
+/// Without an escrow expiry an offer could never be reclaimed.
+#[test]
+#[should_panic(expected = "offers require an escrow expiry")]
+fn test_offer_without_escrow_expiry_panics() {
+    let env = Env::default();
+    env.mock_all_auths();
 
-    let ev0 = Distributed {
-        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient1.clone(),
-        underlying_amount: 3_i128, df_tokens: 3_i128, // floor(3*10/9)
-    };
-    let ev1 = Distributed {
-        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient2.clone(),
-        underlying_amount: 3_i128, df_tokens: 3_i128, // floor(3*10/9)
-    };
-    let ev2 = Distributed {
-        asset: asset_id.clone(), vault: vault_id.clone(), user: recipient3.clone(),
-        underlying_amount: 3_i128, df_tokens: 4_i128, // remainder: 10 - 3 - 3
-    };
+    let (asset_id, vault_id, client) = setup(&env);
 
-    assert_eq!(
-        env.events().all().filter_by_contract(&client.address),
-        vec![
-            &env,
-            (client.address.clone(), ev0.topics(&env), ev0.data(&env)),
-            (client.address.clone(), ev1.topics(&env), ev1.data(&env)),
-            (client.address.clone(), ev2.topics(&env), ev2.data(&env)),
-        ]
-    );
+    let caller = Address::generate(&env);
+    StellarAssetClient::new(&env, &asset_id).mint(&caller, &1000_i128);
+
+    client.offer_distribution(&caller, &asset_id, &vault_id, &vec![
+        &env,
+        Recipient { address: Address::generate(&env), amount: 1000_i128 },
+    ]);
 }
 
 // ── Auth tests ────────────────────────────────────────────────────────────────
@@ -783,7 +6945,7 @@ mod auth {
         let admin = Address::generate(e);
         let asset_id = e.register_stellar_asset_contract_v2(admin.clone()).address();
         let vault_id = e.register(mock_vault::MockVault, ());
-        let distributor_id = e.register(Distributor, ());
+        let distributor_id = e.register(Distributor, (admin.clone(),));
         (asset_id, admin, vault_id, DistributorClient::new(e, &distributor_id))
     }
 
@@ -853,6 +7015,97 @@ mod auth {
         assert_eq!(vault.balance(&caller), 0_i128);
     }
 
+    /// A staking contract that, when asked to credit a user, tries to pull a
+    /// second copy of the same amount out of the distributor by replaying
+    /// the share transfer the distributor just authorised for it.
+    mod mock_replay_stake {
+        use soroban_sdk::{contract, contractimpl, symbol_short, token::TokenClient, Address, Env};
+
+        #[contract]
+        pub struct MockReplayStake;
+
+        #[contractimpl]
+        impl MockReplayStake {
+            pub fn init(e: Env, share: Address, distributor: Address) {
+                e.storage().instance().set(&symbol_short!("share"), &share);
+                e.storage().instance().set(&symbol_short!("dist"), &distributor);
+            }
+
+            pub fn stake(e: Env, _user: Address, amount: i128) {
+                let share: Address = e.storage().instance().get(&symbol_short!("share")).unwrap();
+                let dist: Address = e.storage().instance().get(&symbol_short!("dist")).unwrap();
+                let me = e.current_contract_address();
+                if TokenClient::new(&e, &share).try_transfer(&dist, &me, &amount).is_ok() {
+                    let stolen = Self::stolen(e.clone()) + amount;
+                    e.storage().instance().set(&symbol_short!("stolen"), &stolen);
+                }
+            }
+
+            pub fn stolen(e: Env) -> i128 {
+                e.storage().instance().get(&symbol_short!("stolen")).unwrap_or(0)
+            }
+        }
+    }
+
+    /// Authorisations the distributor grants for one share transfer do not
+    /// carry over to other calls: a staking contract replaying the exact
+    /// transfer it was just paid by gets nothing, and every recipient's
+    /// allocation arrives intact.
+    #[test]
+    fn test_share_transfer_auth_does_not_bleed_to_sub_calls() {
+        let env = Env::default();
+        let (asset_id, admin, _, client) = setup_auth(&env);
+        let distributor_id = client.address.clone();
+
+        let vault_id = env.register(mock_split_vault::MockSplitVault, ());
+        let share_id = env.register_stellar_asset_contract_v2(vault_id.clone()).address();
+        mock_split_vault::MockSplitVaultClient::new(&env, &vault_id).set_share_token(&share_id);
+        let share = TokenClient::new(&env, &share_id);
+
+        let stake_id = env.register(mock_replay_stake::MockReplayStake, ());
+        let stake = mock_replay_stake::MockReplayStakeClient::new(&env, &stake_id);
+        stake.init(&share_id, &distributor_id);
+
+        let caller = Address::generate(&env);
+        let total: i128 = 1000;
+        mint(&env, &asset_id, &admin, &caller, total);
+
+        let recipients = vec![
+            &env,
+            Recipient { address: Address::generate(&env), amount: 600_i128 },
+            Recipient { address: Address::generate(&env), amount: 400_i128 },
+        ];
+        let options = DistributeOptions {
+            share_token: Some(share_id.clone()),
+            stake_contract: Some(stake_id.clone()),
+            ..Default::default()
+        };
+
+        env.mock_auths(&[MockAuth {
+            address: &caller,
+            invoke: &MockAuthInvoke {
+                contract: &distributor_id,
+                fn_name: "distribute_with_options",
+                args: (
+                    caller.clone(), asset_id.clone(), vault_id.clone(), recipients.clone(),
+                    options.clone(),
+                )
+                    .into_val(&env),
+                sub_invokes: &[MockAuthInvoke {
+                    contract: &asset_id,
+                    fn_name: "transfer",
+                    args: (caller.clone(), distributor_id.clone(), total).into_val(&env),
+                    sub_invokes: &[],
+                }],
+            },
+        }]);
+        client.distribute_with_options(&caller, &asset_id, &vault_id, &recipients, &options);
+
+        assert_eq!(stake.stolen(), 0);
+        assert_eq!(share.balance(&stake_id), total);
+        assert_eq!(share.balance(&distributor_id), 0);
+    }
+
     /// No auth at all — panics immediately at `caller.require_auth()`.
     #[test]
     #[should_panic]
@@ -934,4 +7187,149 @@ mod auth {
         // Panics: real_caller.require_auth() has no matching entry.
         client.distribute(&real_caller, &asset_id, &vault_id, &recipients);
     }
+
+    /// A contract caller funds a distribution from its own balance by
+    /// pre-authorising the nested `asset.transfer` via
+    /// `authorize_as_current_contract`; no account signature is involved.
+    #[test]
+    fn test_contract_caller_authorizes_sub_invocation() {
+        let env = Env::default();
+        let (asset_id, admin, vault_id, client) = setup_auth(&env);
+        let vault = MockVaultClient::new(&env, &vault_id);
+
+        let wrapper_id = env.register(mock_caller::MockCaller, ());
+        let wrapper = mock_caller::MockCallerClient::new(&env, &wrapper_id);
+
+        let r1 = Address::generate(&env);
+        let r2 = Address::generate(&env);
+        mint(&env, &asset_id, &admin, &wrapper_id, 1000_i128);
+
+        let recipients = vec![
+            &env,
+            Recipient { address: r1.clone(), amount: 600_i128 },
+            Recipient { address: r2.clone(), amount: 400_i128 },
+        ];
+
+        let results = wrapper.distribute_via(&client.address, &asset_id, &vault_id, &recipients);
+
+        assert_eq!(results, vec![&env, (r1.clone(), 600_i128), (r2.clone(), 400_i128)]);
+        assert_eq!(vault.balance(&r1), 600_i128);
+        assert_eq!(vault.balance(&r2), 400_i128);
+        assert_eq!(TokenClient::new(&env, &asset_id).balance(&wrapper_id), 0_i128);
+    }
+
+    /// `distribute_direct` with real auth: `caller` signs the call and the
+    /// transfer to each recipient, and each recipient signs their own vault
+    /// deposit.
+    ///
+    ///   caller:    distribute_direct(caller, asset, vault, recipients)
+    ///                ├─ asset.transfer(caller, r1, 600)
+    ///                └─ asset.transfer(caller, r2, 400)
+    ///   r1 / r2:   vault.deposit([amount], [amount], r, true)
+    #[test]
+    fn test_distribute_direct_with_recipient_auth_passes() {
+        let env = Env::default();
+        let (asset_id, admin, vault_id, client) = setup_auth(&env);
+        let distributor_id = client.address.clone();
+        let vault = MockVaultClient::new(&env, &vault_id);
+
+        let caller = Address::generate(&env);
+        let r1 = Address::generate(&env);
+        let r2 = Address::generate(&env);
+        mint(&env, &asset_id, &admin, &caller, 1000_i128);
+
+        let recipients = vec![
+            &env,
+            Recipient { address: r1.clone(), amount: 600_i128 },
+            Recipient { address: r2.clone(), amount: 400_i128 },
+        ];
+
+        env.mock_auths(&[
+            MockAuth {
+                address: &caller,
+                invoke: &MockAuthInvoke {
+                    contract: &distributor_id,
+                    fn_name: "distribute_direct",
+                    args: (caller.clone(), asset_id.clone(), vault_id.clone(), recipients.clone())
+                        .into_val(&env),
+                    sub_invokes: &[
+                        MockAuthInvoke {
+                            contract: &asset_id,
+                            fn_name: "transfer",
+                            args: (caller.clone(), r1.clone(), 600_i128).into_val(&env),
+                            sub_invokes: &[],
+                        },
+                        MockAuthInvoke {
+                            contract: &asset_id,
+                            fn_name: "transfer",
+                            args: (caller.clone(), r2.clone(), 400_i128).into_val(&env),
+                            sub_invokes: &[],
+                        },
+                    ],
+                },
+            },
+            MockAuth {
+                address: &r1,
+                invoke: &MockAuthInvoke {
+                    contract: &vault_id,
+                    fn_name: "deposit",
+                    args: (vec![&env, 600_i128], vec![&env, 600_i128], r1.clone(), true)
+                        .into_val(&env),
+                    sub_invokes: &[],
+                },
+            },
+            MockAuth {
+                address: &r2,
+                invoke: &MockAuthInvoke {
+                    contract: &vault_id,
+                    fn_name: "deposit",
+                    args: (vec![&env, 400_i128], vec![&env, 400_i128], r2.clone(), true)
+                        .into_val(&env),
+                    sub_invokes: &[],
+                },
+            },
+        ]);
+
+        let results = client.distribute_direct(&caller, &asset_id, &vault_id, &recipients);
+
+        assert_eq!(results, vec![&env, (r1.clone(), 600_i128), (r2.clone(), 400_i128)]);
+        assert_eq!(vault.balance(&r1), 600_i128);
+        assert_eq!(vault.balance(&r2), 400_i128);
+    }
+
+    /// Without the recipients' own deposit auth, `distribute_direct` fails at
+    /// the first vault deposit even though `caller`'s tree is complete.
+    #[test]
+    #[should_panic]
+    fn test_distribute_direct_without_recipient_auth_fails() {
+        let env = Env::default();
+        let (asset_id, admin, vault_id, client) = setup_auth(&env);
+        let distributor_id = client.address.clone();
+
+        let caller = Address::generate(&env);
+        let r1 = Address::generate(&env);
+        mint(&env, &asset_id, &admin, &caller, 600_i128);
+
+        let recipients = vec![&env, Recipient { address: r1.clone(), amount: 600_i128 }];
+
+        env.mock_auths(&[MockAuth {
+            address: &caller,
+            invoke: &MockAuthInvoke {
+                contract: &distributor_id,
+                fn_name: "distribute_direct",
+                args: (caller.clone(), asset_id.clone(), vault_id.clone(), recipients.clone())
+                    .into_val(&env),
+                sub_invokes: &[MockAuthInvoke {
+                    contract: &asset_id,
+                    fn_name: "transfer",
+                    args: (caller.clone(), r1.clone(), 600_i128).into_val(&env),
+                    sub_invokes: &[],
+                }],
+            },
+        }]);
+
+        // Panics: r1.require_auth() inside the vault deposit has no entry.
+        client.distribute_direct(&caller, &asset_id, &vault_id, &recipients);
+    }
 }
+
@@ -0,0 +1,48 @@
+//! Property checks for the pro-rata floor/remainder split shared by every
+//! distribution mode.
+
+use soroban_sdk::Vec;
+
+/// Asserts the core split invariant for `shares` paid against `weights`
+/// (underlying amounts, tenures, bps, ...) out of `df_minted` df tokens:
+///
+/// - `sum(shares) == df_minted`: nothing is lost or created
+/// - every non-last share equals `floor(weight * df_minted / sum(weights))`
+/// - the last share, which absorbs the remainder, is never negative
+pub fn assert_floor_remainder(weights: &Vec<i128>, df_minted: i128, shares: &Vec<i128>) {
+    assert_eq!(weights.len(), shares.len(), "one share per weight");
+    assert!(!weights.is_empty(), "no weights");
+
+    let total: i128 = weights.iter().sum();
+    let n = weights.len();
+    let mut sum: i128 = 0;
+    for (i, (weight, share)) in weights.iter().zip(shares.iter()).enumerate() {
+        if (i as u32) + 1 < n {
+            assert_eq!(
+                share,
+                weight * df_minted / total,
+                "share {} is not floor({} * {} / {})", i, weight, df_minted, total
+            );
+        } else {
+            assert!(share >= 0, "last share {} is negative", share);
+        }
+        sum += share;
+    }
+    assert_eq!(sum, df_minted, "shares must sum to df_minted");
+}
+
+/// Small deterministic linear congruential generator, so "random" invariant
+/// cases are reproducible run to run.
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg(seed)
+    }
+
+    /// Next value in `lo..=hi`.
+    pub fn next_in(&mut self, lo: i128, hi: i128) -> i128 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        lo + ((self.0 >> 33) as i128) % (hi - lo + 1)
+    }
+}
@@ -13,6 +13,7 @@
 //! 7. Distributor contract ready for testing
 
 pub mod blend_setup;
+pub mod invariants;
 pub mod soroswap_setup;
 
 pub use blend_setup::{
@@ -21,6 +22,7 @@ pub use blend_setup::{
 };
 pub use soroswap_setup::{
     create_soroswap_factory, create_soroswap_pool, create_soroswap_router,
+    SoroswapRouterClient,
 };
 
 use soroban_sdk::{
@@ -133,10 +135,16 @@ pub struct DistributorTestFixture<'a> {
     /// USDC admin client – use `.mint(to, amount)` in tests.
     pub usdc_admin: StellarAssetClient<'a>,
     /// BLND reward token (needed for Blend internals; rarely used directly).
+    pub blnd: TokenClient<'a>,
+    /// BLND admin client.
     pub blnd_admin: StellarAssetClient<'a>,
     /// XLM collateral token (needed for the Blend pool second reserve).
     pub xlm_admin: StellarAssetClient<'a>,
 
+    // ── Soroswap ──
+    /// Router with a seeded BLND/USDC pair.
+    pub soroswap_router: SoroswapRouterClient<'a>,
+
     // ── Blend ──
     /// The Blend lending pool that the strategy deposits into.
     pub blend_pool: BlendPoolClient<'a>,
@@ -160,7 +168,7 @@ pub struct DistributorTestFixture<'a> {
     pub keeper: Address,
 
     // ── Common admin ──
-    /// Deployer of Blend, factory, and token admin.
+    /// Deployer of Blend, factory, token admin, and distributor admin.
     pub admin: Address,
 
     // ── Distributor ──
@@ -319,15 +327,17 @@ impl<'a> DistributorTestFixture<'a> {
         env.cost_estimate().budget().reset_unlimited();
 
         // ── Distributor contract ────────────────────────────────────────────────
-        let distributor_addr = env.register(crate::Distributor, ());
+        let distributor_addr = env.register(crate::Distributor, (admin.clone(),));
         let distributor = crate::DistributorClient::new(&env, &distributor_addr);
 
         DistributorTestFixture {
             env,
             usdc,
             usdc_admin,
+            blnd,
             blnd_admin,
             xlm_admin,
+            soroswap_router,
             blend_pool,
             strategy,
             vault,
@@ -68,14 +68,18 @@ fi
 echo "Downloaded: $(basename "$WASM_FILE")"
 
 # ── Deploy contract ──
-echo "Deploying to $NETWORK..."
+# The deploying identity becomes the distributor admin (constructor argument).
+ADMIN_ADDRESS="$(stellar keys address "$IDENTITY")"
+echo "Deploying to $NETWORK (admin: $ADMIN_ADDRESS)..."
 MAX_ATTEMPTS=3
 CONTRACT_ID=""
 for attempt in $(seq 1 $MAX_ATTEMPTS); do
   CONTRACT_ID=$(stellar contract deploy \
     --wasm "$WASM_FILE" \
     --source-account "$IDENTITY" \
-    --network "$NETWORK") && break
+    --network "$NETWORK" \
+    -- \
+    --admin "$ADMIN_ADDRESS") && break
   echo "Attempt $attempt/$MAX_ATTEMPTS failed."
   if [[ $attempt -lt $MAX_ATTEMPTS ]]; then
     echo "Retrying in 5s..."